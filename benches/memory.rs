@@ -0,0 +1,197 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ECE522_project::AVL::{AvlTree, AvlTreeNode};
+use ECE522_project::BST::{BinarySearchTree, Node};
+use ECE522_project::RBTree::RBTree;
+use ECE522_project::workload::Rng;
+
+// Memory used by each tree, measured with an allocator that counts every allocation.
+// Run with `cargo bench --bench memory`.
+//
+//     bytes/key     heap bytes held by a tree of n keys, divided by n
+//     allocs/key    live heap allocations of that tree, divided by n
+//     churn peak    the most heap bytes held at any point while inserting a new key and deleting an old one
+//                   n times, divided by n
+//     after drop    heap bytes still held after the tree was dropped (memory that is never freed)
+//
+// The keys are the benchmark sizes of `my_benchmark.rs` inserted in a random order with a fixed seed.
+// BTreeSet is there for reference.
+
+const SEED: u64 = 522;
+
+struct CountingAllocator;
+
+// heap bytes in use right now and the most there were since the last `reset_peak`
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+// allocations that were not freed yet
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+
+fn grow(size: usize) {
+    let now = CURRENT.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(now, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            grow(layout.size());
+            LIVE.fetch_add(1, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+        LIVE.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+            grow(new_size);
+        }
+        new_ptr
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn reset_peak() {
+    PEAK.store(CURRENT.load(Ordering::Relaxed), Ordering::Relaxed);
+}
+
+// What the measurements need from a tree
+trait MemoryTree {
+    fn empty() -> Self;
+    fn insert(&mut self, key: u32);
+    fn delete(&mut self, key: u32);
+}
+
+impl MemoryTree for AvlTreeNode<u32> {
+    fn empty() -> Self {
+        AvlTree::generate_empty_tree()
+    }
+
+    fn insert(&mut self, key: u32) {
+        self.insert_node(key);
+    }
+
+    fn delete(&mut self, key: u32) {
+        self.remove_node(&key);
+    }
+}
+
+impl MemoryTree for RBTree<u32> {
+    fn empty() -> Self {
+        RBTree::new()
+    }
+
+    fn insert(&mut self, key: u32) {
+        self.insert_node(key);
+    }
+
+    fn delete(&mut self, key: u32) {
+        let _ = RBTree::delete(self, key);
+    }
+}
+
+impl MemoryTree for Node<u32> {
+    fn empty() -> Self {
+        Node::gen_empty_tree()
+    }
+
+    fn insert(&mut self, key: u32) {
+        self.insert_node(key);
+    }
+
+    fn delete(&mut self, key: u32) {
+        self.delete_node(key);
+    }
+}
+
+impl MemoryTree for BTreeSet<u32> {
+    fn empty() -> Self {
+        BTreeSet::new()
+    }
+
+    fn insert(&mut self, key: u32) {
+        BTreeSet::insert(self, key);
+    }
+
+    fn delete(&mut self, key: u32) {
+        self.remove(&key);
+    }
+}
+
+struct Footprint {
+    bytes: usize,
+    allocations: usize,
+    churn_peak: usize,
+    after_drop: usize,
+}
+
+// `keys` are inserted, then every (insert, delete) pair of `churn` is applied
+fn measure<T: MemoryTree>(keys: &[u32], churn: &[(u32, u32)]) -> Footprint {
+    let start_bytes = CURRENT.load(Ordering::Relaxed);
+    let start_live = LIVE.load(Ordering::Relaxed);
+
+    let mut tree = T::empty();
+    for &key in keys {
+        tree.insert(key);
+    }
+    let bytes = CURRENT.load(Ordering::Relaxed) - start_bytes;
+    let allocations = LIVE.load(Ordering::Relaxed) - start_live;
+
+    reset_peak();
+    for &(insert, delete) in churn {
+        tree.insert(insert);
+        tree.delete(delete);
+    }
+    let churn_peak = PEAK.load(Ordering::Relaxed) - start_bytes;
+
+    drop(tree);
+    let after_drop = CURRENT.load(Ordering::Relaxed) - start_bytes;
+    Footprint { bytes, allocations, churn_peak, after_drop }
+}
+
+fn print_row(name: &str, size: usize, f: &Footprint) {
+    println!(
+        "{:<10} {:>8} {:>10.1} {:>11.2} {:>12.1} {:>14}",
+        name,
+        size,
+        f.bytes as f64 / size as f64,
+        f.allocations as f64 / size as f64,
+        f.churn_peak as f64 / size as f64,
+        f.after_drop
+    );
+}
+
+fn main() {
+    println!(
+        "{:<10} {:>8} {:>10} {:>11} {:>12} {:>14}",
+        "tree", "size", "bytes/key", "allocs/key", "churn peak", "after drop (B)"
+    );
+    for &size in [10000, 40000, 70000, 100000, 130000].iter() {
+        // all the keys are made before anything is measured, the even keys go in, the odd ones come with churn
+        let mut rng = Rng::new(SEED);
+        let mut keys: Vec<u32> = (0..size as u32).map(|k| 2 * k).collect();
+        rng.shuffle(&mut keys);
+        let mut inserts: Vec<u32> = (0..size as u32).map(|k| 2 * k + 1).collect();
+        rng.shuffle(&mut inserts);
+        let mut deletes = keys.clone();
+        rng.shuffle(&mut deletes);
+        let churn: Vec<(u32, u32)> = inserts.into_iter().zip(deletes).collect();
+
+        print_row("avltree", size, &measure::<AvlTreeNode<u32>>(&keys, &churn));
+        print_row("rbtree", size, &measure::<RBTree<u32>>(&keys, &churn));
+        print_row("bstree", size, &measure::<Node<u32>>(&keys, &churn));
+        print_row("btreeset", size, &measure::<BTreeSet<u32>>(&keys, &churn));
+    }
+}
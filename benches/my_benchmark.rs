@@ -1,3 +1,5 @@
+use std::collections::BTreeSet;
+
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
 use ECE522_project::AVL::{AvlTree, AvlTreeNode};
 use ECE522_project::RBTree::RBTree;
@@ -21,7 +23,7 @@ pub fn test_avl_tree(tree_size: i32) {
     }
     let end = tree_size / 10;
     for j in 0..end {
-        avl_tree.exist_or_not(j);
+        avl_tree.exist_or_not(&j);
     }
 }
 
@@ -38,6 +40,33 @@ pub fn test_BST(tree_size: i32) {
     }
 }
 
+// The same workload on the standard library, to see what the trees of this crate are worth.
+// The lookups go through black_box, nothing else is done with their result and it could be optimized away.
+pub fn test_btree_set(tree_size: i32) {
+    let mut set = BTreeSet::new();
+    for i in 0..tree_size {
+        set.insert(i);
+    }
+    let end = tree_size / 10;
+    for j in 0..end {
+        black_box(set.contains(&j));
+    }
+}
+
+// A sorted Vec with binary search, every insert shifts the bigger keys (none here, the keys come in order)
+pub fn test_sorted_vec(tree_size: i32) {
+    let mut vec = Vec::new();
+    for i in 0..tree_size {
+        if let Err(pos) = vec.binary_search(&i) {
+            vec.insert(pos, i);
+        }
+    }
+    let end = tree_size / 10;
+    for j in 0..end {
+        black_box(vec.binary_search(&j).is_ok());
+    }
+}
+
 fn criterion_benchmark_rbtree(c: &mut Criterion) {
     let mut group = c.benchmark_group("rbtree");
     for size in [10000, 40000, 70000, 100000, 130000].iter() {
@@ -80,5 +109,34 @@ fn criterion_benchmark_bstree(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, criterion_benchmark_avltree,criterion_benchmark_rbtree,criterion_benchmark_bstree);
+fn criterion_benchmark_btreeset(c: &mut Criterion) {
+    let mut group = c.benchmark_group("btreeset");
+    for size in [10000, 40000, 70000, 100000, 130000].iter() {
+        group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, &size| {
+            b.iter(|| {
+                test_btree_set(size);
+            }
+            )
+        },
+        );
+    }
+    group.finish();
+}
+
+fn criterion_benchmark_sortedvec(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sortedvec");
+    for size in [10000, 40000, 70000, 100000, 130000].iter() {
+        group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, &size| {
+            b.iter(|| {
+                test_sorted_vec(size);
+            }
+            )
+        },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark_avltree,criterion_benchmark_rbtree,criterion_benchmark_bstree,
+                 criterion_benchmark_btreeset,criterion_benchmark_sortedvec);
 criterion_main!(benches);
@@ -0,0 +1,73 @@
+use ECE522_project::AVL::{AvlTree, AvlTreeNode};
+use ECE522_project::BST::{BinarySearchTree, Node};
+use ECE522_project::RBTree::RBTree;
+use ECE522_project::stats::{self, OpStats};
+
+// Algorithmic cost of the workloads in `my_benchmark.rs`: insert 0..n, then search the first n/10 keys.
+// Counts do not depend on the machine, so the trees can be compared without timing noise.
+// Run with `cargo bench --bench op_costs`.
+
+fn avl_workload(tree_size: i32) -> OpStats {
+    let mut avl_tree: AvlTreeNode<_> = AvlTree::generate_empty_tree();
+    let (_, res) = stats::measure(|| {
+        for i in 0..tree_size {
+            avl_tree.insert_node(i);
+        }
+        for j in 0..tree_size / 10 {
+            avl_tree.exist_or_not(&j);
+        }
+    });
+    res
+}
+
+fn rb_workload(tree_size: i32) -> OpStats {
+    let mut rb_tree = RBTree::new();
+    let (_, res) = stats::measure(|| {
+        for i in 0..tree_size {
+            rb_tree.insert_node(i as u32);
+        }
+        for j in 0..tree_size / 10 {
+            let _ = rb_tree.search_node(j as u32);
+        }
+    });
+    res
+}
+
+fn bst_workload(tree_size: i32) -> OpStats {
+    let mut bs_tree: Node<_> = Node::gen_empty_tree();
+    let (_, res) = stats::measure(|| {
+        for i in 0..tree_size {
+            bs_tree.insert_node(i);
+        }
+        for j in 0..tree_size / 10 {
+            bs_tree.search_node(j);
+        }
+    });
+    res
+}
+
+fn print_row(name: &str, size: i32, s: &OpStats) {
+    println!(
+        "{:<8} {:>8} {:>10} {:>12.2} {:>10.2} {:>12.2} {:>10.2} {:>9}",
+        name,
+        size,
+        s.operations,
+        s.per_operation(s.comparisons),
+        s.per_operation(s.rotations),
+        s.per_operation(s.recolorings),
+        s.per_operation(s.allocations),
+        s.max_path_length
+    );
+}
+
+fn main() {
+    println!(
+        "{:<8} {:>8} {:>10} {:>12} {:>10} {:>12} {:>10} {:>9}",
+        "tree", "size", "ops", "compares/op", "rotate/op", "recolor/op", "alloc/op", "max path"
+    );
+    for size in [10000, 40000, 70000, 100000, 130000].iter() {
+        print_row("avltree", *size, &avl_workload(*size));
+        print_row("rbtree", *size, &rb_workload(*size));
+        print_row("bstree", *size, &bst_workload(*size));
+    }
+}
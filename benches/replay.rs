@@ -0,0 +1,140 @@
+use std::collections::BTreeSet;
+use std::time::Instant;
+
+use criterion::{BatchSize, BenchmarkId, Criterion};
+use ECE522_project::oplog::{self, Op, Replay};
+use ECE522_project::workload::{Distribution, KeyGenerator, Rng};
+use ECE522_project::AVL::{AvlTree, AvlTreeNode};
+use ECE522_project::BST::{BinarySearchTree, Node};
+use ECE522_project::RBTree::RBTree;
+
+// Replay an operation log (see oplog.rs) against every tree, starting from an empty tree
+//     OPLOG=my_run.oplog cargo bench --bench replay
+// Without OPLOG a generated log is used: 100000 operations on zipf distributed keys,
+// about 60% searches, 25% inserts and 15% deletes.
+//
+// First the latency of every single operation is measured and printed as percentiles per tree and
+// operation type, then criterion times the whole log per tree (group `replay`).
+
+const SAMPLE_OPS: usize = 100_000;
+const SEED: u64 = 522;
+
+fn sample_log() -> Vec<Op> {
+    let mut keys = KeyGenerator::new(Distribution::Zipf, SAMPLE_OPS, SEED);
+    let mut rng = Rng::new(SEED);
+    (0..SAMPLE_OPS).map(|_| {
+        let key = keys.next_key();
+        match rng.below(100) {
+            0..=59 => Op::Search(key),
+            60..=84 => Op::Insert(key),
+            _ => Op::Delete(key),
+        }
+    }).collect()
+}
+
+// Nanoseconds of every operation of the log, by operation type
+fn latencies<T: Replay>(mut tree: T, ops: &[Op]) -> [Vec<u64>; 3] {
+    let mut times = [Vec::new(), Vec::new(), Vec::new()];
+    for &op in ops {
+        let start = Instant::now();
+        tree.apply(op);
+        let elapsed = start.elapsed().as_nanos() as u64;
+        let kind = match op {
+            Op::Insert(_) => 0,
+            Op::Delete(_) => 1,
+            Op::Search(_) => 2,
+        };
+        times[kind].push(elapsed);
+    }
+    times
+}
+
+// `sorted` must be sorted, nearest-rank percentile
+fn percentile(sorted: &[u64], p: f64) -> u64 {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.max(1) - 1]
+}
+
+fn print_latencies<T: Replay>(name: &str, tree: T, ops: &[Op]) {
+    let names = ["insert", "delete", "search"];
+    for (kind, mut times) in latencies(tree, ops).iter().cloned().enumerate() {
+        if times.is_empty() {
+            continue;
+        }
+        times.sort_unstable();
+        println!(
+            "{:<9} {:<7} {:>8} {:>8} {:>8} {:>8} {:>8} {:>10}",
+            name,
+            names[kind],
+            times.len(),
+            percentile(&times, 50.0),
+            percentile(&times, 90.0),
+            percentile(&times, 99.0),
+            percentile(&times, 99.9),
+            times[times.len() - 1]
+        );
+    }
+}
+
+fn replay_benchmark(c: &mut Criterion, ops: &[Op]) {
+    let mut group = c.benchmark_group("replay");
+    group.bench_function(BenchmarkId::from_parameter("avltree"), |b| {
+        b.iter_batched(|| -> AvlTreeNode<u64> { AvlTree::generate_empty_tree() }, |mut tree| {
+            ops.iter().for_each(|&op| { tree.apply(op); });
+            tree
+        }, BatchSize::SmallInput)
+    });
+    group.bench_function(BenchmarkId::from_parameter("rbtree"), |b| {
+        b.iter_batched(RBTree::<u64>::new, |mut tree| {
+            ops.iter().for_each(|&op| { tree.apply(op); });
+            tree
+        }, BatchSize::SmallInput)
+    });
+    group.bench_function(BenchmarkId::from_parameter("bstree"), |b| {
+        b.iter_batched(|| -> Node<u64> { Node::gen_empty_tree() }, |mut tree| {
+            ops.iter().for_each(|&op| { tree.apply(op); });
+            tree
+        }, BatchSize::SmallInput)
+    });
+    group.bench_function(BenchmarkId::from_parameter("btreeset"), |b| {
+        b.iter_batched(BTreeSet::<u64>::new, |mut tree| {
+            ops.iter().for_each(|&op| { tree.apply(op); });
+            tree
+        }, BatchSize::SmallInput)
+    });
+    group.finish();
+}
+
+fn main() {
+    let ops = match std::env::var("OPLOG") {
+        Ok(path) => match oplog::load(&path) {
+            Ok(ops) => {
+                println!("Replaying {} operations from {}", ops.len(), path);
+                ops
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        },
+        Err(_) => {
+            println!("Replaying {} generated operations (set OPLOG=<file> to replay a recorded log)", SAMPLE_OPS);
+            sample_log()
+        }
+    };
+
+    println!(
+        "{:<9} {:<7} {:>8} {:>8} {:>8} {:>8} {:>8} {:>10}",
+        "tree", "op", "count", "p50 ns", "p90 ns", "p99 ns", "p99.9 ns", "max ns"
+    );
+    let avl_tree: AvlTreeNode<u64> = AvlTree::generate_empty_tree();
+    print_latencies("avltree", avl_tree, &ops);
+    print_latencies("rbtree", RBTree::<u64>::new(), &ops);
+    let bs_tree: Node<u64> = Node::gen_empty_tree();
+    print_latencies("bstree", bs_tree, &ops);
+    print_latencies("btreeset", BTreeSet::<u64>::new(), &ops);
+
+    let mut c = Criterion::default().configure_from_args();
+    replay_benchmark(&mut c, &ops);
+    c.final_summary();
+}
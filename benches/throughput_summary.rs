@@ -0,0 +1,61 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use ECE522_project::json::JsonValue;
+
+// Relative throughput of the `my_benchmark` groups, read from the results criterion saved.
+// Run `cargo bench --bench my_benchmark` first, then `cargo bench --bench throughput_summary`.
+// Every cell is how many times faster than BTreeSet the tree runs the workload of that size
+// (2.00x = twice the throughput of BTreeSet, 0.50x = half of it).
+
+const GROUPS: [&str; 5] = ["avltree", "rbtree", "bstree", "btreeset", "sortedvec"];
+const BASELINE: &str = "btreeset";
+const SIZES: [i32; 5] = [10000, 40000, 70000, 100000, 130000];
+
+fn criterion_dir() -> PathBuf {
+    let target = match env::var_os("CARGO_TARGET_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target"),
+    };
+    target.join("criterion")
+}
+
+// Mean time of one run of the workload in nanoseconds, None if that benchmark has not been run
+fn mean_time(group: &str, size: i32) -> Option<f64> {
+    let path = criterion_dir().join(group).join(size.to_string()).join("new").join("estimates.json");
+    let estimates = JsonValue::parse(&fs::read_to_string(path).ok()?).ok()?;
+    match estimates.get("mean")?.get("point_estimate")? {
+        JsonValue::Number(time) => time.parse().ok(),
+        _ => None,
+    }
+}
+
+fn main() {
+    if GROUPS.iter().all(|group| SIZES.iter().all(|&size| mean_time(group, size).is_none())) {
+        println!("No results found in {}, run `cargo bench --bench my_benchmark` first.", criterion_dir().display());
+        return;
+    }
+    println!("Throughput relative to {} (insert 0..n, then search the first n/10 keys)", BASELINE);
+    print!("{:>8}", "size");
+    for group in GROUPS.iter() {
+        print!(" {:>10}", group);
+    }
+    println!(" {:>14}", "btreeset time");
+    for &size in SIZES.iter() {
+        print!("{:>8}", size);
+        let baseline = mean_time(BASELINE, size);
+        for group in GROUPS.iter() {
+            match (baseline, mean_time(group, size)) {
+                // small ratios get more digits, the plain BST is a list here and thousands of times slower
+                (Some(baseline), Some(time)) if baseline / time < 0.1 => print!(" {:>9.4}x", baseline / time),
+                (Some(baseline), Some(time)) => print!(" {:>9.2}x", baseline / time),
+                _ => print!(" {:>10}", "-"),
+            }
+        }
+        match baseline {
+            Some(time) => println!(" {:>11.3} ms", time / 1e6),
+            None => println!(" {:>14}", "-"),
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use criterion::measurement::WallTime;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkGroup, BenchmarkId, Criterion};
+use ECE522_project::AVL::{AvlTree, AvlTreeNode};
+use ECE522_project::BST::{BinarySearchTree, Node};
+use ECE522_project::RBTree::RBTree;
+use ECE522_project::workload::Rng;
+
+// Timing of the common workloads for the AVL, red-black and plain binary search trees:
+//     insert_random       insert n keys in a random order
+//     insert_descending   insert n, n-1, ..., 1 (the plain BST degenerates into a list)
+//     lookup_hit          look up every key of a tree of n keys, in a random order
+//     lookup_miss         look up n keys that are not in the tree
+//     churn               on a tree of n keys, insert a new key and delete an old one n times
+//     update              replace every key of a tree of n keys with a key that is not in it
+//     traversal           in-order traversal of a tree of n keys
+//     drop                drop a tree of n keys
+// The keys come from `workload::Rng` with a fixed seed, so every run measures exactly the same work.
+// Run with `cargo bench --bench workloads`, or e.g. `cargo bench --bench workloads -- lookup` for one group.
+
+const SEED: u64 = 522;
+const SIZES: [usize; 3] = [1_000, 10_000, 50_000];
+// the plain BST recurses once per level, and sorted keys make it n levels deep
+const SORTED_SIZES: [usize; 3] = [1_000, 5_000, 10_000];
+
+// What the benchmarks need from a tree, so every workload is written once
+trait BenchTree: Sized {
+    fn empty() -> Self;
+    fn insert(&mut self, key: u32);
+    fn delete(&mut self, key: u32);
+    fn contains(&mut self, key: u32) -> bool;
+    fn update(&mut self, old: u32, new: u32);
+    // the number of keys the in-order traversal visits, the traversal itself is what is timed
+    fn in_order(&mut self) -> usize;
+
+    fn build(keys: &[u32]) -> Self {
+        let mut tree = Self::empty();
+        for &key in keys {
+            tree.insert(key);
+        }
+        tree
+    }
+}
+
+impl BenchTree for AvlTreeNode<u32> {
+    fn empty() -> Self {
+        AvlTree::generate_empty_tree()
+    }
+
+    fn insert(&mut self, key: u32) {
+        self.insert_node(key);
+    }
+
+    fn delete(&mut self, key: u32) {
+        self.remove_node(&key);
+    }
+
+    fn contains(&mut self, key: u32) -> bool {
+        self.exist_or_not(&key)
+    }
+
+    fn update(&mut self, old: u32, new: u32) {
+        // update_node without its messages
+        self.replace_node(&old, new);
+    }
+
+    fn in_order(&mut self) -> usize {
+        self.in_order_traverse().len()
+    }
+}
+
+impl BenchTree for RBTree<u32> {
+    fn empty() -> Self {
+        RBTree::new()
+    }
+
+    fn insert(&mut self, key: u32) {
+        self.insert_node(key);
+    }
+
+    fn delete(&mut self, key: u32) {
+        let _ = RBTree::delete(self, key);
+    }
+
+    fn contains(&mut self, key: u32) -> bool {
+        self.exist_or_not(key)
+    }
+
+    fn update(&mut self, old: u32, new: u32) {
+        // update_node without its messages
+        self.replace_node(old, new);
+    }
+
+    fn in_order(&mut self) -> usize {
+        self.print_in_order_traversal().len()
+    }
+}
+
+impl BenchTree for Node<u32> {
+    fn empty() -> Self {
+        Node::gen_empty_tree()
+    }
+
+    fn insert(&mut self, key: u32) {
+        self.insert_node(key);
+    }
+
+    fn delete(&mut self, key: u32) {
+        self.delete_node(key);
+    }
+
+    fn contains(&mut self, key: u32) -> bool {
+        self.search_node(key)
+    }
+
+    fn update(&mut self, old: u32, new: u32) {
+        // the plain BST has no update, it is a delete and an insert
+        if self.delete_node(old) {
+            self.insert_node(new);
+        }
+    }
+
+    fn in_order(&mut self) -> usize {
+        self.in_order_traverse().len()
+    }
+}
+
+// The even keys 0, 2, ..., 2n - 2 in a random order, the tree is built from these
+fn present_keys(n: usize) -> Vec<u32> {
+    let mut keys: Vec<u32> = (0..n as u32).map(|k| 2 * k).collect();
+    Rng::new(SEED).shuffle(&mut keys);
+    keys
+}
+
+// The odd keys 1, 3, ..., 2n - 1 in a random order, none of them is in the tree
+fn missing_keys(n: usize) -> Vec<u32> {
+    let mut keys: Vec<u32> = (0..n as u32).map(|k| 2 * k + 1).collect();
+    Rng::new(SEED + 1).shuffle(&mut keys);
+    keys
+}
+
+fn insert_random<T: BenchTree>(group: &mut BenchmarkGroup<WallTime>, name: &str, n: usize) {
+    let keys = present_keys(n);
+    // dropping the tree is measured in its own group
+    group.bench_with_input(BenchmarkId::new(name, n), &keys, |b, keys| b.iter_with_large_drop(|| T::build(keys)));
+}
+
+fn insert_descending<T: BenchTree>(group: &mut BenchmarkGroup<WallTime>, name: &str, n: usize) {
+    let keys: Vec<u32> = (1..=n as u32).rev().collect();
+    group.bench_with_input(BenchmarkId::new(name, n), &keys, |b, keys| b.iter_with_large_drop(|| T::build(keys)));
+}
+
+fn lookup_hit<T: BenchTree>(group: &mut BenchmarkGroup<WallTime>, name: &str, n: usize) {
+    let keys = present_keys(n);
+    let mut tree = T::build(&keys);
+    let mut lookups = keys.clone();
+    Rng::new(SEED + 2).shuffle(&mut lookups);
+    group.bench_with_input(BenchmarkId::new(name, n), &lookups, |b, lookups| {
+        b.iter(|| lookups.iter().filter(|&&key| tree.contains(black_box(key))).count())
+    });
+}
+
+fn lookup_miss<T: BenchTree>(group: &mut BenchmarkGroup<WallTime>, name: &str, n: usize) {
+    let mut tree = T::build(&present_keys(n));
+    let lookups = missing_keys(n);
+    group.bench_with_input(BenchmarkId::new(name, n), &lookups, |b, lookups| {
+        b.iter(|| lookups.iter().filter(|&&key| tree.contains(black_box(key))).count())
+    });
+}
+
+fn churn<T: BenchTree>(group: &mut BenchmarkGroup<WallTime>, name: &str, n: usize) {
+    let keys = present_keys(n);
+    // insert a new key, delete one of the first keys, and so on, the size of the tree stays about n
+    let mut deletes = keys.clone();
+    Rng::new(SEED + 3).shuffle(&mut deletes);
+    let ops: Vec<(u32, u32)> = missing_keys(n).into_iter().zip(deletes).collect();
+    group.bench_with_input(BenchmarkId::new(name, n), &ops, |b, ops| {
+        b.iter_batched(
+            || T::build(&keys),
+            |mut tree| {
+                for &(insert, delete) in ops {
+                    tree.insert(insert);
+                    tree.delete(delete);
+                }
+                tree
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn update<T: BenchTree>(group: &mut BenchmarkGroup<WallTime>, name: &str, n: usize) {
+    let keys = present_keys(n);
+    let ops: Vec<(u32, u32)> = keys.iter().copied().zip(missing_keys(n)).collect();
+    group.bench_with_input(BenchmarkId::new(name, n), &ops, |b, ops| {
+        b.iter_batched(
+            || T::build(&keys),
+            |mut tree| {
+                for &(old, new) in ops {
+                    tree.update(old, new);
+                }
+                tree
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn traversal<T: BenchTree>(group: &mut BenchmarkGroup<WallTime>, name: &str, n: usize) {
+    let mut tree = T::build(&present_keys(n));
+    group.bench_function(BenchmarkId::new(name, n), |b| b.iter(|| tree.in_order()));
+}
+
+fn drop_tree<T: BenchTree>(group: &mut BenchmarkGroup<WallTime>, name: &str, n: usize) {
+    let keys = present_keys(n);
+    group.bench_function(BenchmarkId::new(name, n), |b| {
+        b.iter_batched(|| T::build(&keys), drop, BatchSize::LargeInput)
+    });
+}
+
+// One group with the three trees at every size, `$workload` is one of the functions above
+macro_rules! bench_trees {
+    ($c:expr, $group:expr, $sizes:expr, $workload:ident) => {{
+        let mut group = $c.benchmark_group($group);
+        for &n in $sizes.iter() {
+            $workload::<AvlTreeNode<u32>>(&mut group, "avltree", n);
+            $workload::<RBTree<u32>>(&mut group, "rbtree", n);
+            $workload::<Node<u32>>(&mut group, "bstree", n);
+        }
+        group.finish();
+    }};
+}
+
+fn criterion_benchmark_insert(c: &mut Criterion) {
+    bench_trees!(c, "insert_random", SIZES, insert_random);
+    bench_trees!(c, "insert_descending", SORTED_SIZES, insert_descending);
+}
+
+fn criterion_benchmark_lookup(c: &mut Criterion) {
+    bench_trees!(c, "lookup_hit", SIZES, lookup_hit);
+    bench_trees!(c, "lookup_miss", SIZES, lookup_miss);
+}
+
+fn criterion_benchmark_changes(c: &mut Criterion) {
+    bench_trees!(c, "churn", SIZES, churn);
+    bench_trees!(c, "update", SIZES, update);
+}
+
+fn criterion_benchmark_whole_tree(c: &mut Criterion) {
+    bench_trees!(c, "traversal", SIZES, traversal);
+    bench_trees!(c, "drop", SIZES, drop_tree);
+}
+
+criterion_group!(
+    benches,
+    criterion_benchmark_insert,
+    criterion_benchmark_lookup,
+    criterion_benchmark_changes,
+    criterion_benchmark_whole_tree
+);
+criterion_main!(benches);
@@ -1,15 +1,26 @@
 use core::cmp::{max, Ordering};
+use core::iter::repeat_n;
 use core::mem::swap;
+use std::borrow::Borrow;
 use std::fmt::{Debug};
 
+use crate::codec::{self, BinaryKey, ByteReader, CodecError, TreeCodec};
+use crate::json::{self, JsonError, JsonKey, JsonValue, TreeJson};
+use crate::order::{Comparator, Natural};
+use crate::stats;
+use crate::trace::{self, DebugKeys, HiddenKeys, KeyText, TraceEvent};
 use DeleteValue::*;
 use InnerResult::*;
 
 pub type AvlTreeNode<T> = Option<Box<TreeNode<T>>>;
 
 #[derive(Clone, Debug)]
-pub struct TreeNode<T: PartialOrd> {
+pub struct TreeNode<T> {
     val: T,
+    // how many times val was inserted, always 1 outside of AvlMultiset
+    count: usize,
+    // sum of the counts in this subtree, lets AvlMultiset find ranks without visiting every node
+    size: usize,
     height: i32,
     left: AvlTreeNode<T>,
     right: AvlTreeNode<T>,
@@ -23,67 +34,76 @@ enum InnerResult {
     Unknown,
     // Unknown about balance
     Balanced,  // Tree Balanced
+    Duplicate,  // the value is already in the tree, nothing changed
 }
 
-enum DeleteValue<T: PartialOrd> {
+// `Q` is what the wanted key is given as, `T` itself or a form it borrows as (e.g. `str` for `String`)
+enum DeleteValue<'a, T, Q: ?Sized = T> {
     Min,
     // Minimum node
     Max,
     // Maximum node
-    Val(T),
+    Val(&'a Q),
     // "Input" value
     Del(AvlTreeNode<T>), // Deleted Node
 }
 
-impl<T: PartialOrd> PartialEq<Box<TreeNode<T>>> for DeleteValue<T> {
-    fn eq(&self, other: &Box<TreeNode<T>>) -> bool {
+impl<T: Borrow<Q>, Q: ?Sized> DeleteValue<'_, T, Q> {
+    // Where the wanted node is compared to `node`: Equal when it is `node`, Less when it is on the left
+    fn compare<C: Comparator<Q>>(&self, node: &TreeNode<T>, cmp: &C) -> Ordering {
         match self {
-            Min => other.left.is_none(),
-            Max => other.right.is_none(),
-            Val(v) => v == &other.val,
-            _ => false,
-        }
-    }
-}
-
-impl<T: PartialOrd> PartialOrd<Box<TreeNode<T>>> for DeleteValue<T> {
-    fn partial_cmp(&self, other: &Box<TreeNode<T>>) -> Option<Ordering> {
-        match self {
-            Min => Some(Ordering::Less),
-            Max => Some(Ordering::Greater),
-            Val(v) => v.partial_cmp(&other.val),
-            _ => None,
+            Min if node.left.is_none() => Ordering::Equal,
+            Min => Ordering::Less,
+            Max if node.right.is_none() => Ordering::Equal,
+            Max => Ordering::Greater,
+            Val(v) => cmp.compare(v, node.val.borrow()),
+            Del(_) => Ordering::Greater,
         }
     }
 }
 
 // private function trait
-trait __AvlTree<T: PartialOrd> {
-    fn right_rotate(&mut self);
-    fn left_rotate(&mut self);
-    fn rotate_lr(&mut self);
-    fn rotate_rl(&mut self);
-    fn update_height(&mut self);
+trait __AvlTree<T> {
+    fn leaf(val: T) -> Self;
+    fn node_height(&self) -> i32;
+    fn node_size(&self) -> usize;
+    fn right_rotate<K: KeyText<T>>(&mut self, keys: &K);
+    fn left_rotate<K: KeyText<T>>(&mut self, keys: &K);
+    fn rotate_lr<K: KeyText<T>>(&mut self, keys: &K);
+    fn rotate_rl<K: KeyText<T>>(&mut self, keys: &K);
+    fn update_height<K: KeyText<T>>(&mut self, keys: &K);
+    fn update_size(&mut self);
     fn balance_factor(&self) -> i32;
-    fn do_insert(&mut self, val: T) -> InnerResult;
-    fn do_delete(&mut self, val: &mut DeleteValue<T>, val2: &T) -> InnerResult;
-    fn recursive_print(&self, prefix_space: &String, is_right: bool, child_prefix: String);
-    fn contains_node(&self, val: T) -> bool;
-    fn inorder_to_list(&self, vec: &mut Vec<T>);
-    fn preorder_to_list(&self, vec: &mut Vec<T>);
-    fn postorder_to_list(&self, vec: &mut Vec<T>);
+    // `keys` writes the keys into trace events, see `trace::KeyText`
+    fn do_insert<C: Comparator<T>, K: KeyText<T>>(&mut self, val: T, cmp: &C, keys: &K) -> InnerResult;
+    fn do_delete<Q: ?Sized, C: Comparator<Q>, K: KeyText<T> + KeyText<Q>>(&mut self, val: &mut DeleteValue<T, Q>, cmp: &C, keys: &K) -> InnerResult where T: Borrow<Q>;
+    fn recursive_diagram<K: KeyText<T>>(&self, prefix_space: &String, is_right: bool, child_prefix: String, keys: &K, lines: &mut Vec<String>);
+    fn emit_subtree_diagram<K: KeyText<T>>(&self, keys: &K);
+    // show this subtree in `trace::record_steps`, after a rotation
+    fn contains_node<Q: ?Sized, C: Comparator<Q>, K: KeyText<T> + KeyText<Q>>(&self, val: &Q, cmp: &C, keys: &K) -> bool where T: Borrow<Q>;
+    fn change_count<C: Comparator<T>, K: KeyText<T>>(&mut self, val: &T, grow: bool, cmp: &C, keys: &K) -> usize;
+    // add a copy of val (or remove one), returns the count before, 0 if val is missing; never removes the last copy
+    fn entries_to_list<'a>(&'a self, vec: &mut Vec<(&'a T, usize)>);
+    fn inorder_to_list<'a>(&'a self, vec: &mut Vec<&'a T>);
+    fn preorder_to_list<'a>(&'a self, vec: &mut Vec<&'a T>);
+    fn postorder_to_list<'a>(&'a self, vec: &mut Vec<&'a T>);
+    fn range_to_list<'a, Q: ?Sized, C: Comparator<Q>>(&'a self, low: &Q, high: &Q, cmp: &C, vec: &mut Vec<&'a T>) where T: Borrow<Q>;
+    fn is_ordered<C: Comparator<T>>(&self, low: Option<&T>, high: Option<&T>, cmp: &C) -> bool;
+    // Heights, balance and key order of every node are right, all keys strictly between low and high
 }
 
 // public function trait - FOR USERS
-pub trait AvlTree<T: PartialOrd> {
+pub trait AvlTree<T: Ord> {
     fn new(val: T) -> Self;
     // generate a new node
     fn height(&self) -> i32;
     // get height of a node
     fn insert_node(&mut self, val: T);
     // insert node
-    fn delete_node(&mut self, val: T) -> Self;
+    fn delete_node<Q: Ord + Debug + ?Sized>(&mut self, val: &Q) -> Self where T: Debug + Borrow<Q>;
     // delete node
+    fn remove_node<Q: Ord + ?Sized>(&mut self, val: &Q) -> bool where T: Borrow<Q>;
+    // delete node without printing anything, false if it does not exist
     fn validate_tree(&self) -> bool;
     // Is it a "balanced" AVL tree?
     fn is_tree_empty(&self) -> bool;
@@ -92,35 +112,84 @@ pub trait AvlTree<T: PartialOrd> {
     // Height of this tree
     fn number_of_leaves(&self) -> i32;
     // number of leaves in this tree
-    fn in_order_traverse(&mut self) -> Vec<T>;
-    // in_order_traverse, return result as a vector
-    fn pre_order_traverse(&mut self) -> Vec<T>;
-    // pre_order_traverse, return result as a vector
-    fn post_order_traverse(&mut self) -> Vec<T>;
-    // post_order_traverse, return result as a vector
-    fn print_tree_diagram(&mut self);
+    fn in_order_traverse(&self) -> Vec<&T>;
+    // in_order_traverse, return references to the values as a vector
+    fn pre_order_traverse(&self) -> Vec<&T>;
+    // pre_order_traverse, return references to the values as a vector
+    fn post_order_traverse(&self) -> Vec<&T>;
+    // post_order_traverse, return references to the values as a vector
+    fn print_tree_diagram(&mut self) where T: Debug;
     // print the tree nicely
-    fn exist_or_not(&self, val: T) -> bool;
+    fn tree_diagram(&self) -> Vec<String> where T: Debug;
+    // the lines printed by print_tree_diagram, empty for an empty tree
+    fn exist_or_not<Q: Ord + ?Sized>(&self, val: &Q) -> bool where T: Borrow<Q>;
     // check the existence of a specified node
     fn generate_empty_tree() -> Self;
     // generate a new empty tree
-    fn update_node(&mut self, old: T, new: T);
+    fn update_node<Q: Ord + Debug + ?Sized>(&mut self, old: &Q, new: T) where T: Debug + Borrow<Q>;
     // update the node
+    fn replace_node<Q: Ord + ?Sized>(&mut self, old: &Q, new: T) -> bool where T: Borrow<Q>;
+    // update without printing anything, false if old does not exist, new already exists or they are the same
     fn total_number_elements(&mut self) -> i32;
     // count total number of elements
+    fn from_pre_order(keys: &[T]) -> Result<Self, String> where Self: Sized, T: Clone;
+    // rebuild a tree from the output of `pre_order_traverse`
+    fn from_post_order(keys: &[T]) -> Result<Self, String> where Self: Sized, T: Clone;
+    // rebuild a tree from the output of `post_order_traverse`
+    fn range<Q: Ord + ?Sized>(&self, low: &Q, high: &Q) -> Vec<&T> where T: Borrow<Q>;
+    // all values between low and high (both included), in order
+}
+
+// The operations of AvlTree that report to a tracer (see `trace.rs`), with the keys written out in the events.
+// AvlTree does not ask its keys for Debug, its own operations show every key as `?` in a trace.
+pub trait TracedAvlTree<T: Ord + Debug> {
+    fn insert_node_traced(&mut self, val: T);
+    // insert_node
+    fn remove_node_traced<Q: Ord + Debug + ?Sized>(&mut self, val: &Q) -> bool where T: Borrow<Q>;
+    // remove_node
+    fn exist_or_not_traced<Q: Ord + Debug + ?Sized>(&self, val: &Q) -> bool where T: Borrow<Q>;
+    // exist_or_not
 }
 
 // implementation for private
-impl<T: PartialOrd + Copy + Debug> __AvlTree<T> for AvlTreeNode<T> {
+impl<T> __AvlTree<T> for AvlTreeNode<T> {
+    fn leaf(val: T) -> Self {
+        stats::count_allocation();
+        Some(Box::new(TreeNode {
+            val,
+            count: 1,
+            size: 1,
+            height: 1,
+            left: None,
+            right: None,
+        }))
+    }
+
+    fn node_height(&self) -> i32 {
+        match self {
+            None => 0,
+            Some(node) => node.height,
+        }
+    }
+
+    fn node_size(&self) -> usize {
+        match self {
+            None => 0,
+            Some(node) => node.size,
+        }
+    }
+
     //         y                            x
     //        / \     Right Rotation       / \
     //       x  T4    ==============>     z   y
     //      / \                          /\ / \
     //     z  T3                        1 2 3  4
     //   T1 T2
-    fn right_rotate(&mut self) {  // Case LL
+    fn right_rotate<K: KeyText<T>>(&mut self, keys: &K) {  // Case LL
         match self {
             Some(root) => {  // y is root
+                trace::emit(|| TraceEvent::RotateRight { pivot: keys.key_text(&root.val) });
+                stats::count_rotation();
                 // 1. Get the left subtree of root, i.e. the x branch (at this point the left subtree has been stripped)
                 let left = &mut root.left.take();  // Get the value inside Option<>, leave a None
                 match left {
@@ -129,20 +198,21 @@ impl<T: PartialOrd + Copy + Debug> __AvlTree<T> for AvlTreeNode<T> {
                         // 2. T3 is connected to the left side of y (the left side of root and the right side of x are swapped)
                         // root.left=x.right & x.right=root.left
                         swap(&mut root.left, &mut node.right);
-                        self.update_height();  // update height
+                        self.update_height(keys);  // update height
                         // At this point self is y-(T3 & T4)
                         // 3. Connect y to the right side of the x branch (where root becomes x)
                         swap(self, &mut node.right);
                         // 4. Assign the reintegrated x branch (left) to self
                         // At this point self is the x branch after integration
                         swap(self, left);
-                        self.update_height();
+                        self.update_height(keys);
                     }
                     None => unreachable!(),
                 }
             }
             None => unreachable!(),
         }
+        self.emit_subtree_diagram(keys);
     }
 
     //         y                            x
@@ -151,9 +221,11 @@ impl<T: PartialOrd + Copy + Debug> __AvlTree<T> for AvlTreeNode<T> {
     //          / \                      / \ / \
     //         T3  z                    4  3 2  1
     //           T2 T1
-    fn left_rotate(&mut self) {  // Case RR
+    fn left_rotate<K: KeyText<T>>(&mut self, keys: &K) {  // Case RR
         match self {
             Some(root) => { // At this point root is y
+                trace::emit(|| TraceEvent::RotateLeft { pivot: keys.key_text(&root.val) });
+                stats::count_rotation();
                 // 1. Get the right subtree of y, the x branch (at this point the subtree is stripped)
                 let right = &mut root.right.take();
                 match right {
@@ -161,48 +233,72 @@ impl<T: PartialOrd + Copy + Debug> __AvlTree<T> for AvlTreeNode<T> {
                     Some(node) => {
                         // 2. Swap the left side of x with the right side of y (i.e. y-(T4 & T3))
                         swap(&mut root.right, &mut node.left);
-                        self.update_height();
+                        self.update_height(keys);
                         // At this point self is y-(T4 & T3)
                         // 3. Connect the left side of x to self (i.e., the y branch), where root becomes x
                         swap(self, &mut node.left);
                         // 4. Assign the reintegrated x branch to the right variable
                         // At this point self is the x branch
                         swap(self, right);
-                        self.update_height();
+                        self.update_height(keys);
                     }
                     None => unreachable!(),
                 }
             }
             None => unreachable!(),
         }
+        self.emit_subtree_diagram(keys);
     }
 
-    fn rotate_lr(&mut self) {
+    fn rotate_lr<K: KeyText<T>>(&mut self, keys: &K) {
         match self {
             Some(root) => {
-                root.left.left_rotate();
-                self.right_rotate();
+                trace::emit(|| TraceEvent::RotateLR { pivot: keys.key_text(&root.val) });
+                root.left.left_rotate(keys);
+                self.right_rotate(keys);
             }
             None => unreachable!(),
         }
     }
 
-    fn rotate_rl(&mut self) {
+    fn rotate_rl<K: KeyText<T>>(&mut self, keys: &K) {
         match self {
             Some(root) => {
-                root.right.right_rotate();
-                self.left_rotate();
+                trace::emit(|| TraceEvent::RotateRL { pivot: keys.key_text(&root.val) });
+                root.right.right_rotate(keys);
+                self.left_rotate(keys);
             }
             None => unreachable!(),
         }
     }
 
-    fn update_height(&mut self) {
+    fn update_height<K: KeyText<T>>(&mut self, keys: &K) {
         match self {
             None => {}
             // Find the highest height in the left subtree and the right subtree,
             // and add 1 to itself to be its own height
-            Some(node) => node.height = max(node.left.height(), node.right.height()) + 1,
+            Some(node) => {
+                let height = max(node.left.node_height(), node.right.node_height()) + 1;
+                if height != node.height {
+                    trace::emit(|| TraceEvent::HeightUpdate { node: keys.key_text(&node.val), old: node.height, new: height });
+                }
+                node.height = height;
+            }
+        }
+        self.update_size();
+    }
+
+    fn emit_subtree_diagram<K: KeyText<T>>(&self, keys: &K) {
+        trace::emit_diagram(|| {
+            let mut lines = Vec::new();
+            self.recursive_diagram(&"".to_string(), true, "Subtree".to_string(), keys, &mut lines);
+            lines
+        });
+    }
+
+    fn update_size(&mut self) {
+        if let Some(node) = self {
+            node.size = node.count + node.left.node_size() + node.right.node_size();
         }
     }
 
@@ -210,40 +306,48 @@ impl<T: PartialOrd + Copy + Debug> __AvlTree<T> for AvlTreeNode<T> {
         match self {
             None => 0,
             // Balance factor = left subtree height - right subtree height
-            Some(node) => node.left.height() - node.right.height(),
+            Some(node) => node.left.node_height() - node.right.node_height(),
         }
     }
 
-    fn do_insert(&mut self, val: T) -> InnerResult {
+    fn do_insert<C: Comparator<T>, K: KeyText<T>>(&mut self, val: T, cmp: &C, keys: &K) -> InnerResult {
         match self {
             // If there is no node at a given location, create a new one and put it here
             None => {
-                *self = Self::new(val);
+                *self = Self::leaf(val);
                 Unknown
             }
             // Recursive insertion
             Some(root) => {
+                trace::emit(|| TraceEvent::Compare { key: keys.key_text(&val), node: keys.key_text(&root.val) });
+                stats::count_comparison();
+                let order = cmp.compare(&val, &root.val);
                 // Duplicate data, do nothing
-                if val == root.val {
-                    Balanced
-                } else if val < root.val {
+                if order == Ordering::Equal {
+                    Duplicate
+                } else if order == Ordering::Less {
                     // Target value < current node value, find position to the left subtree
-                    match root.left.do_insert(val) {
-                        Balanced => Balanced,
+                    match root.left.do_insert(val, cmp, keys) {
+                        Duplicate => Duplicate,
+                        Balanced => {
+                            // the height stays, but the size may have grown
+                            self.update_size();
+                            Balanced
+                        }
                         NotBalanced => {
                             // When the absolute value of "balance factor" is greater than 1, it is unbalanced,
                             // which is a positive number, representing the left side of the unbalance
                             if self.balance_factor() == 2 {
                                 match NotBalanced {
-                                    Left => self.right_rotate(), // Case: LeftLeft - ll
-                                    Right => self.rotate_lr(), // Case LeftRight - lr
+                                    Left => self.right_rotate(keys), // Case: LeftLeft - ll
+                                    Right => self.rotate_lr(keys), // Case LeftRight - lr
                                     _ => unreachable!(), // The current node must be balanced when `Unknown` is returned
                                 }
                                 Balanced  // The tree is balanced after the operation
-                            } else if self.height() == {
+                            } else if self.node_height() == {
                                 // Verify that the height at this point (after rotate) is the same as the one recorded inside the node
-                                self.update_height();
-                                self.height()
+                                self.update_height(keys);
+                                self.node_height()
                             } {
                                 // Same as: else if self.height = self.height {Balanced}
                                 Balanced
@@ -255,21 +359,25 @@ impl<T: PartialOrd + Copy + Debug> __AvlTree<T> for AvlTreeNode<T> {
                     }
                     // Recursive insertion into the right subtree
                 } else {
-                    match root.right.do_insert(val) {
-                        Balanced => Balanced,
+                    match root.right.do_insert(val, cmp, keys) {
+                        Duplicate => Duplicate,
+                        Balanced => {
+                            self.update_size();
+                            Balanced
+                        }
                         NotBalanced => {
                             // When the absolute value of "equilibrium factor" is greater than 1, it is unbalanced,
                             // which is a negative number, representing the right side of the unbalance
                             if self.balance_factor() == -2 {
                                 match NotBalanced {
-                                    Left => self.rotate_rl(),  // case: RightLeft - rl
-                                    Right => self.left_rotate(),  // case: RightRight - rr
+                                    Left => self.rotate_rl(keys),  // case: RightLeft - rl
+                                    Right => self.left_rotate(keys),  // case: RightRight - rr
                                     _ => unreachable!(),
                                 }
                                 Balanced
-                            } else if self.height() == {
-                                self.update_height();
-                                self.height()
+                            } else if self.node_height() == {
+                                self.update_height(keys);
+                                self.node_height()
                             } {
                                 Balanced
                             } else {
@@ -282,7 +390,7 @@ impl<T: PartialOrd + Copy + Debug> __AvlTree<T> for AvlTreeNode<T> {
         }
     }
 
-    fn do_delete(&mut self, val: &mut DeleteValue<T>, val2: &T) -> InnerResult {
+    fn do_delete<Q: ?Sized, C: Comparator<Q>, K: KeyText<T> + KeyText<Q>>(&mut self, val: &mut DeleteValue<T, Q>, cmp: &C, keys: &K) -> InnerResult where T: Borrow<Q> {
         // Core idea: Hibbard Deletion
         // When the node to be deleted is not empty, first find the subtree with the node to be deleted as the root,
         // and second find the node closest to its value and replace it with this node
@@ -292,23 +400,27 @@ impl<T: PartialOrd + Copy + Debug> __AvlTree<T> for AvlTreeNode<T> {
             // If the place has no value, then "do nothing"
             None => {
                 *val = Del(None);
-                println!("DELETE FAILED: No such node({:?}) to delete", val2);
                 Balanced
             }
             // If have, then
             Some(root) => {
+                if let Val(v) = val {
+                    trace::emit(|| TraceEvent::Compare { key: keys.key_text(*v), node: keys.key_text(&root.val) });
+                    stats::count_comparison();
+                }
+                let order = val.compare(root, cmp);
                 // First get the height of the tree (or subtree) with this node as the heel, and save it as a backup
                 let height = root.height;
                 // case 1: If what you are looking for is the current
-                if val == root {
+                if order == Ordering::Equal {
                     if root.left.is_some() {
                         // Case 1-1: The left and right subtrees are not empty
                         if root.right.is_some() {
                             // Find the tallest subtree in the left or right sides to take the replacement node,
                             // reduce the damage to the balance
-                            if root.left.height() > root.right.height() {
+                            if root.left.node_height() > root.right.node_height() {
                                 *val = Max;  // Give val a "Max tag"
-                                root.left.do_delete(val, val2); // Delete the "largest node(Max)" in the left subtree and return this node
+                                root.left.do_delete(val, cmp, keys); // Delete the "largest node(Max)" in the left subtree and return this node
                                 match val {
                                     // If there is a return value Del<Node<T>>, swap the "largest node(Max)" with the "node to be deleted",
                                     // so that the "largest node" enters the original position of the "node to be deleted".
@@ -316,17 +428,19 @@ impl<T: PartialOrd + Copy + Debug> __AvlTree<T> for AvlTreeNode<T> {
                                         // root.val -> the node we want to delete
                                         // node.val -> the node used to replace the "delete node"
                                         swap(&mut root.val, &mut node.val);
+                                        swap(&mut root.count, &mut node.count);
                                     }
                                     _ => unreachable!(),
                                 }
                             } else {
                                 // else, find the minimum value in the right side
                                 *val = Min;
-                                root.right.do_delete(val, val2);  // delete and return the value
+                                root.right.do_delete(val, cmp, keys);  // delete and return the value
                                 match val {
                                     // Same above
                                     Del(Some(x)) => {
                                         swap(&mut root.val, &mut x.val);
+                                        swap(&mut root.count, &mut x.count);
                                     }
                                     _ => unreachable!(),
                                 }
@@ -345,38 +459,45 @@ impl<T: PartialOrd + Copy + Debug> __AvlTree<T> for AvlTreeNode<T> {
                         swap(self, &mut right);
                         *val = Del(right); // return the deleted value
                     }
-                    self.update_height();  // update the height
-                } else if val < root {  // Case 2: val < root's value, go to the left-side
-                    match root.left.do_delete(val, val2) {  // Recursively delete on the left subtree,
+                    self.update_height(keys);  // update the height
+                } else if order == Ordering::Less {  // Case 2: val < root's value, go to the left-side
+                    match root.left.do_delete(val, cmp, keys) {  // Recursively delete on the left subtree,
                         // when found after the node to be deleted will execute the code of Case 1 and return the result (balance or not)
-                        Balanced => return Balanced,
+                        Balanced => {
+                            // the height stays, but the size may have shrunk
+                            self.update_size();
+                            return Balanced;
+                        }
                         Unknown => {  // If the imbalance will be self-rotating to maintain balance
                             if self.balance_factor() == -2 {  // The right side will be taller after the left side is deleted
                                 let right = self.as_ref().unwrap().right.as_ref().unwrap();  // get right side
-                                if right.left.height() > right.right.height() {  // if right-left is taller than right-right
-                                    self.rotate_rl();  // RightLeft - rl case
+                                if right.left.node_height() > right.right.node_height() {  // if right-left is taller than right-right
+                                    self.rotate_rl(keys);  // RightLeft - rl case
                                 } else {
-                                    self.left_rotate();  // Otherwise, RightRight - rr case
+                                    self.left_rotate(keys);  // Otherwise, RightRight - rr case
                                 }
                             } else {
-                                self.update_height();
+                                self.update_height(keys);
                             }
                         }
                         _ => unreachable!(),
                     }
                 } else {  // Case 3: val > root's value, go to the right-side
-                    match root.right.do_delete(val, val2) {
-                        Balanced => return Balanced,
+                    match root.right.do_delete(val, cmp, keys) {
+                        Balanced => {
+                            self.update_size();
+                            return Balanced;
+                        }
                         Unknown => {
                             if self.balance_factor() == 2 {
                                 let left = self.as_ref().unwrap().left.as_ref().unwrap();
-                                if left.left.height() >= left.right.height() {
-                                    self.right_rotate();
+                                if left.left.node_height() >= left.right.node_height() {
+                                    self.right_rotate(keys);
                                 } else {
-                                    self.rotate_lr();
+                                    self.rotate_lr(keys);
                                 }
                             } else {
-                                self.update_height();
+                                self.update_height(keys);
                             }
                         }
                         _ => unreachable!(),
@@ -385,7 +506,7 @@ impl<T: PartialOrd + Copy + Debug> __AvlTree<T> for AvlTreeNode<T> {
                 // Here is the result of the recursion to the "bottom" level after performing the delete action
                 // and returning it to the top level
                 // root.[direction].do_delete(val) -> Balanced or Unknown?
-                if self.height() == height {
+                if self.node_height() == height {
                     Balanced
                 } else {
                     Unknown
@@ -394,52 +515,56 @@ impl<T: PartialOrd + Copy + Debug> __AvlTree<T> for AvlTreeNode<T> {
         }
     }
 
-    fn recursive_print(&self, prefix_space: &String, is_left: bool, child_prefix: String) {
+    fn recursive_diagram<K: KeyText<T>>(&self, prefix_space: &String, is_left: bool, child_prefix: String, keys: &K, lines: &mut Vec<String>) {
         if self.is_none() {
             let null_prefix = if is_left { "├ " } else { "└ " };
-            println!("{}{}{} {}", prefix_space, null_prefix, child_prefix, "null");
+            lines.push(format!("{}{}{} {}", prefix_space, null_prefix, child_prefix, "null"));
             return;
         }
 
         let node = self.as_ref().unwrap();
         let prefix_current = if is_left { "├ " } else { "└ " };
 
-        // Print the current
-        println!(
-            "{}{}{} {:?} : {}",
-            prefix_space, prefix_current, child_prefix, self.as_ref().unwrap().val, node.height
-        );
+        // Print the current, a key stored more than once shows its count
+        let copies = if node.count > 1 { format!(" (x{})", node.count) } else { String::new() };
+        lines.push(format!(
+            "{}{}{} {}{} : {}",
+            prefix_space, prefix_current, child_prefix, keys.key_text(&node.val), copies, node.height
+        ));
 
         // adjust the space
         let prefix_child = if is_left { "| " } else { "  " };
         let mut prefix_space = prefix_space.to_owned();
         prefix_space.push_str(&prefix_child);
 
-        self.as_ref().unwrap().left.recursive_print(&prefix_space, true, "L ".to_string());
-        self.as_ref().unwrap().right.recursive_print(&prefix_space, false, "R ".to_string());
+        self.as_ref().unwrap().left.recursive_diagram(&prefix_space, true, "L ".to_string(), keys, lines);
+        self.as_ref().unwrap().right.recursive_diagram(&prefix_space, false, "R ".to_string(), keys, lines);
     }
 
-    fn contains_node(&self, val: T) -> bool {
+    fn contains_node<Q: ?Sized, C: Comparator<Q>, K: KeyText<T> + KeyText<Q>>(&self, val: &Q, cmp: &C, keys: &K) -> bool where T: Borrow<Q> {
         match self {
             None => {
                 println!("Tree is empty, please add some node!");
                 false
             }
-            Some(_) => {
-                if val.eq(&self.as_ref().unwrap().val) {
+            Some(node) => {
+                trace::emit(|| TraceEvent::Compare { key: keys.key_text(val), node: keys.key_text(&node.val) });
+                stats::count_comparison();
+                let order = cmp.compare(val, node.val.borrow());
+                if order == Ordering::Equal {
                     true
-                } else if val.lt(&self.as_ref().unwrap().val) {
+                } else if order == Ordering::Less {
                     match &self.as_ref().unwrap().left {
                         None => false,
                         Some(_) => {
-                            self.as_ref().unwrap().left.contains_node(val)
+                            self.as_ref().unwrap().left.contains_node(val, cmp, keys)
                         }
                     }
                 } else {
                     match &self.as_ref().unwrap().right {
                         None => false,
                         Some(_) => {
-                            self.as_ref().unwrap().right.contains_node(val)
+                            self.as_ref().unwrap().right.contains_node(val, cmp, keys)
                         }
                     }
                 }
@@ -447,89 +572,147 @@ impl<T: PartialOrd + Copy + Debug> __AvlTree<T> for AvlTreeNode<T> {
         }
     }
 
-    fn inorder_to_list(&self, vec: &mut Vec<T>) {
+    fn change_count<C: Comparator<T>, K: KeyText<T>>(&mut self, val: &T, grow: bool, cmp: &C, keys: &K) -> usize {
+        let node = match self {
+            None => return 0,
+            Some(node) => node,
+        };
+        trace::emit(|| TraceEvent::Compare { key: keys.key_text(val), node: keys.key_text(&node.val) });
+        stats::count_comparison();
+        let before = match cmp.compare(val, &node.val) {
+            Ordering::Equal => {
+                let before = node.count;
+                if grow {
+                    node.count += 1;
+                } else if before > 1 {
+                    node.count -= 1;
+                }
+                before
+            }
+            Ordering::Less => node.left.change_count(val, grow, cmp, keys),
+            Ordering::Greater => node.right.change_count(val, grow, cmp, keys),
+        };
+        // every subtree on the way holds the copy that was added or removed
+        if grow && before > 0 {
+            node.size += 1;
+        } else if !grow && before > 1 {
+            node.size -= 1;
+        }
+        before
+    }
+
+    fn entries_to_list<'a>(&'a self, vec: &mut Vec<(&'a T, usize)>) {
         if let Some(node) = self {
-            self.as_ref().unwrap().left.inorder_to_list(vec);
-            vec.push(node.val);
-            self.as_ref().unwrap().right.inorder_to_list(vec);
+            node.left.entries_to_list(vec);
+            vec.push((&node.val, node.count));
+            node.right.entries_to_list(vec);
         }
     }
 
-    fn preorder_to_list(&self, vec: &mut Vec<T>) {
+    fn inorder_to_list<'a>(&'a self, vec: &mut Vec<&'a T>) {
         if let Some(node) = self {
-            vec.push(node.val);
             self.as_ref().unwrap().left.inorder_to_list(vec);
+            vec.extend(repeat_n(&node.val, node.count));
             self.as_ref().unwrap().right.inorder_to_list(vec);
         }
     }
 
-    fn postorder_to_list(&self, vec: &mut Vec<T>) {
+    fn preorder_to_list<'a>(&'a self, vec: &mut Vec<&'a T>) {
         if let Some(node) = self {
-            self.as_ref().unwrap().left.inorder_to_list(vec);
-            self.as_ref().unwrap().right.inorder_to_list(vec);
-            vec.push(node.val);
+            vec.extend(repeat_n(&node.val, node.count));
+            self.as_ref().unwrap().left.preorder_to_list(vec);
+            self.as_ref().unwrap().right.preorder_to_list(vec);
+        }
+    }
+
+    fn postorder_to_list<'a>(&'a self, vec: &mut Vec<&'a T>) {
+        if let Some(node) = self {
+            self.as_ref().unwrap().left.postorder_to_list(vec);
+            self.as_ref().unwrap().right.postorder_to_list(vec);
+            vec.extend(repeat_n(&node.val, node.count));
+        }
+    }
+
+    fn range_to_list<'a, Q: ?Sized, C: Comparator<Q>>(&'a self, low: &Q, high: &Q, cmp: &C, vec: &mut Vec<&'a T>) where T: Borrow<Q> {
+        if let Some(node) = self {
+            let above_low = cmp.compare(low, node.val.borrow());
+            let below_high = cmp.compare(node.val.borrow(), high);
+            // Only visit the subtrees that can contain values inside the range
+            if above_low == Ordering::Less {
+                node.left.range_to_list(low, high, cmp, vec);
+            }
+            if above_low != Ordering::Greater && below_high != Ordering::Greater {
+                vec.extend(repeat_n(&node.val, node.count));
+            }
+            if below_high == Ordering::Less {
+                node.right.range_to_list(low, high, cmp, vec);
+            }
+        }
+    }
+
+    fn is_ordered<C: Comparator<T>>(&self, low: Option<&T>, high: Option<&T>, cmp: &C) -> bool {
+        if let Some(root) = self {
+            if root.count == 0 || root.height != max(root.left.node_height(), root.right.node_height()) + 1 {
+                return false;
+            }
+            if root.size != root.count + root.left.node_size() + root.right.node_size() {
+                return false;
+            }
+            if self.balance_factor().abs() > 1 {
+                return false;
+            }
+            // every key on the left of an ancestor has to be smaller than it, not only the direct children
+            if low.is_some_and(|low| cmp.compare(&root.val, low) != Ordering::Greater) {
+                return false;
+            }
+            if high.is_some_and(|high| cmp.compare(&root.val, high) != Ordering::Less) {
+                return false;
+            }
+            return root.left.is_ordered(low, Some(&root.val), cmp) && root.right.is_ordered(Some(&root.val), high, cmp);
         }
+        true
     }
 }
 
 // implementation for public
-impl<T: PartialOrd + Copy + Debug> AvlTree<T> for AvlTreeNode<T> {
+impl<T: Ord> AvlTree<T> for AvlTreeNode<T> {
     // generate a new node
     fn new(val: T) -> Self {
-        Some(Box::new(TreeNode {
-            val,
-            height: 1,
-            left: None,
-            right: None,
-        }))
+        Self::leaf(val)
     }
     // get a node's height
     fn height(&self) -> i32 {
-        match self {
-            None => 0,
-            Some(node) => node.height,
-        }
+        self.node_height()
     }
     // insert
     fn insert_node(&mut self, val: T) {
-        self.do_insert(val);
+        insert_with(self, val, &HiddenKeys);
     }
     // delete
-    fn delete_node(&mut self, val: T) -> Self {
-        let val2 = val.clone();
-        let mut val = Val(val);
-        self.do_delete(&mut val, &val2);
-        match val {
+    fn delete_node<Q: Ord + Debug + ?Sized>(&mut self, val: &Q) -> Self where T: Debug + Borrow<Q> {
+        let _op = stats::begin_operation();
+        let mut del = Val(val);
+        self.do_delete(&mut del, &Natural, &DebugKeys);
+        match del {
             Del(node) => {
                 if node.is_some() {
-                    println!("Node({:?}) delete successfully.", val2);
+                    println!("Node({:?}) delete successfully.", val);
+                } else {
+                    println!("DELETE FAILED: No such node({:?}) to delete", val);
                 }
                 node
             }
             _ => unreachable!()
         }
     }
+
+    fn remove_node<Q: Ord + ?Sized>(&mut self, val: &Q) -> bool where T: Borrow<Q> {
+        remove_with(self, val, &HiddenKeys)
+    }
+
     // Is it a avl tree?
     fn validate_tree(&self) -> bool {
-        if let Some(root) = self {
-            if root.height != max(root.left.height(), root.right.height()) + 1 {
-                return false;
-            }
-            if self.balance_factor().abs() > 1 {
-                return false;
-            }
-            if let Some(x) = &root.left {
-                if !(x.val < root.val && self.as_ref().unwrap().left.validate_tree()) {
-                    return false;
-                }
-            }
-            if let Some(x) = &root.right {
-                if !(x.val > root.val && self.as_ref().unwrap().right.validate_tree()) {
-                    return false;
-                }
-            }
-        }
-        true
+        self.is_ordered(None, None, &Natural)
     }
 
     fn is_tree_empty(&self) -> bool {
@@ -559,71 +742,925 @@ impl<T: PartialOrd + Copy + Debug> AvlTree<T> for AvlTreeNode<T> {
         count
     }
 
-    fn in_order_traverse(&mut self) -> Vec<T> {
+    fn in_order_traverse(&self) -> Vec<&T> {
         let mut inorder_list = Vec::new();
         self.inorder_to_list(&mut inorder_list);
         inorder_list
     }
 
-    fn pre_order_traverse(&mut self) -> Vec<T> {
+    fn pre_order_traverse(&self) -> Vec<&T> {
         let mut preorder_list = Vec::new();
         self.preorder_to_list(&mut preorder_list);
         preorder_list
     }
 
-    fn post_order_traverse(&mut self) -> Vec<T> {
+    fn post_order_traverse(&self) -> Vec<&T> {
         let mut postorder_list = Vec::new();
         self.postorder_to_list(&mut postorder_list);
         postorder_list
     }
 
-    fn print_tree_diagram(&mut self) {
+    fn print_tree_diagram(&mut self) where T: Debug {
         match self {
             None => println!("Tree is Empty! Add some nodes before print."),
             Some(_) => {
                 println!("\n================== TREE PRINT <Node:Height> ==================");
-                self.recursive_print(&"".to_string(), true, "Root".to_string());
+                for line in self.tree_diagram() {
+                    println!("{}", line);
+                }
                 println!("======================== FINISH PRINT ========================");
             }
         }
     }
 
-    fn exist_or_not(&self, val: T) -> bool {
-        match self {
-            None => false,
-            Some(_) => {
-                self.contains_node(val)
-            }
+    fn tree_diagram(&self) -> Vec<String> where T: Debug {
+        let mut lines = Vec::new();
+        if self.is_some() {
+            self.recursive_diagram(&"".to_string(), true, "Root".to_string(), &DebugKeys, &mut lines);
         }
+        lines
+    }
+
+    fn exist_or_not<Q: Ord + ?Sized>(&self, val: &Q) -> bool where T: Borrow<Q> {
+        exist_with(self, val, &HiddenKeys)
     }
 
     fn generate_empty_tree() -> Self {
         Self::None
     }
 
-    fn update_node(&mut self, old: T, new: T) {
+    fn update_node<Q: Ord + Debug + ?Sized>(&mut self, old: &Q, new: T) where T: Debug + Borrow<Q> {
+        let _op = stats::begin_operation();
         match self {
             None => println!("Tree is Empty! Add some nodes before update."),
             Some(_) => {
-                if self.exist_or_not(old) == false {
+                if !exist_with(self, old, &DebugKeys) {
                     println!("UPDATE FAILED: Node({:?}) doesn't exist!", old);
-                } else if old == new {
+                } else if old == new.borrow() {
                     println!("UPDATE FAILED: New value and old value can not be same!");
                 } else {
-                    if self.exist_or_not(old) && self.exist_or_not(new) {
+                    if exist_with(self, old, &DebugKeys) && exist_with(self, new.borrow(), &DebugKeys) {
                         println!("UPDATE FAILED: Both Node({:?}) and Node({:?}) exist!", old, new);
                     } else {
-                        self.do_delete(&mut Val(old), &old);
-                        self.insert_node(new);
+                        {
+                            let _delete = stats::begin_operation();
+                            self.do_delete(&mut Val(old), &Natural, &DebugKeys);
+                        }
                         println!("Node({:?}) has been replaced by Node({:?})", old, new);
+                        insert_with(self, new, &DebugKeys);
                     }
                 }
             }
         }
     }
 
+    fn replace_node<Q: Ord + ?Sized>(&mut self, old: &Q, new: T) -> bool where T: Borrow<Q> {
+        let _op = stats::begin_operation();
+        if old == new.borrow() || !self.exist_or_not(old) || self.exist_or_not(new.borrow()) {
+            return false;
+        }
+        {
+            let _delete = stats::begin_operation();
+            self.do_delete(&mut Val(old), &Natural, &HiddenKeys);
+        }
+        self.insert_node(new);
+        true
+    }
+
     fn total_number_elements(&mut self) -> i32 {
-        let res_vec = self.in_order_traverse();
-        res_vec.len() as i32
+        self.node_size() as i32
+    }
+
+    fn range<Q: Ord + ?Sized>(&self, low: &Q, high: &Q) -> Vec<&T> where T: Borrow<Q> {
+        let mut range_list = Vec::new();
+        self.range_to_list(low, high, &Natural, &mut range_list);
+        range_list
+    }
+
+    fn from_pre_order(keys: &[T]) -> Result<Self, String> where T: Clone {
+        let mut next = 0;
+        let tree = rebuild_from_pre_order(keys, &mut next, None, None, 1)?;
+        finish_rebuild(tree, next == keys.len())
+    }
+
+    fn from_post_order(keys: &[T]) -> Result<Self, String> where T: Clone {
+        // Walk the sequence backwards: root, right subtree, left subtree
+        let mut remaining = keys.len();
+        let tree = rebuild_from_post_order(keys, &mut remaining, None, None, 1)?;
+        finish_rebuild(tree, remaining == 0)
+    }
+}
+
+impl<T: Ord + Debug> TracedAvlTree<T> for AvlTreeNode<T> {
+    fn insert_node_traced(&mut self, val: T) {
+        insert_with(self, val, &DebugKeys);
+    }
+
+    fn remove_node_traced<Q: Ord + Debug + ?Sized>(&mut self, val: &Q) -> bool where T: Borrow<Q> {
+        remove_with(self, val, &DebugKeys)
+    }
+
+    fn exist_or_not_traced<Q: Ord + Debug + ?Sized>(&self, val: &Q) -> bool where T: Borrow<Q> {
+        exist_with(self, val, &DebugKeys)
+    }
+}
+
+// The operations shared by AvlTree and TracedAvlTree, `keys` writes the keys into trace events
+fn insert_with<T: Ord, K: KeyText<T>>(tree: &mut AvlTreeNode<T>, val: T, keys: &K) {
+    let _op = stats::begin_operation();
+    tree.do_insert(val, &Natural, keys);
+}
+
+fn remove_with<T: Ord + Borrow<Q>, Q: Ord + ?Sized, K: KeyText<T> + KeyText<Q>>(tree: &mut AvlTreeNode<T>, val: &Q, keys: &K) -> bool {
+    let _op = stats::begin_operation();
+    let mut del = Val(val);
+    tree.do_delete(&mut del, &Natural, keys);
+    matches!(del, Del(Some(_)))
+}
+
+fn exist_with<T: Ord + Borrow<Q>, Q: Ord + ?Sized, K: KeyText<T> + KeyText<Q>>(tree: &AvlTreeNode<T>, val: &Q, keys: &K) -> bool {
+    let _op = stats::begin_operation();
+    tree.is_some() && tree.contains_node(val, &Natural, keys)
+}
+
+// An AVL tree ordered by a comparator (see `order.rs`) instead of the order of the keys themselves
+//
+//     let mut names = AvlTreeBy::with_comparator(CaseInsensitive);
+//     names.insert_node("Bob".to_string());
+//     names.exist_or_not(&"BOB".to_string());  // true
+//
+// The keys do not need to be comparable at all, a record can be ordered by one of its fields with `ByKey`.
+pub struct AvlTreeBy<T, C> {
+    root: AvlTreeNode<T>,
+    cmp: C,
+}
+
+impl<T, C: Comparator<T>> AvlTreeBy<T, C> {
+    pub fn with_comparator(cmp: C) -> Self {
+        AvlTreeBy { root: None, cmp }
+    }
+
+    pub fn comparator(&self) -> &C {
+        &self.cmp
+    }
+
+    // false if a key the comparator calls equal is already in the tree
+    pub fn insert_node(&mut self, val: T) -> bool {
+        let _op = stats::begin_operation();
+        !matches!(self.root.do_insert(val, &self.cmp, &HiddenKeys), Duplicate)
+    }
+
+    // false if there is no such key
+    pub fn remove_node(&mut self, val: &T) -> bool {
+        let _op = stats::begin_operation();
+        let mut del = Val(val);
+        self.root.do_delete(&mut del, &self.cmp, &HiddenKeys);
+        matches!(del, Del(Some(_)))
+    }
+
+    pub fn exist_or_not(&self, val: &T) -> bool {
+        let _op = stats::begin_operation();
+        self.root.is_some() && self.root.contains_node(val, &self.cmp, &HiddenKeys)
+    }
+
+    // all keys between low and high (both included) in the order of the comparator
+    pub fn range(&self, low: &T, high: &T) -> Vec<&T> {
+        let mut range_list = Vec::new();
+        self.root.range_to_list(low, high, &self.cmp, &mut range_list);
+        range_list
+    }
+
+    pub fn in_order_traverse(&self) -> Vec<&T> {
+        let mut inorder_list = Vec::new();
+        self.root.inorder_to_list(&mut inorder_list);
+        inorder_list
+    }
+
+    pub fn pre_order_traverse(&self) -> Vec<&T> {
+        let mut preorder_list = Vec::new();
+        self.root.preorder_to_list(&mut preorder_list);
+        preorder_list
+    }
+
+    pub fn post_order_traverse(&self) -> Vec<&T> {
+        let mut postorder_list = Vec::new();
+        self.root.postorder_to_list(&mut postorder_list);
+        postorder_list
+    }
+
+    pub fn is_tree_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    pub fn height_of_tree(&self) -> i32 {
+        self.root.node_height()
+    }
+
+    pub fn total_number_elements(&self) -> i32 {
+        self.root.node_size() as i32
+    }
+
+    // balanced, and every key in the place the comparator puts it
+    pub fn validate_tree(&self) -> bool {
+        self.root.is_ordered(None, None, &self.cmp)
+    }
+
+    pub fn tree_diagram(&self) -> Vec<String> where T: Debug {
+        let mut lines = Vec::new();
+        if self.root.is_some() {
+            self.root.recursive_diagram(&"".to_string(), true, "Root".to_string(), &DebugKeys, &mut lines);
+        }
+        lines
+    }
+}
+
+// An AVL tree that keeps duplicates: a key inserted again is not refused, its node counts it instead
+//
+//     let mut bag = AvlMultiset::new();
+//     bag.insert(3);
+//     bag.insert(3);
+//     bag.insert(1);
+//     bag.count(&3);              // 2
+//     bag.in_order_traverse();    // [1, 3, 3]
+//     bag.select(1);              // Some(3), the second smallest
+//
+// Traversals, ranges, `len`, `rank` and `select` count every copy of a key.
+pub struct AvlMultiset<T, C = Natural> {
+    root: AvlTreeNode<T>,
+    cmp: C,
+}
+
+impl<T: Ord> AvlMultiset<T> {
+    pub fn new() -> Self {
+        AvlMultiset { root: None, cmp: Natural }
+    }
+}
+
+impl<T: Ord> Default for AvlMultiset<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, C: Comparator<T>> AvlMultiset<T, C> {
+    pub fn with_comparator(cmp: C) -> Self {
+        AvlMultiset { root: None, cmp }
+    }
+
+    pub fn comparator(&self) -> &C {
+        &self.cmp
+    }
+
+    // the number of copies of val after inserting it
+    pub fn insert(&mut self, val: T) -> usize {
+        let _op = stats::begin_operation();
+        let before = self.root.change_count(&val, true, &self.cmp, &HiddenKeys);
+        if before > 0 {
+            return before + 1;
+        }
+        self.root.do_insert(val, &self.cmp, &HiddenKeys);
+        1
+    }
+
+    // remove one copy of val, false if there is none
+    pub fn remove_one(&mut self, val: &T) -> bool {
+        let _op = stats::begin_operation();
+        match self.root.change_count(val, false, &self.cmp, &HiddenKeys) {
+            0 => false,
+            1 => {
+                self.root.do_delete(&mut Val(val), &self.cmp, &HiddenKeys);
+                true
+            }
+            _ => true,
+        }
+    }
+
+    // remove every copy of val, returns how many there were
+    pub fn remove_all(&mut self, val: &T) -> usize {
+        let _op = stats::begin_operation();
+        let mut del = Val(val);
+        self.root.do_delete(&mut del, &self.cmp, &HiddenKeys);
+        match del {
+            Del(Some(node)) => node.count,
+            _ => 0,
+        }
+    }
+
+    pub fn count(&self, val: &T) -> usize {
+        let _op = stats::begin_operation();
+        let mut node = &self.root;
+        while let Some(current) = node {
+            trace::emit(|| TraceEvent::Compare { key: HiddenKeys.key_text(val), node: HiddenKeys.key_text(&current.val) });
+            stats::count_comparison();
+            node = match self.cmp.compare(val, &current.val) {
+                Ordering::Equal => return current.count,
+                Ordering::Less => &current.left,
+                Ordering::Greater => &current.right,
+            };
+        }
+        0
+    }
+
+    pub fn contains(&self, val: &T) -> bool {
+        self.count(val) > 0
+    }
+
+    // every copy counted
+    pub fn len(&self) -> usize {
+        self.root.node_size()
+    }
+
+    // how many different keys
+    pub fn distinct_len(&self) -> usize {
+        count_nodes(&self.root) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    // every different key once with its count, in order
+    pub fn entries(&self) -> Vec<(&T, usize)> {
+        let mut entries = Vec::new();
+        self.root.entries_to_list(&mut entries);
+        entries
+    }
+
+    // how many copies of keys smaller than val there are, the index val has or would get in in_order_traverse
+    pub fn rank(&self, val: &T) -> usize {
+        let _op = stats::begin_operation();
+        let mut rank = 0;
+        let mut node = &self.root;
+        while let Some(current) = node {
+            trace::emit(|| TraceEvent::Compare { key: HiddenKeys.key_text(val), node: HiddenKeys.key_text(&current.val) });
+            stats::count_comparison();
+            node = match self.cmp.compare(val, &current.val) {
+                Ordering::Equal => return rank + current.left.node_size(),
+                Ordering::Less => &current.left,
+                Ordering::Greater => {
+                    // everything on the left and every copy of this key comes first
+                    rank += current.left.node_size() + current.count;
+                    &current.right
+                }
+            };
+        }
+        rank
+    }
+
+    // the key at position index (from 0) of in_order_traverse, None if there are not that many
+    pub fn select(&self, index: usize) -> Option<&T> {
+        let mut index = index;
+        let mut node = &self.root;
+        while let Some(current) = node {
+            let left = current.left.node_size();
+            if index < left {
+                node = &current.left;
+            } else if index < left + current.count {
+                return Some(&current.val);
+            } else {
+                index -= left + current.count;
+                node = &current.right;
+            }
+        }
+        None
+    }
+
+    pub fn range(&self, low: &T, high: &T) -> Vec<&T> {
+        let mut range_list = Vec::new();
+        self.root.range_to_list(low, high, &self.cmp, &mut range_list);
+        range_list
+    }
+
+    pub fn in_order_traverse(&self) -> Vec<&T> {
+        let mut inorder_list = Vec::new();
+        self.root.inorder_to_list(&mut inorder_list);
+        inorder_list
+    }
+
+    pub fn pre_order_traverse(&self) -> Vec<&T> {
+        let mut preorder_list = Vec::new();
+        self.root.preorder_to_list(&mut preorder_list);
+        preorder_list
+    }
+
+    pub fn post_order_traverse(&self) -> Vec<&T> {
+        let mut postorder_list = Vec::new();
+        self.root.postorder_to_list(&mut postorder_list);
+        postorder_list
+    }
+
+    pub fn height_of_tree(&self) -> i32 {
+        self.root.node_height()
+    }
+
+    pub fn validate_tree(&self) -> bool {
+        self.root.is_ordered(None, None, &self.cmp)
+    }
+
+    pub fn tree_diagram(&self) -> Vec<String> where T: Debug {
+        let mut lines = Vec::new();
+        if self.root.is_some() {
+            self.root.recursive_diagram(&"".to_string(), true, "Root".to_string(), &DebugKeys, &mut lines);
+        }
+        lines
+    }
+}
+
+// Rebuilding from a traversal sequence
+// Every key of a subtree has to lie between the bounds given by its ancestors, so the first key (pre-order)
+// or last key (post-order) outside of the bounds ends the current subtree. Each key is looked at once.
+
+// No balanced tree with n nodes is taller than this, protects the recursion from degenerate input
+fn max_rebuild_depth(n: usize) -> usize {
+    2 * (usize::BITS - n.leading_zeros()) as usize + 2
+}
+
+fn in_bounds<T: Ord>(key: &T, lower: Option<&T>, upper: Option<&T>) -> bool {
+    lower.is_none_or(|l| l < key) && upper.is_none_or(|u| key < u)
+}
+
+fn new_rebuilt_node<T: Ord>(val: T, left: AvlTreeNode<T>, right: AvlTreeNode<T>) -> AvlTreeNode<T> {
+    let height = max(left.height(), right.height()) + 1;
+    let size = 1 + left.node_size() + right.node_size();
+    Some(Box::new(TreeNode { val, count: 1, size, height, left, right }))
+}
+
+fn rebuild_from_pre_order<T: Ord + Clone>(keys: &[T], next: &mut usize, lower: Option<&T>, upper: Option<&T>, depth: usize) -> Result<AvlTreeNode<T>, String> {
+    match keys.get(*next) {
+        Some(val) if in_bounds(val, lower, upper) => {
+            if depth > max_rebuild_depth(keys.len()) {
+                return Err("The sequence does not describe a balanced tree".to_string());
+            }
+            *next += 1;
+            let left = rebuild_from_pre_order(keys, next, lower, Some(val), depth + 1)?;
+            let right = rebuild_from_pre_order(keys, next, Some(val), upper, depth + 1)?;
+            Ok(new_rebuilt_node(val.clone(), left, right))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn rebuild_from_post_order<T: Ord + Clone>(keys: &[T], remaining: &mut usize, lower: Option<&T>, upper: Option<&T>, depth: usize) -> Result<AvlTreeNode<T>, String> {
+    match keys[..*remaining].last() {
+        Some(val) if in_bounds(val, lower, upper) => {
+            if depth > max_rebuild_depth(keys.len()) {
+                return Err("The sequence does not describe a balanced tree".to_string());
+            }
+            *remaining -= 1;
+            let right = rebuild_from_post_order(keys, remaining, Some(val), upper, depth + 1)?;
+            let left = rebuild_from_post_order(keys, remaining, lower, Some(val), depth + 1)?;
+            Ok(new_rebuilt_node(val.clone(), left, right))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn finish_rebuild<T: Ord>(tree: AvlTreeNode<T>, all_used: bool) -> Result<AvlTreeNode<T>, String> {
+    if !all_used {
+        return Err("The sequence is not a traversal of a binary search tree with unique keys".to_string());
+    }
+    if !tree.validate_tree() {
+        return Err("The sequence does not describe a balanced AVL tree".to_string());
+    }
+    Ok(tree)
+}
+
+
+
+// Binary persistence, see `codec.rs` for the file layout
+const AVL_MAGIC: &[u8; 4] = b"AVLT";
+
+fn count_nodes<T>(tree: &AvlTreeNode<T>) -> u32 {
+    match tree {
+        None => 0,
+        Some(node) => 1 + count_nodes(&node.left) + count_nodes(&node.right),
+    }
+}
+
+fn encode_node<T: Ord + BinaryKey>(tree: &AvlTreeNode<T>, out: &mut Vec<u8>) {
+    match tree {
+        None => out.push(codec::TAG_EMPTY),
+        Some(node) => {
+            out.push(codec::TAG_NODE);
+            codec::write_varint(out, node.height as u64);
+            node.val.write_key(out);
+            encode_node(&node.left, out);
+            encode_node(&node.right, out);
+        }
+    }
+}
+
+fn decode_node<T: Ord + BinaryKey>(input: &mut ByteReader, depth: usize, count: &mut u32) -> Result<AvlTreeNode<T>, CodecError> {
+    if depth > codec::MAX_DEPTH {
+        return Err(CodecError::InvalidTree("tree is too deep".to_string()));
+    }
+    match input.read_u8()? {
+        codec::TAG_EMPTY => Ok(None),
+        codec::TAG_NODE => {
+            let height = input.read_varint()?;
+            if height == 0 || height > codec::MAX_DEPTH as u64 {
+                return Err(CodecError::InvalidTree(format!("invalid node height {}", height)));
+            }
+            let val = T::read_key(input)?;
+            *count += 1;
+            let left = decode_node(input, depth + 1, count)?;
+            let right = decode_node(input, depth + 1, count)?;
+            let size = 1 + left.as_ref().map_or(0, |n| n.size) + right.as_ref().map_or(0, |n| n.size);
+            Ok(Some(Box::new(TreeNode {
+                val,
+                count: 1,
+                size,
+                height: height as i32,
+                left,
+                right,
+            })))
+        }
+        tag => Err(CodecError::InvalidTag(tag)),
+    }
+}
+
+impl<T: Ord + BinaryKey> TreeCodec for AvlTreeNode<T> {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = codec::write_header(AVL_MAGIC, T::KEY_TAG, count_nodes(self));
+        encode_node(self, &mut out);
+        codec::finish(&mut out);
+        out
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, CodecError> {
+        let (node_count, mut input) = codec::open(bytes, AVL_MAGIC, T::KEY_TAG)?;
+        let mut decoded = 0;
+        let tree: Self = decode_node(&mut input, 0, &mut decoded)?;
+        if !input.is_empty() {
+            return Err(CodecError::TrailingBytes);
+        }
+        if decoded != node_count {
+            return Err(CodecError::InvalidTree(format!("header says {} nodes, found {}", node_count, decoded)));
+        }
+        // The heights are stored, not recomputed, so make sure they still describe a balanced tree
+        if !tree.validate_tree() {
+            return Err(CodecError::InvalidTree("not a balanced AVL tree".to_string()));
+        }
+        Ok(tree)
+    }
+}
+
+
+// JSON import / export, see `json.rs`
+fn node_to_json<T: Ord + JsonKey>(tree: &AvlTreeNode<T>) -> JsonValue {
+    match tree {
+        None => JsonValue::Null,
+        Some(node) => json::node_object(
+            node.val.to_json(),
+            ("height", JsonValue::Number(node.height.to_string())),
+            node_to_json(&node.left),
+            node_to_json(&node.right),
+        ),
+    }
+}
+
+// The "height" field is optional, but if it is given it has to be right
+fn node_from_json<T: Ord + JsonKey>(value: &JsonValue) -> Result<AvlTreeNode<T>, JsonError> {
+    if value == &JsonValue::Null {
+        return Ok(None);
+    }
+    let val = T::from_json(json::field(value, "key")?)?;
+    let left: AvlTreeNode<T> = node_from_json(json::field(value, "left")?)?;
+    let right: AvlTreeNode<T> = node_from_json(json::field(value, "right")?)?;
+    let height = max(left.as_ref().map_or(0, |n| n.height), right.as_ref().map_or(0, |n| n.height)) + 1;
+    match value.get("height") {
+        None | Some(JsonValue::Null) => {}
+        Some(JsonValue::Number(h)) if h.parse() == Ok(height) => {}
+        Some(other) => {
+            return Err(JsonError::InvalidTree(format!("node height {} does not match the actual height {}", other, height)));
+        }
+    }
+    let size = 1 + left.as_ref().map_or(0, |n| n.size) + right.as_ref().map_or(0, |n| n.size);
+    Ok(Some(Box::new(TreeNode { val, count: 1, size, height, left, right })))
+}
+
+const NOT_AN_AVL_TREE: &str = "keys out of order or not a balanced AVL tree";
+
+impl<T: Ord + JsonKey> TreeJson for AvlTreeNode<T> {
+    fn to_json_nested(&self) -> String {
+        node_to_json(self).to_string()
+    }
+
+    fn to_json_sorted(&self) -> String {
+        let mut keys = Vec::new();
+        self.inorder_to_list(&mut keys);
+        JsonValue::Array(keys.iter().map(|k| k.to_json()).collect()).to_string()
+    }
+
+    fn from_json_nested(text: &str) -> Result<Self, JsonError> {
+        let tree: Self = node_from_json(&JsonValue::parse(text)?)?;
+        // checks the heights, the balance and the order of all keys, not only of a node and its children
+        if !tree.validate_tree() {
+            return Err(JsonError::InvalidTree(NOT_AN_AVL_TREE.to_string()));
+        }
+        Ok(tree)
+    }
+
+    fn from_json_sorted(text: &str) -> Result<Self, JsonError> {
+        let mut tree: Self = AvlTree::generate_empty_tree();
+        for key in json::parse_sorted_keys(text)? {
+            tree.insert_node(key);
+        }
+        Ok(tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A node with the given height and children, no checks at all, so tests can build broken trees
+    fn node(val: i32, height: i32, left: AvlTreeNode<i32>, right: AvlTreeNode<i32>) -> AvlTreeNode<i32> {
+        let size = 1 + left.node_size() + right.node_size();
+        Some(Box::new(TreeNode { val, count: 1, size, height, left, right }))
+    }
+
+    //        10
+    //      /    \
+    //     5      15
+    //    / \    /
+    //   1  20  12
+    // Heights and balance are fine and every node is ordered with its own children,
+    // but 20 is on the left of 10
+    fn grandchild_out_of_order() -> AvlTreeNode<i32> {
+        node(10, 3, node(5, 2, node(1, 1, None, None), node(20, 1, None, None)), node(15, 2, node(12, 1, None, None), None))
+    }
+
+    #[test]
+    fn keys_are_checked_against_all_ancestors() {
+        assert!(!grandchild_out_of_order().validate_tree());
+    }
+
+    #[test]
+    fn decoding_rejects_a_key_on_the_wrong_side_of_an_ancestor() {
+        let bytes = grandchild_out_of_order().to_bytes();
+        match AvlTreeNode::<i32>::from_bytes(&bytes) {
+            Err(CodecError::InvalidTree(_)) => {}
+            other => panic!("expected an invalid tree, got {:?}", other.map(|tree| tree.pre_order_traverse().into_iter().copied().collect::<Vec<_>>())),
+        }
+    }
+
+    fn from_json(text: &str) -> Result<AvlTreeNode<i32>, JsonError> {
+        TreeJson::from_json_nested(text)
+    }
+
+    fn invalid_tree(res: Result<AvlTreeNode<i32>, JsonError>) -> String {
+        match res {
+            Err(JsonError::InvalidTree(message)) => message,
+            Err(e) => panic!("expected an invalid tree, got {}", e),
+            Ok(tree) => panic!("expected an invalid tree, got {:?}", tree.pre_order_traverse()),
+        }
+    }
+
+    #[test]
+    fn json_round_trips() {
+        let mut tree: AvlTreeNode<i32> = AvlTree::generate_empty_tree();
+        assert_eq!(tree.to_json_nested(), "null");
+        assert_eq!(tree.to_json_sorted(), "[]");
+        assert!(from_json("null").unwrap().is_none());
+        for i in [8, 3, 12, 1, 5, 10, 15, 4, 7, 20, -6] {
+            tree.insert_node(i);
+        }
+        tree.remove_node(&10);
+        let copy = from_json(&tree.to_json_nested()).unwrap();
+        assert_eq!(copy.pre_order_traverse(), tree.pre_order_traverse());
+        assert_eq!(copy.to_json_nested(), tree.to_json_nested());
+        assert!(copy.validate_tree());
+        let sorted: AvlTreeNode<i32> = TreeJson::from_json_sorted(&tree.to_json_sorted()).unwrap();
+        assert_eq!(sorted.in_order_traverse(), tree.in_order_traverse());
+        assert!(sorted.validate_tree());
+        let small = node(2, 2, node(1, 1, None, None), None);
+        assert_eq!(small.to_json_nested(), r#"{"key":2,"height":2,"left":{"key":1,"height":1,"left":null,"right":null},"right":null}"#);
+    }
+
+    #[test]
+    fn json_heights_are_optional() {
+        let mut tree = from_json(r#"{"key":2,"left":{"key":1,"left":null,"right":null},"right":null}"#).unwrap();
+        assert_eq!(tree.height_of_tree(), 2);
+        assert_eq!(tree.total_number_elements(), 2);
+        assert_eq!(
+            invalid_tree(from_json(r#"{"key":2,"height":1,"left":{"key":1,"left":null,"right":null},"right":null}"#)),
+            "node height 1 does not match the actual height 2"
+        );
+    }
+
+    #[test]
+    fn json_with_string_keys() {
+        let mut words: AvlTreeNode<String> = AvlTree::generate_empty_tree();
+        for word in ["pear", "apple", "fig", "say \"hi\""] {
+            words.insert_node(word.to_string());
+        }
+        let copy: AvlTreeNode<String> = TreeJson::from_json_nested(&words.to_json_nested()).unwrap();
+        assert_eq!(copy.pre_order_traverse(), words.pre_order_traverse());
+        assert!(matches!(<AvlTreeNode<String> as TreeJson>::from_json_sorted("[1,2]"), Err(JsonError::Schema(_))));
+    }
+
+    #[test]
+    fn json_that_is_not_a_tree_is_rejected() {
+        assert!(matches!(from_json(r#"{"key":1,"left":null"#), Err(JsonError::Syntax { .. })));
+        assert!(matches!(from_json("[1,2]"), Err(JsonError::Schema(_))));
+        assert!(matches!(from_json(r#"{"key":1,"left":null}"#), Err(JsonError::Schema(_))));
+        assert!(matches!(from_json(r#"{"key":"1","left":null,"right":null}"#), Err(JsonError::Schema(_))));
+        assert!(matches!(
+            from_json(r#"{"key":1,"left":null,"right":null,"key":2}"#),
+            Err(JsonError::Syntax { .. })
+        ));
+    }
+
+    #[test]
+    fn json_with_duplicate_keys_is_rejected() {
+        assert_eq!(
+            invalid_tree(from_json(&node(1, 2, node(1, 1, None, None), None).to_json_nested())),
+            NOT_AN_AVL_TREE
+        );
+        assert!(matches!(<AvlTreeNode<i32> as TreeJson>::from_json_sorted("[1,1]"), Err(JsonError::Schema(_))));
+    }
+
+    #[test]
+    fn json_out_of_order_is_rejected() {
+        assert_eq!(invalid_tree(from_json(&grandchild_out_of_order().to_json_nested())), NOT_AN_AVL_TREE);
+        assert_eq!(
+            invalid_tree(from_json(&node(1, 2, node(2, 1, None, None), None).to_json_nested())),
+            NOT_AN_AVL_TREE
+        );
+    }
+
+    #[test]
+    fn json_unbalanced_is_rejected() {
+        // 1 -> 2 -> 3, ordered and with the right heights, but the root leans two levels to the right
+        let chain = node(1, 3, None, node(2, 2, None, node(3, 1, None, None)));
+        assert_eq!(invalid_tree(from_json(&chain.to_json_nested())), NOT_AN_AVL_TREE);
+    }
+
+    const NOT_A_TRAVERSAL: &str = "The sequence is not a traversal of a binary search tree with unique keys";
+
+    fn pre_order(tree: &AvlTreeNode<i32>) -> Vec<i32> {
+        tree.pre_order_traverse().into_iter().copied().collect()
+    }
+
+    fn post_order(tree: &AvlTreeNode<i32>) -> Vec<i32> {
+        tree.post_order_traverse().into_iter().copied().collect()
+    }
+
+    fn rebuild_pre(keys: &[i32]) -> Result<AvlTreeNode<i32>, String> {
+        AvlTree::from_pre_order(keys)
+    }
+
+    fn rebuild_post(keys: &[i32]) -> Result<AvlTreeNode<i32>, String> {
+        AvlTree::from_post_order(keys)
+    }
+
+    #[test]
+    fn rebuilt_trees_are_the_same_tree() {
+        assert!(rebuild_pre(&[]).unwrap().is_none());
+        assert!(rebuild_post(&[]).unwrap().is_none());
+        let mut tree: AvlTreeNode<i32> = AvlTree::generate_empty_tree();
+        for i in 0..200 {
+            tree.insert_node(i * 37 % 211);
+            if i % 5 == 0 {
+                tree.remove_node(&(i * 11 % 211));
+            }
+            for copy in [rebuild_pre(&pre_order(&tree)).unwrap(), rebuild_post(&post_order(&tree)).unwrap()] {
+                // same keys in the same places with the same heights
+                assert_eq!(copy.to_json_nested(), tree.to_json_nested());
+                assert!(copy.validate_tree());
+            }
+        }
+    }
+
+    #[test]
+    fn rebuilding_needs_a_real_traversal() {
+        // 2 is the root and 3 its right child, there is no place left for 1
+        assert_eq!(rebuild_pre(&[2, 3, 1]).err().as_deref(), Some(NOT_A_TRAVERSAL));
+        assert_eq!(rebuild_post(&[3, 1, 2]).err().as_deref(), Some(NOT_A_TRAVERSAL));
+        // a key smaller than the root after the right subtree has started
+        assert_eq!(rebuild_pre(&[4, 2, 6, 5, 1]).err().as_deref(), Some(NOT_A_TRAVERSAL));
+        assert_eq!(rebuild_post(&[1, 5, 6, 2, 4]).err().as_deref(), Some(NOT_A_TRAVERSAL));
+    }
+
+    #[test]
+    fn rebuilding_refuses_duplicates() {
+        assert_eq!(rebuild_pre(&[2, 1, 1]).err().as_deref(), Some(NOT_A_TRAVERSAL));
+        assert_eq!(rebuild_pre(&[2, 2]).err().as_deref(), Some(NOT_A_TRAVERSAL));
+        assert_eq!(rebuild_post(&[1, 3, 3, 2]).err().as_deref(), Some(NOT_A_TRAVERSAL));
+    }
+
+    #[test]
+    fn rebuilding_refuses_unbalanced_trees() {
+        let unbalanced = Some("The sequence does not describe a balanced AVL tree");
+        assert_eq!(rebuild_pre(&[1, 2, 3]).err().as_deref(), unbalanced);
+        assert_eq!(rebuild_post(&[3, 2, 1]).err().as_deref(), unbalanced);
+        // a long sorted run would be a list, it is refused before the recursion gets deep
+        let sorted: Vec<i32> = (0..100_000).collect();
+        let too_deep = Some("The sequence does not describe a balanced tree");
+        assert_eq!(rebuild_pre(&sorted).err().as_deref(), too_deep);
+        assert_eq!(rebuild_post(&sorted).err().as_deref(), too_deep);
+    }
+
+    #[test]
+    fn string_keys_are_looked_up_as_str() {
+        let mut words: AvlTreeNode<String> = AvlTree::generate_empty_tree();
+        for word in ["pear", "apple", "fig", "kiwi", "banana"] {
+            words.insert_node(word.to_string());
+        }
+        assert!(words.exist_or_not("apple"));
+        assert!(!words.exist_or_not("Apple"));
+        assert_eq!(words.range("b", "g"), vec!["banana", "fig"]);
+        assert!(words.remove_node("fig"));
+        assert!(!words.remove_node("fig"));
+        assert!(words.delete_node("kiwi").is_some());
+        assert!(words.replace_node("pear", "plum".to_string()));
+        assert!(!words.replace_node("pear", "grape".to_string()));
+        words.update_node("plum", "cherry".to_string());
+        assert_eq!(words.in_order_traverse(), vec!["apple", "banana", "cherry"]);
+        assert!(words.validate_tree());
+    }
+
+    #[test]
+    fn traversals_return_the_keys_in_the_tree() {
+        let mut words: AvlTreeNode<String> = AvlTree::generate_empty_tree();
+        words.insert_node("a".repeat(1000));
+        let first: *const String = words.in_order_traverse()[0];
+        let again: *const String = words.pre_order_traverse()[0];
+        assert_eq!(first, again);
+    }
+
+    #[test]
+    fn byte_keys_are_looked_up_as_slices() {
+        let mut blobs: AvlTreeNode<Vec<u8>> = AvlTree::generate_empty_tree();
+        for blob in [vec![3, 1], vec![1], vec![2, 2, 2], vec![]] {
+            blobs.insert_node(blob);
+        }
+        let empty: &[u8] = &[];
+        assert!(blobs.exist_or_not(empty));
+        assert!(blobs.exist_or_not(&[2u8, 2, 2][..]));
+        assert!(blobs.remove_node(&[3u8, 1][..]));
+        assert_eq!(blobs.post_order_traverse().len(), 3);
+    }
+
+    // Neither Copy, Clone nor Debug, ordered by id only
+    struct Record {
+        id: u32,
+        payload: Vec<u8>,
+    }
+
+    impl PartialEq for Record {
+        fn eq(&self, other: &Self) -> bool {
+            self.id == other.id
+        }
+    }
+
+    impl Eq for Record {}
+
+    impl PartialOrd for Record {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Record {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.id.cmp(&other.id)
+        }
+    }
+
+    #[test]
+    fn keys_need_neither_copy_nor_debug() {
+        let record = |id: u32| Record { id, payload: vec![id as u8; 4] };
+        let mut records: AvlTreeNode<Record> = AvlTree::generate_empty_tree();
+        for id in [5, 2, 8, 1, 9, 3] {
+            records.insert_node(record(id));
+        }
+        assert!(records.exist_or_not(&record(8)));
+        assert!(records.remove_node(&record(2)));
+        assert!(records.replace_node(&record(9), record(7)));
+        let ids: Vec<u32> = records.in_order_traverse().iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![1, 3, 5, 7, 8]);
+        assert_eq!(records.range(&record(3), &record(7)).iter().map(|r| r.payload[0]).collect::<Vec<u8>>(), vec![3, 5, 7]);
+        assert!(records.validate_tree());
+        // the plain operations show every key as `?` in a trace
+        let (_, events) = trace::record(|| records.exist_or_not(&record(1)));
+        assert_eq!(events[0], TraceEvent::Compare { key: "?".to_string(), node: "?".to_string() });
+    }
+
+    #[test]
+    fn a_comparator_tree_finds_a_duplicate_on_one_walk() {
+        let mut tree = AvlTreeBy::with_comparator(Natural);
+        for key in [2, 1, 3] {
+            assert!(tree.insert_node(key));
+        }
+        // 3 is compared with 2 and then with itself, and nothing changes
+        let (inserted, counted) = stats::measure(|| tree.insert_node(3));
+        assert!(!inserted);
+        assert_eq!(counted.comparisons, 2);
+        assert_eq!(tree.total_number_elements(), 3);
+        assert!(tree.remove_node(&1) && tree.insert_node(4) && tree.insert_node(5));
+        assert_eq!(tree.total_number_elements(), 4);
+        assert!(tree.validate_tree());
     }
 }
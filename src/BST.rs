@@ -1,69 +1,243 @@
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::iter::repeat_n;
+
+use crate::order::{Comparator, Natural};
+use crate::stats;
+
 pub type Node<T> = Option<Box<Bstree<T>>>;
 
 #[derive(Debug)]
 pub struct Bstree<T> {
     val: T,
+    // how many times val was inserted, always 1 outside of BstMultiset
+    count: usize,
+    // sum of the counts in this subtree, lets BstMultiset find ranks along one path
+    size: usize,
     left: Node<T>,
     right: Node<T>,
 }
 
-pub trait BinarySearchTree<T: Copy + PartialOrd> {
+pub trait BinarySearchTree<T: Copy + Ord> {
     fn gen_empty_tree() -> Self;
     fn new_node(&mut self, val: T) -> Self;
     fn insert_node(&mut self, val: T);
+    fn delete_node(&mut self, val: T) -> bool;
     fn search_node(&self, val: T) -> bool;
     fn search_node_optimize(&self, val: T) -> bool;
+    fn height_of_tree(&self) -> i32;
+    fn number_of_leaves(&self) -> i32;
+    fn in_order_traverse(&self) -> Vec<T>;
+    fn tree_diagram(&self) -> Vec<String> where T: Debug;
 }
 
-impl<T> BinarySearchTree<T> for Node<T> where T: Copy + PartialOrd {
-    fn gen_empty_tree() -> Self {
-        Self::None
-    }
+// private function trait
+trait __BinarySearchTree<T: Copy> {
+    fn leaf(val: T) -> Self;
+    fn node_size(&self) -> usize;
+    fn update_size(&mut self);
+    fn do_insert<C: Comparator<T>>(&mut self, val: T, cmp: &C) -> bool;
+    fn do_delete<C: Comparator<T>>(&mut self, val: T, cmp: &C) -> bool;
+    fn do_search<C: Comparator<T>>(&self, val: T, cmp: &C) -> bool;
+    fn min_entry(&self) -> (T, usize);
+    fn change_count<C: Comparator<T>>(&mut self, val: T, grow: bool, cmp: &C) -> usize;
+    fn entries_to_list(&self, vec: &mut Vec<(T, usize)>);
+    fn inorder_to_list(&self, vec: &mut Vec<T>);
+    fn recursive_diagram(&self, prefix_space: &str, is_left: bool, child_prefix: &str, lines: &mut Vec<String>) where T: Debug;
+}
 
-    fn new_node(&mut self, val: T) -> Self {
+impl<T> __BinarySearchTree<T> for Node<T> where T: Copy {
+    fn leaf(val: T) -> Self {
+        stats::count_allocation();
         Some(Box::from(Bstree {
             val,
+            count: 1,
+            size: 1,
             left: None,
             right: None,
         }))
     }
 
-    fn insert_node(&mut self, val: T) {
+    fn node_size(&self) -> usize {
         match self {
-            None => *self = self.new_node(val),
+            None => 0,
+            Some(node) => node.size,
+        }
+    }
+
+    fn update_size(&mut self) {
+        if let Some(node) = self {
+            node.size = node.count + node.left.node_size() + node.right.node_size();
+        }
+    }
+
+    // false if the value is already in the tree
+    fn do_insert<C: Comparator<T>>(&mut self, val: T, cmp: &C) -> bool {
+        match self {
+            None => {
+                *self = Self::leaf(val);
+                true
+            }
             Some(node) => {
-                if val < node.val {
-                    node.left.insert_node(val);
-                } else if val > node.val {
-                    node.right.insert_node(val);
-                } else {
-                    println!("Node exists!");
+                stats::count_comparison();
+                let inserted = match cmp.compare(&val, &node.val) {
+                    Ordering::Less => node.left.do_insert(val, cmp),
+                    Ordering::Greater => node.right.do_insert(val, cmp),
+                    Ordering::Equal => false,
+                };
+                if inserted {
+                    node.size += 1;
                 }
+                inserted
             }
         }
     }
 
-    fn search_node(&self, val: T) -> bool {
+    fn do_delete<C: Comparator<T>>(&mut self, val: T, cmp: &C) -> bool {
+        let node = match self {
+            None => return false,
+            Some(node) => node,
+        };
+        stats::count_comparison();
+        let deleted = match cmp.compare(&val, &node.val) {
+            Ordering::Less => node.left.do_delete(val, cmp),
+            Ordering::Greater => node.right.do_delete(val, cmp),
+            Ordering::Equal => {
+                if node.left.is_none() || node.right.is_none() {
+                    // zero or one child: the child takes the place of the node
+                    let child = if node.left.is_some() { node.left.take() } else { node.right.take() };
+                    *self = child;
+                    return true;
+                }
+                // two children: take the smallest value of the right subtree and delete it there
+                let (successor, count) = node.right.min_entry();
+                node.val = successor;
+                node.count = count;
+                node.right.do_delete(successor, cmp)
+            }
+        };
+        // the removed node may have held several copies, so count the subtree again
+        self.update_size();
+        deleted
+    }
+
+    fn do_search<C: Comparator<T>>(&self, val: T, cmp: &C) -> bool {
         match self {
             None => false,
             Some(node) => {
-                if val < node.val {
-                    self.as_ref().unwrap().left.search_node(val)
-                } else if val > node.val {
-                    self.as_ref().unwrap().right.search_node(val)
-                } else if val == node.val {
-                    true
-                } else {
-                    false
+                stats::count_comparison();
+                match cmp.compare(&val, &node.val) {
+                    Ordering::Less => self.as_ref().unwrap().left.do_search(val, cmp),
+                    Ordering::Greater => self.as_ref().unwrap().right.do_search(val, cmp),
+                    Ordering::Equal => true,
                 }
             }
         }
     }
 
+    fn min_entry(&self) -> (T, usize) {
+        let mut node = self.as_ref().unwrap();
+        while let Some(left) = &node.left {
+            node = left;
+        }
+        (node.val, node.count)
+    }
+
+    // add a copy of val (or remove one), returns the count before, 0 if val is missing; never removes the last copy
+    fn change_count<C: Comparator<T>>(&mut self, val: T, grow: bool, cmp: &C) -> usize {
+        let node = match self {
+            None => return 0,
+            Some(node) => node,
+        };
+        stats::count_comparison();
+        let before = match cmp.compare(&val, &node.val) {
+            Ordering::Equal => {
+                let before = node.count;
+                if grow {
+                    node.count += 1;
+                } else if before > 1 {
+                    node.count -= 1;
+                }
+                before
+            }
+            Ordering::Less => node.left.change_count(val, grow, cmp),
+            Ordering::Greater => node.right.change_count(val, grow, cmp),
+        };
+        if grow && before > 0 {
+            node.size += 1;
+        } else if !grow && before > 1 {
+            node.size -= 1;
+        }
+        before
+    }
+
+    fn entries_to_list(&self, vec: &mut Vec<(T, usize)>) {
+        if let Some(node) = self {
+            node.left.entries_to_list(vec);
+            vec.push((node.val, node.count));
+            node.right.entries_to_list(vec);
+        }
+    }
+
+    fn inorder_to_list(&self, vec: &mut Vec<T>) {
+        if let Some(node) = self {
+            node.left.inorder_to_list(vec);
+            vec.extend(repeat_n(node.val, node.count));
+            node.right.inorder_to_list(vec);
+        }
+    }
+
+    fn recursive_diagram(&self, prefix_space: &str, is_left: bool, child_prefix: &str, lines: &mut Vec<String>) where T: Debug {
+        let prefix_current = if is_left { "├ " } else { "└ " };
+        let node = match self {
+            None => {
+                lines.push(format!("{}{}{} null", prefix_space, prefix_current, child_prefix));
+                return;
+            }
+            Some(node) => node,
+        };
+        let copies = if node.count > 1 { format!(" (x{})", node.count) } else { String::new() };
+        lines.push(format!("{}{}{} {:?}{}", prefix_space, prefix_current, child_prefix, node.val, copies));
+        let prefix_space = format!("{}{}", prefix_space, if is_left { "| " } else { "  " });
+        node.left.recursive_diagram(&prefix_space, true, "L ", lines);
+        node.right.recursive_diagram(&prefix_space, false, "R ", lines);
+    }
+}
+
+impl<T> BinarySearchTree<T> for Node<T> where T: Copy + Ord {
+    fn gen_empty_tree() -> Self {
+        Self::None
+    }
+
+    fn new_node(&mut self, val: T) -> Self {
+        Self::leaf(val)
+    }
+
+    fn insert_node(&mut self, val: T) {
+        let _op = stats::begin_operation();
+        if !self.do_insert(val, &Natural) {
+            println!("Node exists!");
+        }
+    }
+
+    fn delete_node(&mut self, val: T) -> bool {
+        let _op = stats::begin_operation();
+        self.do_delete(val, &Natural)
+    }
+
+    fn search_node(&self, val: T) -> bool {
+        let _op = stats::begin_operation();
+        self.do_search(val, &Natural)
+    }
+
     fn search_node_optimize(&self, val: T) -> bool {
+        let _op = stats::begin_operation();
         let mut path = Vec::new();
         path.push(self);
-        while let Some(&node) = path.get(0) {
+        while let Some(&node) = path.first() {
+            if node.is_some() {
+                stats::count_comparison();
+            }
             if node.as_ref().is_some() && val < node.as_ref().unwrap().val {
                 path.pop();
                 path.push(&node.as_ref().unwrap().left);
@@ -78,4 +252,224 @@ impl<T> BinarySearchTree<T> for Node<T> where T: Copy + PartialOrd {
         }
         false
     }
-}
\ No newline at end of file
+    fn height_of_tree(&self) -> i32 {
+        match self {
+            None => 0,
+            Some(node) => 1 + node.left.height_of_tree().max(node.right.height_of_tree()),
+        }
+    }
+
+    fn number_of_leaves(&self) -> i32 {
+        match self {
+            None => 0,
+            Some(node) if node.left.is_none() && node.right.is_none() => 1,
+            Some(node) => node.left.number_of_leaves() + node.right.number_of_leaves(),
+        }
+    }
+
+    fn in_order_traverse(&self) -> Vec<T> {
+        let mut vec = Vec::new();
+        self.inorder_to_list(&mut vec);
+        vec
+    }
+
+    fn tree_diagram(&self) -> Vec<String> where T: Debug {
+        let mut lines = Vec::new();
+        if self.is_some() {
+            self.recursive_diagram("", true, "Root", &mut lines);
+        }
+        lines
+    }
+}
+
+// A binary search tree ordered by a comparator (see `order.rs`) instead of the order of the values themselves
+pub struct BstBy<T, C> {
+    root: Node<T>,
+    cmp: C,
+}
+
+impl<T: Copy, C: Comparator<T>> BstBy<T, C> {
+    pub fn with_comparator(cmp: C) -> Self {
+        BstBy { root: None, cmp }
+    }
+
+    pub fn comparator(&self) -> &C {
+        &self.cmp
+    }
+
+    // false if a value the comparator calls equal is already in the tree
+    pub fn insert_node(&mut self, val: T) -> bool {
+        let _op = stats::begin_operation();
+        self.root.do_insert(val, &self.cmp)
+    }
+
+    pub fn delete_node(&mut self, val: T) -> bool {
+        let _op = stats::begin_operation();
+        self.root.do_delete(val, &self.cmp)
+    }
+
+    pub fn search_node(&self, val: T) -> bool {
+        let _op = stats::begin_operation();
+        self.root.do_search(val, &self.cmp)
+    }
+
+    pub fn in_order_traverse(&self) -> Vec<T> {
+        let mut vec = Vec::new();
+        self.root.inorder_to_list(&mut vec);
+        vec
+    }
+
+    pub fn tree_diagram(&self) -> Vec<String> where T: Debug {
+        let mut lines = Vec::new();
+        if self.root.is_some() {
+            self.root.recursive_diagram("", true, "Root", &mut lines);
+        }
+        lines
+    }
+}
+
+// A binary search tree that keeps duplicates: a value inserted again is counted by its node instead of refused
+//
+// Traversals, `len`, `rank` and `select` count every copy of a value.
+pub struct BstMultiset<T, C = Natural> {
+    root: Node<T>,
+    cmp: C,
+}
+
+impl<T: Copy + Ord> BstMultiset<T> {
+    pub fn new() -> Self {
+        BstMultiset { root: None, cmp: Natural }
+    }
+}
+
+impl<T: Copy + Ord> Default for BstMultiset<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Copy, C: Comparator<T>> BstMultiset<T, C> {
+    pub fn with_comparator(cmp: C) -> Self {
+        BstMultiset { root: None, cmp }
+    }
+
+    pub fn comparator(&self) -> &C {
+        &self.cmp
+    }
+
+    // the number of copies of val after inserting it
+    pub fn insert(&mut self, val: T) -> usize {
+        let _op = stats::begin_operation();
+        let before = self.root.change_count(val, true, &self.cmp);
+        if before > 0 {
+            return before + 1;
+        }
+        self.root.do_insert(val, &self.cmp);
+        1
+    }
+
+    // remove one copy of val, false if there is none
+    pub fn remove_one(&mut self, val: T) -> bool {
+        let _op = stats::begin_operation();
+        match self.root.change_count(val, false, &self.cmp) {
+            0 => false,
+            1 => self.root.do_delete(val, &self.cmp),
+            _ => true,
+        }
+    }
+
+    // remove every copy of val, returns how many there were
+    pub fn remove_all(&mut self, val: T) -> usize {
+        let _op = stats::begin_operation();
+        let count = self.count(val);
+        if count > 0 {
+            self.root.do_delete(val, &self.cmp);
+        }
+        count
+    }
+
+    pub fn count(&self, val: T) -> usize {
+        let _op = stats::begin_operation();
+        let mut node = &self.root;
+        while let Some(current) = node {
+            stats::count_comparison();
+            node = match self.cmp.compare(&val, &current.val) {
+                Ordering::Equal => return current.count,
+                Ordering::Less => &current.left,
+                Ordering::Greater => &current.right,
+            };
+        }
+        0
+    }
+
+    pub fn contains(&self, val: T) -> bool {
+        self.count(val) > 0
+    }
+
+    // every copy counted
+    pub fn len(&self) -> usize {
+        self.root.node_size()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    // every different value once with its count, in order
+    pub fn entries(&self) -> Vec<(T, usize)> {
+        let mut vec = Vec::new();
+        self.root.entries_to_list(&mut vec);
+        vec
+    }
+
+    // how many copies of values smaller than val there are
+    pub fn rank(&self, val: T) -> usize {
+        let _op = stats::begin_operation();
+        let mut rank = 0;
+        let mut node = &self.root;
+        while let Some(current) = node {
+            stats::count_comparison();
+            node = match self.cmp.compare(&val, &current.val) {
+                Ordering::Equal => return rank + current.left.node_size(),
+                Ordering::Less => &current.left,
+                Ordering::Greater => {
+                    rank += current.left.node_size() + current.count;
+                    &current.right
+                }
+            };
+        }
+        rank
+    }
+
+    // the value at position index (from 0) of in_order_traverse, None if there are not that many
+    pub fn select(&self, index: usize) -> Option<T> {
+        let mut index = index;
+        let mut node = &self.root;
+        while let Some(current) = node {
+            let left = current.left.node_size();
+            if index < left {
+                node = &current.left;
+            } else if index < left + current.count {
+                return Some(current.val);
+            } else {
+                index -= left + current.count;
+                node = &current.right;
+            }
+        }
+        None
+    }
+
+    pub fn in_order_traverse(&self) -> Vec<T> {
+        let mut vec = Vec::new();
+        self.root.inorder_to_list(&mut vec);
+        vec
+    }
+
+    pub fn tree_diagram(&self) -> Vec<String> where T: Debug {
+        let mut lines = Vec::new();
+        if self.root.is_some() {
+            self.root.recursive_diagram("", true, "Root", &mut lines);
+        }
+        lines
+    }
+}
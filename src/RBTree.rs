@@ -1,53 +1,92 @@
 use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::iter::repeat_n;
 use std::rc::Rc;
 use std::panic::panic_any;
 use std::slice::RChunks;
 
+use crate::codec::{self, BinaryKey, ByteReader, CodecError, TreeCodec};
+use crate::json::{self, JsonError, JsonKey, JsonValue, TreeJson};
+use crate::order::{Comparator, Natural};
+use crate::stats;
+use crate::trace::{self, DebugKeys, KeyText, TraceEvent};
+
+// The fix-up cases of `insert_rebalance` and `adjust_double_black`, case n is at index n - 1
+pub const INSERT_CASES: [&str; 4] = [
+    "uncle is red, recolor and move up",
+    "uncle is black and node is an inner child, rotate parent",
+    "uncle is black and node is an outer child, recolor and rotate grandparent",
+    "parent is the root, make it black",
+];
+pub const DELETE_CASES: [&str; 7] = [
+    "no sibling, move the double black up",
+    "sibling is red, recolor and rotate parent",
+    "black sibling with two black children, recolor sibling",
+    "black sibling is a left child with a red left child (LL)",
+    "black sibling is a left child with a red right child (LR)",
+    "black sibling is a right child with a red left child (RL)",
+    "black sibling is a right child with a red right child (RR)",
+];
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum NodeColor {
     Red,
     Black,
 }
 
-type Tree = Rc<RefCell<TreeNode<u32>>>;
-type RedBlackTree = Option<Tree>;
+type Tree<T> = Rc<RefCell<TreeNode<T>>>;
+type RedBlackTree<T> = Option<Tree<T>>;
 
 struct TreeNode<T> {
     pub color: NodeColor,
     pub key: T,
-    pub parent: RedBlackTree,
-    left: RedBlackTree,
-    right: RedBlackTree,
+    // how many times key was inserted, always 1 outside of RBMultiset
+    count: usize,
+    // sum of the counts in this subtree, lets RBMultiset find ranks along one path
+    size: usize,
+    pub parent: RedBlackTree<T>,
+    left: RedBlackTree<T>,
+    right: RedBlackTree<T>,
 }
 
-pub struct RBTree {
-    root: RedBlackTree,
+// Keys only need a total order, `u32` is what the CLI and the benchmarks have always used.
+// `C` decides that order, the keys' own one unless the tree was made `with_comparator`
+pub struct RBTree<T = u32, C = Natural> {
+    root: RedBlackTree<T>,
+    cmp: C,
 }
 
 /************TreeNode***************/
-impl<T: Ord + Clone> TreeNode<T> {
+impl<T: Clone + Debug> TreeNode<T> {
     fn new(val: T) -> Self {
+        stats::count_allocation();
         TreeNode {
             color: NodeColor::Red,
             key: val,
+            count: 1,
+            size: 1,
             parent: None,
             left: None,
             right: None,
         }
     }
-    fn inserted(pre_node: &mut Tree, val: u32) -> RedBlackTree {
-        if (*pre_node.borrow()).key == val {
+    fn inserted<C: Comparator<T>>(pre_node: &mut Tree<T>, val: T, cmp: &C) -> RedBlackTree<T> {
+        trace::emit(|| TraceEvent::Compare { key: DebugKeys.key_text(&val), node: DebugKeys.key_text(&pre_node.borrow().key) });
+        stats::count_comparison();
+        let order = cmp.compare(&pre_node.borrow().key, &val);
+        if order == Ordering::Equal {
             //println!("11");
             //return if val is already in the tree
             return Option::None;
-        } else if (*pre_node.borrow()).key > val {
+        } else if order == Ordering::Greater {
             //println!("22");
             //handle val left case
             let mut left_node = &mut (*pre_node.borrow_mut()).left;
             match &mut left_node {
 
                 //recursively
-                Some(node) => return TreeNode::<u32>::inserted(node, val),
+                Some(node) => return TreeNode::inserted(node, val, cmp),
 
                 //reaches the end
                 None => {
@@ -66,7 +105,7 @@ impl<T: Ord + Clone> TreeNode<T> {
             let mut right_node = &mut (*pre_node.borrow_mut()).right;
             match &mut right_node {
                 //recursively
-                Some(node) => return TreeNode::<u32>::inserted(node, val),
+                Some(node) => return TreeNode::inserted(node, val, cmp),
 
                 //reaches the end
                 None => {
@@ -78,47 +117,129 @@ impl<T: Ord + Clone> TreeNode<T> {
             }
         }
     }
+}
+
+// Size of a subtree, 0 when it is empty
+fn node_size<T>(node: &RedBlackTree<T>) -> usize {
+    match node {
+        None => 0,
+        Some(node) => node.borrow().size,
+    }
+}
+
+// Count the size of a node again from its children
+fn update_size<T>(node: &Tree<T>) {
+    let mut node = node.borrow_mut();
+    node.size = node.count + node_size(&node.left) + node_size(&node.right);
+}
 
-    fn insert_rebalance(root_node: &mut RedBlackTree, insert_node: &Tree) {
+// Count the sizes again from node up to the root, after a node was added or removed below node
+fn update_sizes_upward<T>(node: RedBlackTree<T>) {
+    let mut node = node;
+    while let Some(current) = node {
+        update_size(&current);
+        node = current.borrow().parent.clone();
+    }
+}
+
+/***********RbTree****************/
+impl<T: Ord + Clone + Debug> RBTree<T> {
+    pub fn new() -> Self {
+        RBTree {
+            root: None,
+            cmp: Natural,
+        }
+    }
+}
+
+impl<T: Clone + Debug, C: Comparator<T>> RBTree<T, C> {
+    // A tree ordered by `cmp` instead of the order of the keys themselves, see `order.rs`
+    pub fn with_comparator(cmp: C) -> Self {
+        RBTree {
+            root: None,
+            cmp,
+        }
+    }
+
+    pub fn comparator(&self) -> &C {
+        &self.cmp
+    }
+
+    pub fn insert_node(&mut self, val: T) -> bool {
+        let _op = stats::begin_operation();
+        //return a bool for testing
+        //println!("aa");
+        match &mut self.root {
+            Some(node) => {
+                //insert
+                let ins = TreeNode::inserted(node, val, &self.cmp);
+                if let Some(insert_node) = ins {
+                    // every ancestor has one more key, the rotations below keep the sizes right
+                    update_sizes_upward(Self::get_parent(&insert_node));
+                    //balance
+                    Self::insert_rebalance(&mut self.root, &insert_node);
+                    //self.len += 1;
+                    return true;
+                } else {
+                    return false;
+                }
+            }
+
+            //handle a new tree
+            None => {
+                //the root is always black
+                let mut root = TreeNode::new(val);
+                root.color = NodeColor::Black;
+                self.root = Some(Rc::new(RefCell::new(root)));
+                //self.len += 1;
+                return true;
+            }
+        }
+    }
+
+
+    fn insert_rebalance(root_node: &mut RedBlackTree<T>, insert_node: &Tree<T>) {
 
         let mut current = Rc::clone(insert_node);
-        while let Some(mut parent_node) = RBTree::get_parent(&current) {
+        while let Some(mut parent_node) = Self::get_parent(&current) {
 
             //check exist for parent_node
             if (*parent_node.borrow()).color == NodeColor::Red {
                 //let grandparent = parent_node.borrow().parent.as_ref().unwrap().upgrade().unwrap();
-                let grandparent = TreeNode::<u32>::get_grandparent(&current);
+                let grandparent = Self::get_grandparent(&current);
 
                 if let Some(grandparent) = grandparent {
 
-                    if RBTree::is_left_side(&parent_node) {
+                    if Self::is_left_side(&parent_node) {
                         //when uncle node is red
 
                         let mut uncle = (*grandparent.borrow()).right.clone();
                         if let Some(uncle) = uncle {
                             if (*uncle.borrow_mut()).color == NodeColor::Red {
                                 //when uncle node is red
-                                RBTree::reset_color(&mut &uncle, NodeColor::Black);
-                                RBTree::reset_color(&mut &parent_node, NodeColor::Black);
-                                RBTree::reset_color(&mut &grandparent, NodeColor::Red);
+                                trace::emit(|| TraceEvent::Case { operation: "insert", number: 1, description: INSERT_CASES[0] });
+                                Self::reset_color(&mut &uncle, NodeColor::Black);
+                                Self::reset_color(&mut &parent_node, NodeColor::Black);
+                                Self::reset_color(&mut &grandparent, NodeColor::Red);
                                 current = Rc::clone(&grandparent);
-                                println!("99");
                                 continue;
                             }
                         }
 
                         //when current node is the right child of parent_node and uncle node is black
-                        if !RBTree::is_left_side(&current) {
-                            RBTree::left_rotation(root_node, &parent_node);
+                        if !Self::is_left_side(&current) {
+                            trace::emit(|| TraceEvent::Case { operation: "insert", number: 2, description: INSERT_CASES[1] });
+                            Self::left_rotation(root_node, &parent_node);
                             let n = Rc::clone(&parent_node);
                             parent_node = Rc::clone(&current);
                             current = Rc::clone(&n);
                         }
 
                         //when current node is the left child of parent node and uncle node is black
-                        RBTree::reset_color(&mut &parent_node, NodeColor::Black);
-                        RBTree::reset_color(&mut &grandparent, NodeColor::Red);
-                        RBTree::right_rotation(root_node, &grandparent);
+                        trace::emit(|| TraceEvent::Case { operation: "insert", number: 3, description: INSERT_CASES[2] });
+                        Self::reset_color(&mut &parent_node, NodeColor::Black);
+                        Self::reset_color(&mut &grandparent, NodeColor::Red);
+                        Self::right_rotation(root_node, &grandparent);
 
                         //when parent is the right child
                     } else {
@@ -128,31 +249,35 @@ impl<T: Ord + Clone> TreeNode<T> {
                         let mut uncle = (*grandparent.borrow()).left.clone();
                         if let Some(uncle) = uncle {
                             if (*uncle.borrow()).color == NodeColor::Red {
-                                RBTree::reset_color(&mut &uncle, NodeColor::Black);
-                                RBTree::reset_color(&mut &parent_node, NodeColor::Black);
-                                RBTree::reset_color(&mut &grandparent, NodeColor::Red);
+                                trace::emit(|| TraceEvent::Case { operation: "insert", number: 1, description: INSERT_CASES[0] });
+                                Self::reset_color(&mut &uncle, NodeColor::Black);
+                                Self::reset_color(&mut &parent_node, NodeColor::Black);
+                                Self::reset_color(&mut &grandparent, NodeColor::Red);
                                 current = Rc::clone(&grandparent);
                                 continue;
                             }
                         }
                         //when current node is the left child of parent_node
                         //the uncle node is black
-                        if RBTree::is_left_side(&current) {
-                            RBTree::right_rotation(root_node, &parent_node);
+                        if Self::is_left_side(&current) {
+                            trace::emit(|| TraceEvent::Case { operation: "insert", number: 2, description: INSERT_CASES[1] });
+                            Self::right_rotation(root_node, &parent_node);
                             let n = Rc::clone(&parent_node);
                             parent_node = Rc::clone(&current);
                             current = Rc::clone(&n);
                         }
                         //when uncle node is black
                         //when current node is the right child of parent_node
-                        RBTree::reset_color(&mut &parent_node, NodeColor::Black);
-                        RBTree::reset_color(&mut &grandparent, NodeColor::Red);
-                        RBTree::left_rotation(root_node, &grandparent);
+                        trace::emit(|| TraceEvent::Case { operation: "insert", number: 3, description: INSERT_CASES[2] });
+                        Self::reset_color(&mut &parent_node, NodeColor::Black);
+                        Self::reset_color(&mut &grandparent, NodeColor::Red);
+                        Self::left_rotation(root_node, &grandparent);
                     }
                 }
                 else{
                     //break;
-                    RBTree::reset_color(&mut &parent_node, NodeColor::Black);
+                    trace::emit(|| TraceEvent::Case { operation: "insert", number: 4, description: INSERT_CASES[3] });
+                    Self::reset_color(&mut &parent_node, NodeColor::Black);
                 }
             } else {
                 //if parent_node node is black
@@ -160,74 +285,37 @@ impl<T: Ord + Clone> TreeNode<T> {
             }
         }
         //set root node black
-        RBTree::reset_color(&mut root_node.as_ref().unwrap(), NodeColor::Black);
+        Self::reset_color(&mut root_node.as_ref().unwrap(), NodeColor::Black);
         //root_node.color = NodeColor::Black;
     }
 
-    fn get_grandparent(node: &Tree) -> RedBlackTree {
+    fn get_grandparent(node: &Tree<T>) -> RedBlackTree<T> {
         // get_parent(node).and_then(|par| get_parent(&par))
-        let temp = RBTree::get_parent(node);
+        let temp = Self::get_parent(node);
         if let Some(new_parent) = temp {
             // println!("parent exists");
-            return RBTree::get_parent(&new_parent);
+            return Self::get_parent(&new_parent);
         } else {
             return Option::None;
         }
     }
-}
-
-/***********RbTree****************/
-impl RBTree {
-    pub fn new() -> Self {
-        RBTree {
-            root: None,
-        }
-    }
-
-    pub fn insert_node(&mut self, val: u32) -> bool {
-        //return a bool for testing
-        //println!("aa");
-        match &mut self.root {
-            Some(node) => {
-                //insert
-                let ins = TreeNode::<u32>::inserted(node, val);
-                if let Some(insert_node) = ins {
-                    //balance
-                    TreeNode::<u32>::insert_rebalance(&mut self.root, &insert_node);
-                    //self.len += 1;
-                    return true;
-                } else {
-                    return false;
-                }
-            }
-
-            //handle a new tree
-            None => {
-                //颜色？？
-                self.root = Some(Rc::new(RefCell::new(TreeNode::new(val))));
-                //self.len += 1;
-                return true;
-            }
-        }
-    }
-
 
-    fn is_left_side(option_node: &Tree) -> bool {
+    fn is_left_side(option_node: &Tree<T>) -> bool {
         let node = option_node.borrow();
         let parent_option = &node.parent.as_ref().unwrap();
         let parent_node = parent_option.borrow();
         match parent_node.left.as_ref() {
-            Some(x) => x.borrow().key == node.key,
+            Some(x) => Rc::ptr_eq(x, option_node),
             None => false,
         }
     }
 
-    fn get_sibiling_node(node_p: &Tree) -> RedBlackTree {
+    fn get_sibiling_node(node_p: &Tree<T>) -> RedBlackTree<T> {
         let node = node_p.borrow();
         if node.parent.is_some() {
             let parent = &node.parent.as_ref().unwrap();
             let parent_node = parent.borrow();
-            if RBTree::is_left_side(node_p) {
+            if Self::is_left_side(node_p) {
                 return parent_node.right.clone();
             }
             return parent_node.left.clone();
@@ -235,7 +323,7 @@ impl RBTree {
         return None;
     }
 
-    fn get_parent(node_p: &Tree) -> RedBlackTree {
+    fn get_parent(node_p: &Tree<T>) -> RedBlackTree<T> {
         let node = node_p.borrow();
         if node.parent.is_some() {
             return Some(Rc::clone(node.parent.as_ref().unwrap()));
@@ -243,38 +331,56 @@ impl RBTree {
         return None;
     }
 
-    fn get_color(node: &Tree) -> NodeColor {
+    fn get_color(node: &Tree<T>) -> NodeColor {
         let node = node.borrow();
         if node.color == NodeColor::Red {
             return NodeColor::Red;
         }
         NodeColor::Black
     }
-    fn has_red_child(node_p: &Tree) -> bool {
+    fn has_red_child(node_p: &Tree<T>) -> bool {
         let node = node_p.borrow();
-        if node.left.is_some() && RBTree::get_color(node.left.as_ref().unwrap()) == NodeColor::Red {
+        if node.left.is_some() && Self::get_color(node.left.as_ref().unwrap()) == NodeColor::Red {
             return true;
         }
-        if node.right.is_some() && RBTree::get_color(node.right.as_ref().unwrap()) == NodeColor::Red {
+        if node.right.is_some() && Self::get_color(node.right.as_ref().unwrap()) == NodeColor::Red {
             return true;
         }
         false
     }
-    fn get_key(node_p: &Tree) -> u32 {
+    // the key of a node together with its count
+    fn get_key(node_p: &Tree<T>) -> (T, usize) {
         let node = node_p.borrow();
-        node.key
+        (node.key.clone(), node.count)
     }
-    fn reset_color(node: &mut &Tree, new_color: NodeColor) {
-        let mut node = node.borrow_mut();
+    fn reset_color(node_p: &mut &Tree<T>, new_color: NodeColor) {
+        let mut node = node_p.borrow_mut();
+        if node.color == new_color {
+            return;
+        }
+        trace::emit(|| TraceEvent::Recolor { node: DebugKeys.key_text(&node.key), color: format!("{:?}", new_color) });
+        stats::count_recoloring();
         node.color = new_color;
+        drop(node);
+        Self::emit_diagram_from(node_p);
     }
-    fn private_get_number_leaves(node_op: &RedBlackTree, mut count: u32) -> u32 {
+    // show the whole tree in `trace::record_steps`, found from any of its nodes
+    fn emit_diagram_from(node: &Tree<T>) {
+        trace::emit_diagram(|| {
+            let mut root = Rc::clone(node);
+            while let Some(parent) = Self::get_parent(&root) {
+                root = parent;
+            }
+            Self::diagram_of(&Some(root))
+        });
+    }
+    fn private_get_number_leaves(node_op: &RedBlackTree<T>, mut count: u32) -> u32 {
         let node = node_op.as_ref().unwrap().borrow_mut();
         if node.left.is_some() {
-            count = RBTree::private_get_number_leaves(&node.left, count);
+            count = Self::private_get_number_leaves(&node.left, count);
         }
         if node.right.is_some() {
-            count = RBTree::private_get_number_leaves(&node.right, count);
+            count = Self::private_get_number_leaves(&node.right, count);
         }
         if node.left.is_none() && node.right.is_none() {
             count = count + 1;
@@ -290,18 +396,18 @@ impl RBTree {
         if self.is_empty() {
             return count;
         } else {
-            count = RBTree::private_get_number_leaves(&self.root, count);
+            count = Self::private_get_number_leaves(&self.root, count);
         }
         count
     }
 
-    fn private_get_height(node_op: &RedBlackTree) -> u32 {
+    fn private_get_height(node_op: &RedBlackTree<T>) -> u32 {
         if node_op.is_none() {
             return 0u32;
         }
         let node = node_op.as_ref().unwrap().borrow_mut();
-        let left_height: u32 = RBTree::private_get_height(&node.left);
-        let right_height: u32 = RBTree::private_get_height(&node.right);
+        let left_height: u32 = Self::private_get_height(&node.left);
+        let right_height: u32 = Self::private_get_height(&node.right);
         if left_height > right_height {
             return left_height + 1;
         }
@@ -311,9 +417,11 @@ impl RBTree {
         if self.is_empty() {
             return 0u32;
         }
-        RBTree::private_get_height(&self.root)
+        Self::private_get_height(&self.root)
     }
-    fn left_rotation(root: &mut RedBlackTree, node: &Tree) {
+    fn left_rotation(root: &mut RedBlackTree<T>, node: &Tree<T>) {
+        trace::emit(|| TraceEvent::RotateLeft { pivot: DebugKeys.key_text(&node.borrow().key) });
+        stats::count_rotation();
         {
             let parent_option = &node.borrow().parent;
             let right_option = &node.borrow().right;
@@ -321,7 +429,7 @@ impl RBTree {
                 *root = right_option.clone();
             }
             if let Some(parent_node) = parent_option {
-                if RBTree::is_left_side(node) {
+                if Self::is_left_side(node) {
                     parent_node.borrow_mut().left = right_option.clone();
                 } else {
                     parent_node.borrow_mut().right = right_option.clone();
@@ -338,9 +446,15 @@ impl RBTree {
             node.borrow_mut().right = None;
         }
         right_node.borrow_mut().left = Some(node.clone());
+        // node is now below right_node, so its size goes first
+        update_size(node);
+        update_size(&right_node);
+        trace::emit_diagram(|| Self::diagram_of(root));
     }
 
-    fn right_rotation(root: &mut RedBlackTree, node: &Tree) {
+    fn right_rotation(root: &mut RedBlackTree<T>, node: &Tree<T>) {
+        trace::emit(|| TraceEvent::RotateRight { pivot: DebugKeys.key_text(&node.borrow().key) });
+        stats::count_rotation();
         {
             let parent_option = &node.borrow().parent;
             let left_option = &node.borrow().left;
@@ -348,7 +462,7 @@ impl RBTree {
                 *root = left_option.clone();
             }
             if let Some(parent_node) = parent_option {
-                if RBTree::is_left_side(node) {
+                if Self::is_left_side(node) {
                     parent_node.borrow_mut().left = left_option.clone();
                 } else {
                     parent_node.borrow_mut().right = left_option.clone();
@@ -365,14 +479,17 @@ impl RBTree {
             node.borrow_mut().left = None;
         }
         left_node.borrow_mut().right = Some(node.clone());
+        update_size(node);
+        update_size(&left_node);
+        trace::emit_diagram(|| Self::diagram_of(root));
     }
-    fn find_right_child(node: RedBlackTree) -> RedBlackTree {
+    fn find_right_child(node: RedBlackTree<T>) -> RedBlackTree<T> {
         if !node.as_ref().unwrap().borrow().right.is_none() {
             return Self::find_right_child(node.as_ref().unwrap().borrow().right.clone());
         }
         return node;
     }
-    fn find_replace_node(node: &Tree) -> RedBlackTree {
+    fn find_replace_node(node: &Tree<T>) -> RedBlackTree<T> {
         let node = node.borrow();
         if node.left.is_some() && node.right.is_some() {
             return Self::find_right_child(node.left.clone());
@@ -384,7 +501,7 @@ impl RBTree {
             return None;
         }
     }
-    fn private_search(&mut self, val: u32) -> (bool, RedBlackTree) {
+    fn private_search(&self, val: &T) -> (bool, RedBlackTree<T>) {
         if self.root.is_none() {
             return (false, None);
         }
@@ -393,13 +510,16 @@ impl RBTree {
         while !option_children.is_none() {
             option_parent = option_children;
             let parent_node = option_parent.as_ref().unwrap();
-            let parent_node_val = parent_node.borrow().key;
-            if parent_node_val < val {
+            let parent_node_val = parent_node.borrow().key.clone();
+            trace::emit(|| TraceEvent::Compare { key: DebugKeys.key_text(val), node: DebugKeys.key_text(&parent_node_val) });
+            stats::count_comparison();
+            let order = self.cmp.compare(&parent_node_val, val);
+            if order == Ordering::Less {
                 option_children = match parent_node.borrow().right {
                     Some(ref option_node) => (Some(option_node.clone())),
                     None => None,
                 };
-            } else if parent_node_val > val {
+            } else if order == Ordering::Greater {
                 option_children = match parent_node.borrow().left {
                     Some(ref option_node) => (Some(option_node.clone())),
                     None => None,
@@ -410,9 +530,9 @@ impl RBTree {
         }
         return (false, option_parent);
     }
-    fn private_delete_node(&mut self, node_to_delete: &mut &Tree) -> Result<(), String> {
-        let replace_node = RBTree::find_replace_node(node_to_delete);
-        let replace_delete_black: bool = (replace_node.is_none() || RBTree::get_color(replace_node.as_ref().unwrap()) == NodeColor::Black) && RBTree::get_color(node_to_delete) == NodeColor::Black;
+    fn private_delete_node(&mut self, node_to_delete: &mut &Tree<T>) -> Result<(), String> {
+        let replace_node = Self::find_replace_node(node_to_delete);
+        let replace_delete_black: bool = (replace_node.is_none() || Self::get_color(replace_node.as_ref().unwrap()) == NodeColor::Black) && Self::get_color(node_to_delete) == NodeColor::Black;
         let parent = if node_to_delete.borrow().parent.is_some() {
             Some(Rc::clone(node_to_delete.borrow().parent.as_ref().unwrap()))
         } else {
@@ -429,11 +549,12 @@ impl RBTree {
                     //replace_node and node_to_delete are both black.node_to_delete is leaf
                     self.adjust_double_black(node_to_delete);
                 }
-                if RBTree::is_left_side(node_to_delete) {
+                if Self::is_left_side(node_to_delete) {
                     parent.as_ref().unwrap().borrow_mut().left = None;
                 } else {
                     parent.as_ref().unwrap().borrow_mut().right = None;
                 }
+                update_sizes_upward(parent);
             }
             return Ok(());
         }
@@ -441,14 +562,16 @@ impl RBTree {
         else if node_to_delete.borrow().left.is_none() || node_to_delete.borrow().right.is_none() {
             if node_to_delete.borrow().parent.is_none() {
                 //node_to_delete is root
-                let replace_key = RBTree::get_key(replace_node.as_ref().unwrap());
+                let (replace_key, replace_count) = Self::get_key(replace_node.as_ref().unwrap());
                 let mut root = self.root.as_ref().unwrap().borrow_mut();
                 root.key = replace_key;
+                root.count = replace_count;
+                root.size = replace_count;
                 root.left = None;
                 root.right = None;
             } else {
                 //reset the child
-                if RBTree::is_left_side(node_to_delete) {
+                if Self::is_left_side(node_to_delete) {
                     parent.as_ref().unwrap().borrow_mut().left = replace_node.clone();
                 } else {
                     parent.as_ref().unwrap().borrow_mut().right = replace_node.clone();
@@ -458,224 +581,887 @@ impl RBTree {
                 if replace_delete_black {
                     self.adjust_double_black(replace_node.as_ref().unwrap())
                 } else {
-                    RBTree::reset_color(&mut replace_node.as_ref().unwrap(), NodeColor::Black);
+                    Self::reset_color(&mut replace_node.as_ref().unwrap(), NodeColor::Black);
                 }
+                // the rotations keep parent above the removed node, so the sizes to fix are on its way up
+                update_sizes_upward(parent);
             }
             return Ok(());
         } else {
             // node_to_delete has 2 children, change the value of node_to_delete and use recursion to delete replace_node
-            let replace_key = RBTree::get_key(replace_node.as_ref().unwrap());
+            let (replace_key, replace_count) = Self::get_key(replace_node.as_ref().unwrap());
             node_to_delete.borrow_mut().key = replace_key;
+            node_to_delete.borrow_mut().count = replace_count;
             self.private_delete_node(&mut replace_node.as_ref().unwrap())?;
             return Ok(());
         }
     }
-    fn adjust_double_black(&mut self, node: &Tree) {
+    fn adjust_double_black(&mut self, node: &Tree<T>) {
         if node.borrow().parent.is_none() {
             return;
         }
-        let node_s = RBTree::get_sibiling_node(node);
-        let node_p = RBTree::get_parent(node);
+        let node_s = Self::get_sibiling_node(node);
+        let node_p = Self::get_parent(node);
 
         if node_s.is_none() {
+            trace::emit(|| TraceEvent::Case { operation: "delete", number: 1, description: DELETE_CASES[0] });
             self.adjust_double_black(&node_p.unwrap());
         } else {
-            if RBTree::get_color(node_s.as_ref().unwrap()) == NodeColor::Black {
+            if Self::get_color(node_s.as_ref().unwrap()) == NodeColor::Black {
                 //node_s is black
-                if RBTree::has_red_child(node_s.as_ref().unwrap()) {
+                if Self::has_red_child(node_s.as_ref().unwrap()) {
                     //the child of node_s at least is red
                     if node_s.as_ref().unwrap().borrow().left.is_some()
-                        && RBTree::get_color(node_s.as_ref().unwrap().borrow().left.as_ref().unwrap()) == NodeColor::Red {
-                        if RBTree::is_left_side(node_s.as_ref().unwrap()) {
+                        && Self::get_color(node_s.as_ref().unwrap().borrow().left.as_ref().unwrap()) == NodeColor::Red {
+                        if Self::is_left_side(node_s.as_ref().unwrap()) {
                             //node_s is left, and left child of node_s is red LL
-                            let s_color = RBTree::get_color(node_s.as_ref().unwrap());
-                            let p_color = RBTree::get_color(node_p.as_ref().unwrap());
+                            trace::emit(|| TraceEvent::Case { operation: "delete", number: 4, description: DELETE_CASES[3] });
+                            let s_color = Self::get_color(node_s.as_ref().unwrap());
+                            let p_color = Self::get_color(node_p.as_ref().unwrap());
                             //reset color of left_node_s to s_color, reset color of node_s to p_color
-                            RBTree::reset_color(&mut node_s.as_ref().unwrap().borrow_mut().left.as_ref().unwrap(), s_color);
-                            RBTree::reset_color(&mut node_s.as_ref().unwrap(), p_color);
+                            let child_s = node_s.as_ref().unwrap().borrow().left.clone().unwrap();
+                            Self::reset_color(&mut &child_s, s_color);
+                            Self::reset_color(&mut node_s.as_ref().unwrap(), p_color);
                             //do right rotation to node_p
-                            RBTree::right_rotation(&mut self.root, node_p.as_ref().unwrap());
-                            RBTree::reset_color(&mut node_p.as_ref().unwrap(), NodeColor::Black);
+                            Self::right_rotation(&mut self.root, node_p.as_ref().unwrap());
+                            Self::reset_color(&mut node_p.as_ref().unwrap(), NodeColor::Black);
                         } else {
                             //node_s is right, and left child of node_s is red RL
-                            let p_color = RBTree::get_color(node_p.as_ref().unwrap());
+                            trace::emit(|| TraceEvent::Case { operation: "delete", number: 6, description: DELETE_CASES[5] });
+                            let p_color = Self::get_color(node_p.as_ref().unwrap());
                             //reset the color of left child of node_s to p_color
-                            RBTree::reset_color(&mut node_s.as_ref().unwrap().borrow_mut().left.as_ref().unwrap(), p_color);
+                            let child_s = node_s.as_ref().unwrap().borrow().left.clone().unwrap();
+                            Self::reset_color(&mut &child_s, p_color);
                             //do right_rotation to node_s, do left_rotation to node_p
-                            RBTree::right_rotation(&mut self.root, node_s.as_ref().unwrap());
-                            RBTree::left_rotation(&mut self.root, node_p.as_ref().unwrap());
-                            RBTree::reset_color(&mut node_p.as_ref().unwrap(), NodeColor::Black);
+                            Self::right_rotation(&mut self.root, node_s.as_ref().unwrap());
+                            Self::left_rotation(&mut self.root, node_p.as_ref().unwrap());
+                            Self::reset_color(&mut node_p.as_ref().unwrap(), NodeColor::Black);
                         }
                     } else {
-                        if RBTree::is_left_side(node_s.as_ref().unwrap()) {
+                        if Self::is_left_side(node_s.as_ref().unwrap()) {
                             //node_s is left, the right child of node_s is red LR
-                            let p_color = RBTree::get_color(node_p.as_ref().unwrap());
+                            trace::emit(|| TraceEvent::Case { operation: "delete", number: 5, description: DELETE_CASES[4] });
+                            let p_color = Self::get_color(node_p.as_ref().unwrap());
                             //reset the color of right child to p_color
-                            RBTree::reset_color(&mut node_s.as_ref().unwrap().borrow_mut().right.as_ref().unwrap(), p_color);
+                            let child_s = node_s.as_ref().unwrap().borrow().right.clone().unwrap();
+                            Self::reset_color(&mut &child_s, p_color);
                             //do left_rotation to node_s, do right_rotation to node_p
-                            RBTree::left_rotation(&mut self.root, node_s.as_ref().unwrap());
-                            RBTree::right_rotation(&mut self.root, node_p.as_ref().unwrap());
-                            RBTree::reset_color(&mut node_p.as_ref().unwrap(), NodeColor::Black);
+                            Self::left_rotation(&mut self.root, node_s.as_ref().unwrap());
+                            Self::right_rotation(&mut self.root, node_p.as_ref().unwrap());
+                            Self::reset_color(&mut node_p.as_ref().unwrap(), NodeColor::Black);
                         } else {
                             //node_s is right, the right child of node_s is red RR
-                            let s_color = RBTree::get_color(node_s.as_ref().unwrap());
-                            let p_color = RBTree::get_color(node_p.as_ref().unwrap());
+                            trace::emit(|| TraceEvent::Case { operation: "delete", number: 7, description: DELETE_CASES[6] });
+                            let s_color = Self::get_color(node_s.as_ref().unwrap());
+                            let p_color = Self::get_color(node_p.as_ref().unwrap());
                             //reset the color of right child to s_color, reset the s_color to p_color
-                            RBTree::reset_color(&mut node_s.as_ref().unwrap().borrow_mut().right.as_ref().unwrap(), s_color);
-                            RBTree::reset_color(&mut node_s.as_ref().unwrap(), p_color);
+                            let child_s = node_s.as_ref().unwrap().borrow().right.clone().unwrap();
+                            Self::reset_color(&mut &child_s, s_color);
+                            Self::reset_color(&mut node_s.as_ref().unwrap(), p_color);
                             //do left_rotation to node_p
-                            RBTree::left_rotation(&mut self.root, node_p.as_ref().unwrap());
-                            RBTree::reset_color(&mut node_p.as_ref().unwrap(), NodeColor::Black);
+                            Self::left_rotation(&mut self.root, node_p.as_ref().unwrap());
+                            Self::reset_color(&mut node_p.as_ref().unwrap(), NodeColor::Black);
                         }
                     }
                 } else {
                     //2 black children
-                    RBTree::reset_color(&mut node_s.as_ref().unwrap(), NodeColor::Red);
-                    if RBTree::get_color(node_p.as_ref().unwrap()) == NodeColor::Black {
+                    trace::emit(|| TraceEvent::Case { operation: "delete", number: 3, description: DELETE_CASES[2] });
+                    Self::reset_color(&mut node_s.as_ref().unwrap(), NodeColor::Red);
+                    if Self::get_color(node_p.as_ref().unwrap()) == NodeColor::Black {
                         self.adjust_double_black(node_p.as_ref().unwrap());
                     } else {
-                        RBTree::reset_color(&mut node_p.as_ref().unwrap(), NodeColor::Black);
+                        Self::reset_color(&mut node_p.as_ref().unwrap(), NodeColor::Black);
                     }
                 }
             } else {
                 //node_s is red
                 //reset the node_s  be black, reset the node_p be red,
-                RBTree::reset_color(&mut node_p.as_ref().unwrap(), NodeColor::Red);
-                RBTree::reset_color(&mut node_s.as_ref().unwrap(), NodeColor::Black);
-                if RBTree::is_left_side(node_s.as_ref().unwrap()) {
+                trace::emit(|| TraceEvent::Case { operation: "delete", number: 2, description: DELETE_CASES[1] });
+                Self::reset_color(&mut node_p.as_ref().unwrap(), NodeColor::Red);
+                Self::reset_color(&mut node_s.as_ref().unwrap(), NodeColor::Black);
+                if Self::is_left_side(node_s.as_ref().unwrap()) {
                     //s is left child,node_p do right rotation
-                    RBTree::right_rotation(&mut self.root, node_p.as_ref().unwrap());
+                    Self::right_rotation(&mut self.root, node_p.as_ref().unwrap());
                 } else {
                     // s is right child,node_p do left rotation
-                    RBTree::left_rotation(&mut self.root, node_p.as_ref().unwrap());
+                    Self::left_rotation(&mut self.root, node_p.as_ref().unwrap());
                 }
                 self.adjust_double_black(node);
             }
         }
     }
-    pub fn search_node(&mut self, val: u32) -> Result<(), String> {
-        match self.private_search(val) {
+    pub fn search_node(&mut self, val: T) -> Result<(), String> {
+        let _op = stats::begin_operation();
+        match self.private_search(&val) {
             (false, _) => Err(format!("The node with val is not found").to_string()),
             (true, _) => Ok(()),
         }
     }
 
-    pub fn exist_or_not(&mut self,val:u32) -> bool{
-        return self.private_search(val).0;
+    pub fn exist_or_not(&mut self,val:T) -> bool{
+        let _op = stats::begin_operation();
+        return self.private_search(&val).0;
     }
 
-    pub fn update_node(&mut self,old_val:u32,new_val:u32) {
+    pub fn update_node(&mut self,old_val:T,new_val:T) {
+        let _op = stats::begin_operation();
         if self.is_empty(){
             println!("Tree is Empty! Add some nodes before update.");
         }
-        else if self.exist_or_not(old_val) == false {
+        else if self.exist_or_not(old_val.clone()) == false {
             println!("UPDATE FAILED: Node({:?}) doesn't exist!", old_val);
-        } else if old_val == new_val {
+        } else if self.cmp.compare(&old_val, &new_val) == Ordering::Equal {
             println!("UPDATE FAILED: New value and old value can not be same!");
         } else {
-            if self.exist_or_not(new_val) {
+            if self.exist_or_not(new_val.clone()) {
                 println!("UPDATE FAILED: New Node({:?}) exist!", new_val);
             } else {
-                self.delete(old_val);
-                self.insert_node(new_val);
+                self.delete(old_val.clone());
+                self.insert_node(new_val.clone());
                 println!("Node({:?}) has been replaced by Node({:?})", old_val, new_val);
                 }
             }
         }
 
+    // update_node without printing anything, false if old_val does not exist, new_val already exists or they are the same
+    pub fn replace_node(&mut self, old_val: T, new_val: T) -> bool {
+        let _op = stats::begin_operation();
+        if self.cmp.compare(&old_val, &new_val) == Ordering::Equal || !self.exist_or_not(old_val.clone()) || self.exist_or_not(new_val.clone()) {
+            return false;
+        }
+        let _ = self.delete(old_val);
+        self.insert_node(new_val)
+    }
 
-    pub fn delete(&mut self, val: u32) -> Result<(), String> {
+
+    pub fn delete(&mut self, val: T) -> Result<(), String> {
+        let _op = stats::begin_operation();
         if self.root.is_none() {
             return Err(format!("Tree is none").to_string());
         }
-        let (is_found, option_node_to_delete) = self.private_search(val);
+        let (is_found, option_node_to_delete) = self.private_search(&val);
         if !is_found {
             return Err(format!("The node with val is not found").to_string());
         }
         let mut node_to_delete = option_node_to_delete.as_ref().unwrap();
         self.private_delete_node(&mut node_to_delete)
     }
-    fn vec_nodes_in_order(&self) -> Vec<u32> {
+    fn vec_nodes_in_order(&self) -> Vec<T> {
         let mut vec = Vec::new();
         self.nodes_in_order(&self.root, &mut vec);
         vec
     }
-    fn nodes_in_order(&self, node: &RedBlackTree, vec: &mut Vec<u32>) {
+    fn nodes_in_order(&self, node: &RedBlackTree<T>, vec: &mut Vec<T>) {
         if node.is_none() {
             return;
         }
         let node = node.as_ref().unwrap().borrow_mut();
         self.nodes_in_order(&node.left, vec);
-        vec.push(node.key);
+        vec.extend(repeat_n(node.key.clone(), node.count));
         self.nodes_in_order(&node.right, vec);
     }
 
-    fn nodes_pre_order(&self, node: &RedBlackTree, vec: &mut Vec<u32>) {
+    // every different key once with its count
+    fn entries_in_order(&self, node: &RedBlackTree<T>, vec: &mut Vec<(T, usize)>) {
+        if let Some(node) = node {
+            let node = node.borrow();
+            self.entries_in_order(&node.left, vec);
+            vec.push((node.key.clone(), node.count));
+            self.entries_in_order(&node.right, vec);
+        }
+    }
+
+    fn nodes_pre_order(&self, node: &RedBlackTree<T>, vec: &mut Vec<T>) {
         if node.is_none() {
             return;
         }
         let node = node.as_ref().unwrap().borrow_mut();
-        vec.push(node.key);
+        vec.extend(repeat_n(node.key.clone(), node.count));
         self.nodes_pre_order(&node.left, vec);
         self.nodes_pre_order(&node.right, vec);
     }
 
-    fn nodes_post_order(&self, node: &RedBlackTree, vec: &mut Vec<u32>) {
+    fn nodes_post_order(&self, node: &RedBlackTree<T>, vec: &mut Vec<T>) {
         if node.is_none() {
             return;
         }
         let node = node.as_ref().unwrap().borrow_mut();
         self.nodes_post_order(&node.left, vec);
         self.nodes_post_order(&node.right, vec);
-        vec.push(node.key);
+        vec.extend(repeat_n(node.key.clone(), node.count));
     }
 
-    pub fn print_in_order_traversal(&self) -> Vec<u32>{
+    pub fn print_in_order_traversal(&self) -> Vec<T>{
         let mut vec = Vec::new();
         self.nodes_in_order(&self.root, &mut vec);
         vec
     }
 
-    pub fn print_pre_order_traversal(&self) -> Vec<u32>{
+    pub fn print_pre_order_traversal(&self) -> Vec<T>{
         let mut vec = Vec::new();
         self.nodes_pre_order(&self.root, &mut vec);
         vec
     }
 
-    pub fn print_post_order_traversal(&self) -> Vec<u32>{
+    pub fn print_post_order_traversal(&self) -> Vec<T>{
         let mut vec = Vec::new();
         self.nodes_post_order(&self.root, &mut vec);
         vec
     }
 
-    fn recursion_print(node: &RedBlackTree, pre_space: &String, is_left: bool, child_pre: String) {
+    fn nodes_in_range(&self, node: &RedBlackTree<T>, low: &T, high: &T, vec: &mut Vec<T>) {
+        if node.is_none() {
+            return;
+        }
+        let node = node.as_ref().unwrap().borrow();
+        let above_low = self.cmp.compare(low, &node.key);
+        let below_high = self.cmp.compare(&node.key, high);
+        //only visit the subtrees that can contain values inside the range
+        if above_low == Ordering::Less {
+            self.nodes_in_range(&node.left, low, high, vec);
+        }
+        if above_low != Ordering::Greater && below_high != Ordering::Greater {
+            vec.extend(repeat_n(node.key.clone(), node.count));
+        }
+        if below_high == Ordering::Less {
+            self.nodes_in_range(&node.right, low, high, vec);
+        }
+    }
+
+    //all values between low and high (both included), in order
+    pub fn range(&self, low: T, high: T) -> Vec<T> {
+        let mut vec = Vec::new();
+        self.nodes_in_range(&self.root, &low, &high, &mut vec);
+        vec
+    }
+
+    fn recursion_diagram(node: &RedBlackTree<T>, pre_space: &String, is_left: bool, child_pre: String, lines: &mut Vec<String>) {
         if node.is_none() {
             let none_pre = if is_left { "├ " } else { "└ " };
-            println!("{}{}{} {}", pre_space, none_pre, child_pre, "null");
+            lines.push(format!("{}{}{} {}", pre_space, none_pre, child_pre, "null"));
             return;
         }
         let node = node.as_ref().unwrap().borrow();
         let col = if node.color == NodeColor::Black { "Black" } else { "Red" };
         let pre_current = if is_left { "├ " } else { "└ " };
-        println!("{}{}{} {}:{}", pre_space, pre_current, child_pre, node.key, col);
+        let copies = if node.count > 1 { format!(" (x{})", node.count) } else { String::new() };
+        lines.push(format!("{}{}{} {:?}{}:{}", pre_space, pre_current, child_pre, node.key, copies, col));
 
         let pre_child = if is_left { "| " } else { " " };
         let mut pre_space = pre_space.to_owned();
         pre_space.push_str(&pre_child);
 
-        RBTree::recursion_print(&node.left, &pre_space, true, "L".to_string());
-        RBTree::recursion_print(&node.right, &pre_space, false, "R".to_string());
+        Self::recursion_diagram(&node.left, &pre_space, true, "L".to_string(), lines);
+        Self::recursion_diagram(&node.right, &pre_space, false, "R".to_string(), lines);
+    }
+
+    fn diagram_of(root: &RedBlackTree<T>) -> Vec<String> {
+        let mut lines = Vec::new();
+        Self::recursion_diagram(root, &"".to_string(), true, "Root".to_string(), &mut lines);
+        lines
+    }
+
+    // the lines of the diagram printed by print_tree
+    pub fn tree_diagram(&self) -> Vec<String> {
+        Self::diagram_of(&self.root)
     }
 
     pub fn print_tree(&self) {
         // println!("The RbTree will be printed in format <L/R> <Key>:<Color>");
         println!("\n================== TREE PRINT <Node:Color> ==================");
-        RBTree::recursion_print(&self.root, &"".to_string(), true, "Root".to_string());
+        for line in self.tree_diagram() {
+            println!("{}", line);
+        }
         println!("\n======================= FINISH PRINT ========================");
     }
 
+    // every node holds one key here, so the size of the root is the number of keys
     pub fn total_number_elements(&self) ->i32 {
-        let res_vec = RBTree::vec_nodes_in_order(self);
-        res_vec.len() as i32
+        node_size(&self.root) as i32
+    }
+
+    // Black height of a subtree (counting the nil leaves), None if a red-black rule is broken below this node
+    fn private_black_height(node_op: &RedBlackTree<T>) -> Option<u32> {
+        let node_p = match node_op {
+            None => return Some(1),
+            Some(node_p) => node_p,
+        };
+        let node = node_p.borrow();
+        if node.size != node.count + node_size(&node.left) + node_size(&node.right) {
+            return None;
+        }
+        for child in node.left.iter().chain(node.right.iter()) {
+            let child_node = child.borrow();
+            // every child has to point back to its parent
+            if !child_node.parent.as_ref().is_some_and(|parent| Rc::ptr_eq(parent, node_p)) {
+                return None;
+            }
+            // a red node can not have a red child
+            if node.color == NodeColor::Red && child_node.color == NodeColor::Red {
+                return None;
+            }
+        }
+        let left_height = Self::private_black_height(&node.left)?;
+        let right_height = Self::private_black_height(&node.right)?;
+        if left_height != right_height {
+            return None;
+        }
+        Some(left_height + if node.color == NodeColor::Black { 1 } else { 0 })
+    }
+
+    // Is it a valid red-black tree? (black root, no red-red, same black height on every path, search order)
+    pub fn validate_tree(&self) -> bool {
+        if let Some(root) = &self.root {
+            let root = root.borrow();
+            if root.color != NodeColor::Black || root.parent.is_some() {
+                return false;
+            }
+        }
+        let mut entries = Vec::new();
+        self.entries_in_order(&self.root, &mut entries);
+        Self::private_black_height(&self.root).is_some()
+            && entries.iter().all(|(_, count)| *count > 0)
+            && entries.windows(2).all(|w| self.cmp.compare(&w[0].0, &w[1].0) == Ordering::Less)
+    }
+
+    // Colors in the same order as `print_pre_order_traversal`, so both can be fed back into `from_pre_order`
+    pub fn pre_order_colors(&self) -> Vec<NodeColor> {
+        let mut vec = Vec::new();
+        Self::colors_pre_order(&self.root, &mut vec);
+        vec
+    }
+
+    fn colors_pre_order(node: &RedBlackTree<T>, vec: &mut Vec<NodeColor>) {
+        if let Some(node) = node {
+            let node = node.borrow();
+            vec.push(node.color.clone());
+            Self::colors_pre_order(&node.left, vec);
+            Self::colors_pre_order(&node.right, vec);
+        }
+    }
+}
+
+impl<T: Ord + Clone + Debug> RBTree<T> {
+    // Rebuild a tree from the output of `print_pre_order_traversal` and the color of each of those nodes
+    pub fn from_pre_order(keys: &[T], colors: &[NodeColor]) -> Result<RBTree<T>, String> {
+        if keys.len() != colors.len() {
+            return Err(format!("Got {} keys but {} colors", keys.len(), colors.len()));
+        }
+        let mut next = 0;
+        let root = RBTree::rebuild_from_pre_order(keys, colors, &mut next, &None, None, None, 1)?;
+        if next != keys.len() {
+            return Err("The sequence is not a traversal of a binary search tree with unique keys".to_string());
+        }
+        let tree = RBTree { root, cmp: Natural };
+        if !tree.validate_tree() {
+            return Err("The keys and colors do not describe a valid red-black tree".to_string());
+        }
+        Ok(tree)
+    }
+
+    // Every key of a subtree lies between the bounds given by its ancestors,
+    // the first key outside of the bounds ends the current subtree
+    fn rebuild_from_pre_order(keys: &[T], colors: &[NodeColor], next: &mut usize, parent: &RedBlackTree<T>,
+                              lower: Option<&T>, upper: Option<&T>, depth: usize) -> Result<RedBlackTree<T>, String> {
+        let key = match keys.get(*next) {
+            Some(key) if lower.is_none_or(|l| l < key) && upper.is_none_or(|u| key < u) => key,
+            _ => return Ok(None),
+        };
+        // A red-black tree with n nodes is never taller than 2 * log2(n + 1)
+        if depth > 2 * (usize::BITS - keys.len().leading_zeros()) as usize + 2 {
+            return Err("The keys and colors do not describe a valid red-black tree".to_string());
+        }
+        let mut tree_node = TreeNode::new(key.clone());
+        tree_node.color = colors[*next].clone();
+        tree_node.parent = parent.clone();
+        let node = Some(Rc::new(RefCell::new(tree_node)));
+        *next += 1;
+        let left = RBTree::rebuild_from_pre_order(keys, colors, next, &node, lower, Some(key), depth + 1)?;
+        let right = RBTree::rebuild_from_pre_order(keys, colors, next, &node, Some(key), upper, depth + 1)?;
+        {
+            let mut tree_node = node.as_ref().unwrap().borrow_mut();
+            tree_node.size = 1 + node_size(&left) + node_size(&right);
+            tree_node.left = left;
+            tree_node.right = right;
+        }
+        Ok(node)
+    }
+}
+
+
+/***********RBMultiset****************/
+// A red-black tree that keeps duplicates: a key inserted again is counted by its node instead of refused
+//
+// Traversals, ranges, `len`, `rank` and `select` count every copy of a key. The read-only methods of the tree
+// (height, leaves, diagram, validation, ...) are reachable through `tree()`.
+pub struct RBMultiset<T = u32, C = Natural> {
+    tree: RBTree<T, C>,
+}
+
+impl<T: Ord + Clone + Debug> RBMultiset<T> {
+    pub fn new() -> Self {
+        RBMultiset { tree: RBTree::new() }
+    }
+}
+
+impl<T: Ord + Clone + Debug> Default for RBMultiset<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone + Debug, C: Comparator<T>> RBMultiset<T, C> {
+    pub fn with_comparator(cmp: C) -> Self {
+        RBMultiset { tree: RBTree::with_comparator(cmp) }
+    }
+
+    pub fn tree(&self) -> &RBTree<T, C> {
+        &self.tree
+    }
+
+    // the number of copies of val after inserting it
+    pub fn insert(&mut self, val: T) -> usize {
+        let _op = stats::begin_operation();
+        if let (true, Some(node)) = self.tree.private_search(&val) {
+            node.borrow_mut().count += 1;
+            let count = node.borrow().count;
+            update_sizes_upward(Some(node));
+            return count;
+        }
+        self.tree.insert_node(val);
+        1
+    }
+
+    // remove one copy of val, false if there is none
+    pub fn remove_one(&mut self, val: T) -> bool {
+        let _op = stats::begin_operation();
+        let node = match self.tree.private_search(&val) {
+            (true, Some(node)) => node,
+            _ => return false,
+        };
+        if node.borrow().count > 1 {
+            node.borrow_mut().count -= 1;
+            update_sizes_upward(Some(node));
+            return true;
+        }
+        self.tree.private_delete_node(&mut &node).is_ok()
+    }
+
+    // remove every copy of val, returns how many there were
+    pub fn remove_all(&mut self, val: T) -> usize {
+        let _op = stats::begin_operation();
+        let node = match self.tree.private_search(&val) {
+            (true, Some(node)) => node,
+            _ => return 0,
+        };
+        let count = node.borrow().count;
+        match self.tree.private_delete_node(&mut &node) {
+            Ok(()) => count,
+            Err(_) => 0,
+        }
+    }
+
+    pub fn count(&self, val: T) -> usize {
+        let _op = stats::begin_operation();
+        match self.tree.private_search(&val) {
+            (true, Some(node)) => node.borrow().count,
+            _ => 0,
+        }
+    }
+
+    pub fn contains(&self, val: T) -> bool {
+        self.count(val) > 0
+    }
+
+    // every copy counted
+    pub fn len(&self) -> usize {
+        node_size(&self.tree.root)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    // every different key once with its count, in order
+    pub fn entries(&self) -> Vec<(T, usize)> {
+        let mut vec = Vec::new();
+        self.tree.entries_in_order(&self.tree.root, &mut vec);
+        vec
+    }
+
+    // how many copies of keys smaller than val there are, the index val has or would get in the in-order traversal
+    pub fn rank(&self, val: T) -> usize {
+        let _op = stats::begin_operation();
+        let mut rank = 0;
+        let mut node = self.tree.root.clone();
+        while let Some(current) = node {
+            let current = current.borrow();
+            trace::emit(|| TraceEvent::Compare { key: DebugKeys.key_text(&val), node: DebugKeys.key_text(&current.key) });
+            stats::count_comparison();
+            node = match self.tree.cmp.compare(&val, &current.key) {
+                Ordering::Equal => return rank + node_size(&current.left),
+                Ordering::Less => current.left.clone(),
+                Ordering::Greater => {
+                    // everything on the left and every copy of this key comes first
+                    rank += node_size(&current.left) + current.count;
+                    current.right.clone()
+                }
+            };
+        }
+        rank
+    }
+
+    // the key at position index (from 0) of the in-order traversal, None if there are not that many
+    pub fn select(&self, index: usize) -> Option<T> {
+        let mut index = index;
+        let mut node = self.tree.root.clone();
+        while let Some(current) = node {
+            let current = current.borrow();
+            let left = node_size(&current.left);
+            if index < left {
+                node = current.left.clone();
+            } else if index < left + current.count {
+                return Some(current.key.clone());
+            } else {
+                index -= left + current.count;
+                node = current.right.clone();
+            }
+        }
+        None
+    }
+
+    pub fn range(&self, low: T, high: T) -> Vec<T> {
+        self.tree.range(low, high)
+    }
+
+    pub fn print_in_order_traversal(&self) -> Vec<T> {
+        self.tree.print_in_order_traversal()
+    }
+
+    pub fn print_pre_order_traversal(&self) -> Vec<T> {
+        self.tree.print_pre_order_traversal()
+    }
+
+    pub fn print_post_order_traversal(&self) -> Vec<T> {
+        self.tree.print_post_order_traversal()
+    }
+}
+
+/***********Binary persistence****************/
+// See `codec.rs` for the file layout
+const RB_MAGIC: &[u8; 4] = b"RBTR";
+const COLOR_RED: u8 = 0;
+const COLOR_BLACK: u8 = 1;
+
+impl<T: BinaryKey + Ord + Clone + Debug> RBTree<T> {
+    fn encode_node(node: &RedBlackTree<T>, out: &mut Vec<u8>) {
+        match node {
+            None => out.push(codec::TAG_EMPTY),
+            Some(node) => {
+                let node = node.borrow();
+                out.push(codec::TAG_NODE);
+                out.push(if node.color == NodeColor::Red { COLOR_RED } else { COLOR_BLACK });
+                node.key.write_key(out);
+                RBTree::encode_node(&node.left, out);
+                RBTree::encode_node(&node.right, out);
+            }
+        }
+    }
+
+    fn decode_node(input: &mut ByteReader, parent: &RedBlackTree<T>, depth: usize, count: &mut u32) -> Result<RedBlackTree<T>, CodecError> {
+        if depth > codec::MAX_DEPTH {
+            return Err(CodecError::InvalidTree("tree is too deep".to_string()));
+        }
+        match input.read_u8()? {
+            codec::TAG_EMPTY => Ok(None),
+            codec::TAG_NODE => {
+                let color = match input.read_u8()? {
+                    COLOR_RED => NodeColor::Red,
+                    COLOR_BLACK => NodeColor::Black,
+                    tag => return Err(CodecError::InvalidTag(tag)),
+                };
+                let mut tree_node = TreeNode::new(T::read_key(input)?);
+                tree_node.color = color;
+                tree_node.parent = parent.clone();
+                let node = Some(Rc::new(RefCell::new(tree_node)));
+                *count += 1;
+                let left = RBTree::decode_node(input, &node, depth + 1, count)?;
+                let right = RBTree::decode_node(input, &node, depth + 1, count)?;
+                {
+                    let mut tree_node = node.as_ref().unwrap().borrow_mut();
+                    tree_node.size = 1 + node_size(&left) + node_size(&right);
+                    tree_node.left = left;
+                    tree_node.right = right;
+                }
+                Ok(node)
+            }
+            tag => Err(CodecError::InvalidTag(tag)),
+        }
+    }
+}
+
+impl<T: BinaryKey + Ord + Clone + Debug> TreeCodec for RBTree<T> {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = codec::write_header(RB_MAGIC, T::KEY_TAG, self.total_number_elements() as u32);
+        RBTree::encode_node(&self.root, &mut out);
+        codec::finish(&mut out);
+        out
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, CodecError> {
+        let (node_count, mut input) = codec::open(bytes, RB_MAGIC, T::KEY_TAG)?;
+        let mut decoded = 0;
+        let root = RBTree::decode_node(&mut input, &None, 0, &mut decoded)?;
+        if !input.is_empty() {
+            return Err(CodecError::TrailingBytes);
+        }
+        if decoded != node_count {
+            return Err(CodecError::InvalidTree(format!("header says {} nodes, found {}", node_count, decoded)));
+        }
+        let tree = RBTree { root, cmp: Natural };
+        if !tree.validate_tree() {
+            return Err(CodecError::InvalidTree("not a valid red-black tree".to_string()));
+        }
+        Ok(tree)
+    }
+}
+
+
+/***********JSON import / export****************/
+// See `json.rs`
+impl<T: JsonKey + Ord + Clone + Debug> RBTree<T> {
+    fn node_to_json(node: &RedBlackTree<T>) -> JsonValue {
+        match node {
+            None => JsonValue::Null,
+            Some(node) => {
+                let node = node.borrow();
+                let color = if node.color == NodeColor::Red { "red" } else { "black" };
+                json::node_object(
+                    node.key.to_json(),
+                    ("color", JsonValue::String(color.to_string())),
+                    RBTree::node_to_json(&node.left),
+                    RBTree::node_to_json(&node.right),
+                )
+            }
+        }
+    }
+
+    fn node_from_json(value: &JsonValue, parent: &RedBlackTree<T>) -> Result<RedBlackTree<T>, JsonError> {
+        if value == &JsonValue::Null {
+            return Ok(None);
+        }
+        let color = match json::field(value, "color")? {
+            JsonValue::String(c) if c == "red" => NodeColor::Red,
+            JsonValue::String(c) if c == "black" => NodeColor::Black,
+            other => return Err(JsonError::Schema(format!("color must be \"red\" or \"black\", found {}", other))),
+        };
+        let mut tree_node = TreeNode::new(T::from_json(json::field(value, "key")?)?);
+        tree_node.color = color;
+        tree_node.parent = parent.clone();
+        let node = Some(Rc::new(RefCell::new(tree_node)));
+        let left = RBTree::node_from_json(json::field(value, "left")?, &node)?;
+        let right = RBTree::node_from_json(json::field(value, "right")?, &node)?;
+        {
+            let mut tree_node = node.as_ref().unwrap().borrow_mut();
+            tree_node.size = 1 + node_size(&left) + node_size(&right);
+            tree_node.left = left;
+            tree_node.right = right;
+        }
+        Ok(node)
+    }
+}
+
+impl<T: JsonKey + Ord + Clone + Debug> TreeJson for RBTree<T> {
+    fn to_json_nested(&self) -> String {
+        RBTree::node_to_json(&self.root).to_string()
+    }
+
+    fn to_json_sorted(&self) -> String {
+        let keys = self.vec_nodes_in_order();
+        JsonValue::Array(keys.iter().map(|k| k.to_json()).collect()).to_string()
+    }
+
+    fn from_json_nested(text: &str) -> Result<Self, JsonError> {
+        let root = RBTree::node_from_json(&JsonValue::parse(text)?, &None)?;
+        let tree = RBTree { root, cmp: Natural };
+        if !tree.validate_tree() {
+            return Err(JsonError::InvalidTree("not a valid red-black tree".to_string()));
+        }
+        Ok(tree)
+    }
+
+    fn from_json_sorted(text: &str) -> Result<Self, JsonError> {
+        let mut tree = RBTree::new();
+        for key in json::parse_sorted_keys(text)? {
+            tree.insert_node(key);
+        }
+        Ok(tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The JSON of one node, `left` and `right` are the JSON of the children
+    fn node(key: i32, color: &str, left: &str, right: &str) -> String {
+        format!(r#"{{"key":{},"color":"{}","left":{},"right":{}}}"#, key, color, left, right)
+    }
+
+    fn leaf(key: i32, color: &str) -> String {
+        node(key, color, "null", "null")
+    }
+
+    fn from_json(text: &str) -> Result<RBTree<i32>, JsonError> {
+        TreeJson::from_json_nested(text)
+    }
+
+    fn is_invalid_tree(text: &str) -> bool {
+        from_json(text).err() == Some(JsonError::InvalidTree("not a valid red-black tree".to_string()))
+    }
+
+    #[test]
+    fn json_round_trips() {
+        let mut tree: RBTree<i32> = RBTree::new();
+        assert_eq!(tree.to_json_nested(), "null");
+        assert_eq!(tree.to_json_sorted(), "[]");
+        assert!(from_json("null").unwrap().is_empty());
+        for i in 1..=30 {
+            tree.insert_node(i * 7 % 31);
+        }
+        for i in [3, 14, 22, 9] {
+            tree.delete(i).unwrap();
+        }
+        let copy = from_json(&tree.to_json_nested()).unwrap();
+        assert_eq!(copy.print_pre_order_traversal(), tree.print_pre_order_traversal());
+        assert_eq!(copy.pre_order_colors(), tree.pre_order_colors());
+        assert_eq!(copy.to_json_nested(), tree.to_json_nested());
+        let sorted: RBTree<i32> = TreeJson::from_json_sorted(&tree.to_json_sorted()).unwrap();
+        assert_eq!(sorted.print_in_order_traversal(), tree.print_in_order_traversal());
+        assert!(sorted.validate_tree());
+    }
+
+    #[test]
+    fn json_of_a_known_tree() {
+        let mut tree: RBTree<i32> = RBTree::new();
+        for i in [1, 2, 3] {
+            tree.insert_node(i);
+        }
+        let text = node(2, "black", &leaf(1, "red"), &leaf(3, "red"));
+        assert_eq!(tree.to_json_nested(), text);
+        let mut copy = from_json(&text).unwrap();
+        // the parent links are rebuilt, so the tree keeps working after the import
+        copy.delete(2).unwrap();
+        copy.insert_node(4);
+        assert!(copy.validate_tree());
+        assert_eq!(copy.print_in_order_traversal(), vec![1, 3, 4]);
+    }
+
+    #[test]
+    fn json_that_is_not_a_tree_is_rejected() {
+        assert!(matches!(from_json(r#"{"key":1,"color":"black""#), Err(JsonError::Syntax { .. })));
+        assert!(matches!(from_json(r#"{"key":1,"left":null,"right":null}"#), Err(JsonError::Schema(_))));
+        assert!(matches!(from_json(&leaf(1, "green")), Err(JsonError::Schema(_))));
+        assert!(matches!(from_json(r#"{"key":1.5,"color":"black","left":null,"right":null}"#), Err(JsonError::Schema(_))));
+        assert!(matches!(
+            from_json(r#"{"key":1,"color":"black","left":null,"right":null,"color":"red"}"#),
+            Err(JsonError::Syntax { .. })
+        ));
+    }
+
+    #[test]
+    fn json_with_broken_colors_is_rejected() {
+        // a red root
+        assert!(is_invalid_tree(&leaf(1, "red")));
+        // a red node with a red child
+        assert!(is_invalid_tree(&node(2, "black", &node(1, "red", &leaf(0, "red"), "null"), &leaf(3, "black"))));
+        // one black node more on the left than on the right
+        assert!(is_invalid_tree(&node(2, "black", &leaf(1, "black"), "null")));
+    }
+
+    #[test]
+    fn json_with_duplicate_keys_is_rejected() {
+        assert!(is_invalid_tree(&node(2, "black", &leaf(2, "red"), "null")));
+        assert!(matches!(<RBTree<i32> as TreeJson>::from_json_sorted("[1,2,2]"), Err(JsonError::Schema(_))));
+    }
+
+    #[test]
+    fn json_out_of_order_is_rejected() {
+        // colors are fine, but 20 is on the left of 10
+        let text = node(10, "black", &node(5, "red", &leaf(1, "black"), &leaf(20, "black")), &node(15, "red", &leaf(12, "black"), &leaf(17, "black")));
+        assert!(is_invalid_tree(&text));
+        assert!(from_json(&text.replace(":20,", ":7,")).unwrap().validate_tree());
+    }
+
+    const NOT_A_TRAVERSAL: &str = "The sequence is not a traversal of a binary search tree with unique keys";
+    const NOT_RED_BLACK: &str = "The keys and colors do not describe a valid red-black tree";
+
+    fn rebuild(keys: &[i32], colors: &[NodeColor]) -> Result<RBTree<i32>, String> {
+        RBTree::from_pre_order(keys, colors)
+    }
+
+    #[test]
+    fn rebuilt_trees_are_the_same_tree() {
+        assert!(rebuild(&[], &[]).unwrap().is_empty());
+        let mut tree: RBTree<i32> = RBTree::new();
+        for i in 0..200 {
+            tree.insert_node(i * 37 % 211);
+            if i % 5 == 0 {
+                let _ = tree.delete(i * 11 % 211);
+            }
+            let copy = rebuild(&tree.print_pre_order_traversal(), &tree.pre_order_colors()).unwrap();
+            assert_eq!(copy.to_json_nested(), tree.to_json_nested());
+            assert!(copy.validate_tree());
+        }
+    }
+
+    #[test]
+    fn rebuilding_needs_as_many_colors_as_keys() {
+        use NodeColor::*;
+        assert_eq!(rebuild(&[2, 1, 3], &[Black, Red]).err(), Some("Got 3 keys but 2 colors".to_string()));
+        assert_eq!(rebuild(&[2], &[Black, Red]).err(), Some("Got 1 keys but 2 colors".to_string()));
+    }
+
+    #[test]
+    fn rebuilding_needs_a_real_traversal() {
+        use NodeColor::*;
+        assert_eq!(rebuild(&[2, 3, 1], &[Black, Red, Red]).err().as_deref(), Some(NOT_A_TRAVERSAL));
+        assert_eq!(rebuild(&[4, 2, 6, 5, 1], &[Black, Black, Black, Red, Red]).err().as_deref(), Some(NOT_A_TRAVERSAL));
+    }
+
+    #[test]
+    fn rebuilding_refuses_duplicates() {
+        use NodeColor::*;
+        assert_eq!(rebuild(&[2, 2], &[Black, Red]).err().as_deref(), Some(NOT_A_TRAVERSAL));
+        assert_eq!(rebuild(&[2, 1, 1], &[Black, Red, Red]).err().as_deref(), Some(NOT_A_TRAVERSAL));
+    }
+
+    #[test]
+    fn rebuilding_refuses_colors_that_do_not_fit() {
+        use NodeColor::*;
+        assert!(rebuild(&[2, 1, 3], &[Black, Red, Red]).is_ok());
+        // red root, red child of a red node, more black nodes on one side
+        assert_eq!(rebuild(&[2, 1, 3], &[Red, Black, Black]).err().as_deref(), Some(NOT_RED_BLACK));
+        assert_eq!(rebuild(&[3, 2, 1], &[Black, Red, Red]).err().as_deref(), Some(NOT_RED_BLACK));
+        assert_eq!(rebuild(&[2, 1, 3], &[Black, Black, Red]).err().as_deref(), Some(NOT_RED_BLACK));
+        // a long sorted run would be a list, it is refused before the recursion gets deep
+        let sorted: Vec<i32> = (0..100_000).collect();
+        assert_eq!(rebuild(&sorted, &vec![Black; sorted.len()]).err().as_deref(), Some(NOT_RED_BLACK));
+    }
+
+    #[test]
+    fn the_number_of_elements_follows_inserts_and_deletes() {
+        let mut tree = RBTree::<i32>::new();
+        assert_eq!(tree.total_number_elements(), 0);
+        for key in 0..100 {
+            tree.insert_node(key);
+        }
+        assert!(!tree.insert_node(50));
+        for key in (0..100).step_by(3) {
+            assert!(tree.delete(key).is_ok());
+        }
+        assert!(tree.delete(0).is_err());
+        assert_eq!(tree.total_number_elements(), 66);
+        assert_eq!(tree.total_number_elements() as usize, tree.print_in_order_traversal().len());
     }
 }
@@ -0,0 +1,352 @@
+use std::fs;
+
+use ECE522_project::codec::TreeCodec;
+
+use crate::cli::registry::{self, Keyed};
+use crate::cli::script::{arity_ok, is_command, run_command};
+use crate::cli::tree::CliTree;
+use crate::cli::workload::{check_options, run_churn, run_gen, CHURN_USAGE, GEN_USAGE};
+use crate::cli::{EXIT_FAILED, EXIT_OK, EXIT_USAGE};
+
+// Batch files: one command per line, run against a single tree
+//     cargo run run ops.txt --tree avl
+//     cargo run run words.txt --tree rb --key string
+//     cargo run run more.txt --tree rb --load my_tree.rb
+//
+//     # comments and empty lines are ignored
+//     insert 5 3 8
+//     delete 3
+//     update 8 9
+//     print
+//     assert-contains 5 9
+//     assert-height 2
+//     gen --count 100 --dist zipf --seed 7
+//     churn --ops 1000 --seed 7
+//
+// Every command of the non-interactive mode can be used, plus gen, churn and the assertions below.
+// A failed command does not stop the run, all problems are reported with their line number at the end.
+
+const ASSERTIONS: [&str; 6] = [
+    "assert-contains", "assert-missing", "assert-height", "assert-leaves", "assert-count", "assert-valid",
+];
+
+pub fn batch_usage() {
+    eprintln!("Usage: cargo run run <file> [--tree avl|rb] [--key int|unsigned|float|char|string] [--load <tree file>]\n\
+               Every line holds one command: insert <values>, delete <values>, update <old> <new>,\n\
+               contains <values>, range <low> <high>, leaves, height, inorder, preorder, postorder,\n\
+               empty, count, validate, print, assert-contains <values>, assert-missing <values>,\n\
+               assert-height <n>, assert-leaves <n>, assert-count <n>, assert-valid,\n\
+               {},\n\
+               {}", GEN_USAGE, CHURN_USAGE);
+}
+
+// `cargo run run <file> [--tree <tree>] [--key <key type>] [--load <tree file>]`, `args` starts at the file
+pub fn run_batch_command(args: &[String]) -> i32 {
+    let path = match args.first() {
+        Some(path) => path,
+        None => {
+            batch_usage();
+            return EXIT_USAGE;
+        }
+    };
+    let mut tree = "avl";
+    let mut key = None;
+    let mut load = None;
+    let mut options = args[1..].iter();
+    while let Some(option) = options.next() {
+        match (option.as_str(), options.next()) {
+            ("--tree", Some(value)) => tree = value,
+            ("--key", Some(value)) => key = Some(value.as_str()),
+            ("--load", Some(value)) => load = Some(value.as_str()),
+            _ => {
+                batch_usage();
+                return EXIT_USAGE;
+            }
+        }
+    }
+    let kind = match registry::find_tree_kind(tree) {
+        Some(kind) => kind,
+        None => {
+            eprintln!("Unknown tree type '{}'", tree);
+            return EXIT_USAGE;
+        }
+    };
+    let key_type = match registry::key_type_for(kind, key) {
+        Ok(key_type) => key_type,
+        Err(e) => {
+            eprintln!("{}", e);
+            return EXIT_USAGE;
+        }
+    };
+    (kind.run_batch)(key_type, path, load)
+}
+
+// Outcome of one line
+enum LineResult {
+    Passed,
+    // the command ran but failed, or an assertion did not hold
+    Failed(String),
+    // the line could not be understood
+    Invalid(String),
+}
+
+// What a run of a batch file found
+struct Summary {
+    executed: usize,
+    // line numbers of the lines that failed or could not be understood
+    failures: Vec<usize>,
+    invalid: usize,
+}
+
+impl Summary {
+    fn exit_code(&self) -> i32 {
+        if self.invalid > 0 {
+            EXIT_USAGE
+        } else if !self.failures.is_empty() {
+            EXIT_FAILED
+        } else {
+            EXIT_OK
+        }
+    }
+}
+
+// Run every line of the file, returns the exit code
+pub fn run_batch<T: CliTree + TreeCodec>(tree: &mut Keyed<T>, path: &str) -> i32 {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Cannot read {}: {}", path, e);
+            return EXIT_USAGE;
+        }
+    };
+
+    let summary = run_lines(tree, path, &content);
+    println!("=========== BATCH SUMMARY ===========");
+    println!("File: {}", path);
+    println!("Commands run: {}", summary.executed);
+    println!("Passed: {}", summary.executed - summary.failures.len());
+    println!("Failed: {}", summary.failures.len() - summary.invalid);
+    println!("Invalid: {}", summary.invalid);
+    if !summary.failures.is_empty() {
+        println!("Problem lines: {:?}", summary.failures);
+    }
+    println!("=====================================");
+    summary.exit_code()
+}
+
+// Run the lines of `content`, reporting every problem with its place in `path`
+fn run_lines<T: CliTree + TreeCodec>(tree: &mut Keyed<T>, path: &str, content: &str) -> Summary {
+    let mut summary = Summary { executed: 0, failures: Vec::new(), invalid: 0 };
+    for (number, line) in content.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        summary.executed += 1;
+        match run_line(tree, line) {
+            LineResult::Passed => {}
+            LineResult::Failed(reason) => {
+                eprintln!("{}:{}: FAILED `{}`: {}", path, number + 1, line, reason);
+                summary.failures.push(number + 1);
+            }
+            LineResult::Invalid(reason) => {
+                eprintln!("{}:{}: INVALID `{}`: {}", path, number + 1, line, reason);
+                summary.failures.push(number + 1);
+                summary.invalid += 1;
+            }
+        }
+    }
+    summary
+}
+
+// The line without its comment. A `#` starts one at the beginning of the line, or after whitespace when more
+// text follows it; inside a word or as the last word of the line it is a key, e.g. `insert a#b #`.
+fn strip_comment(line: &str) -> &str {
+    if line.trim_start().starts_with('#') {
+        return "";
+    }
+    let mut previous = ' ';
+    for (i, c) in line.char_indices() {
+        if c == '#' && previous.is_whitespace() && !line[i + 1..].trim().is_empty() {
+            return &line[..i];
+        }
+        previous = c;
+    }
+    line
+}
+
+fn run_line<T: CliTree + TreeCodec>(keyed: &mut Keyed<T>, line: &str) -> LineResult {
+    let mut words = line.split_whitespace();
+    let command = words.next().unwrap();
+    let args: Vec<&str> = words.collect();
+
+    if command == "gen" || command == "churn" {
+        if let Err(reason) = check_options(command, &args) {
+            return LineResult::Invalid(reason);
+        }
+        let result = if command == "gen" { run_gen(keyed, &args) } else { run_churn(keyed, &args) };
+        return match result {
+            Ok(_) => LineResult::Passed,
+            Err(reason) => LineResult::Failed(reason),
+        };
+    }
+    let tree = &mut keyed.tree;
+
+    if is_command(command) {
+        let mut values = Vec::new();
+        for arg in &args {
+            match arg.parse() {
+                Ok(v) => values.push(v),
+                Err(_) => return LineResult::Invalid(format!("'{}' is not a valid value", arg)),
+            }
+        }
+        if !arity_ok(command, values.len()) {
+            return LineResult::Invalid(format!("wrong number of values for '{}'", command));
+        }
+        return if run_command(tree, command, &values) {
+            LineResult::Passed
+        } else {
+            LineResult::Failed(format!("'{}' did not succeed", command))
+        };
+    }
+    if !ASSERTIONS.contains(&command) {
+        return LineResult::Invalid(format!("unknown command '{}'", command));
+    }
+
+    match command {
+        "assert-contains" | "assert-missing" => {
+            let mut values = Vec::new();
+            for arg in &args {
+                match arg.parse::<T::Key>() {
+                    Ok(v) => values.push(v),
+                    Err(_) => return LineResult::Invalid(format!("'{}' is not a valid value", arg)),
+                }
+            }
+            if values.is_empty() {
+                return LineResult::Invalid(format!("'{}' needs at least one value", command));
+            }
+            let expected = command == "assert-contains";
+            let wrong: Vec<T::Key> = values.into_iter().filter(|v| tree.contains(v.clone()) != expected).collect();
+            if wrong.is_empty() {
+                LineResult::Passed
+            } else if expected {
+                LineResult::Failed(format!("{:?} not found", wrong))
+            } else {
+                LineResult::Failed(format!("{:?} unexpectedly found", wrong))
+            }
+        }
+        "assert-valid" => {
+            if !args.is_empty() {
+                return LineResult::Invalid("'assert-valid' takes no values".to_string());
+            }
+            if tree.validate() {
+                LineResult::Passed
+            } else {
+                LineResult::Failed("the tree is not valid".to_string())
+            }
+        }
+        _ => {
+            // assert-height / assert-leaves / assert-count <n>
+            let expected = match args.as_slice() {
+                [n] => match n.parse::<usize>() {
+                    Ok(n) => n,
+                    Err(_) => return LineResult::Invalid(format!("'{}' is not a valid number", n)),
+                },
+                _ => return LineResult::Invalid(format!("'{}' needs exactly one number", command)),
+            };
+            let (what, actual) = match command {
+                "assert-height" => ("height", tree.height() as usize),
+                "assert-leaves" => ("number of leaves", tree.leaves() as usize),
+                _ => ("number of elements", tree.count()),
+            };
+            if actual == expected {
+                LineResult::Passed
+            } else {
+                LineResult::Failed(format!("expected {} {}, found {}", what, expected, actual))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::key::KeyType;
+    use ECE522_project::AVL::{AvlTree, AvlTreeNode};
+    use ECE522_project::RBTree::RBTree;
+
+    fn avl<K: Ord>(key_type: KeyType) -> Keyed<AvlTreeNode<K>> {
+        Keyed { tree: AvlTree::generate_empty_tree(), key_type }
+    }
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn problems_are_reported_with_the_line_they_are_on() {
+        let content = "# a comment, then an empty line\n\
+                       \n\
+                       insert 5 3 8\n\
+                       delete 4\n\
+                       assert-contains 5 8  # a comment after a command\n\
+                       frobnicate 1\n\
+                       assert-height 7\n\
+                       \x20   # an indented comment\n\
+                       assert-count x\n\
+                       assert-valid\n";
+        let mut tree = avl::<i32>(KeyType::Int);
+        let summary = run_lines(&mut tree, "test.txt", content);
+        assert_eq!(summary.executed, 7);
+        assert_eq!(summary.failures, vec![4, 6, 7, 9]);
+        assert_eq!(summary.invalid, 2);
+        assert_eq!(summary.exit_code(), EXIT_USAGE);
+    }
+
+    #[test]
+    fn the_exit_code_follows_the_worst_line() {
+        let mut tree = avl::<i32>(KeyType::Int);
+        assert_eq!(run_lines(&mut tree, "ok.txt", "insert 1 2 3\nassert-count 3\nassert-leaves 2\n").exit_code(), EXIT_OK);
+        let failed = run_lines(&mut tree, "failed.txt", "assert-missing 2\nassert-contains 1\n");
+        assert_eq!((failed.failures, failed.invalid), (vec![1], 0));
+        assert_eq!(run_lines(&mut tree, "failed.txt", "assert-missing 2\n").exit_code(), EXIT_FAILED);
+        assert_eq!(run_lines(&mut tree, "invalid.txt", "assert-missing 2\nassert-valid 1\n").exit_code(), EXIT_USAGE);
+    }
+
+    #[test]
+    fn gen_and_churn_run_inside_a_batch() {
+        let mut tree = Keyed { tree: RBTree::<u32>::new(), key_type: KeyType::Unsigned };
+        let content = "gen --count 50 --dist sequential\nassert-count 50\nchurn --ops 200 --seed 3\nassert-valid\ngen --count\n";
+        let summary = run_lines(&mut tree, "workload.txt", content);
+        assert_eq!((summary.failures, summary.invalid), (vec![5], 1));
+    }
+
+    #[test]
+    fn a_hash_starts_a_comment_only_at_the_start_or_after_whitespace() {
+        assert_eq!(strip_comment("# all of it"), "");
+        assert_eq!(strip_comment("   # all of it"), "");
+        assert_eq!(strip_comment("insert 5 # five"), "insert 5 ");
+        assert_eq!(strip_comment("insert 5\t#five"), "insert 5\t");
+        assert_eq!(strip_comment("insert a#b"), "insert a#b");
+        assert_eq!(strip_comment("insert #"), "insert #");
+        assert_eq!(strip_comment("insert # "), "insert # ");
+    }
+
+    #[test]
+    fn hashes_can_be_keys() {
+        let mut words = avl::<String>(KeyType::Str);
+        let summary = run_lines(&mut words, "words.txt", "insert a#b #\nassert-contains a#b #\nassert-count 2 # a#b and #\n");
+        assert_eq!(summary.exit_code(), EXIT_OK);
+        let mut chars = avl::<char>(KeyType::Char);
+        let summary = run_lines(&mut chars, "chars.txt", "insert a #\nassert-contains #\ndelete #\nassert-missing #\n");
+        assert_eq!(summary.exit_code(), EXIT_OK);
+    }
+
+    #[test]
+    fn a_wrong_command_line_is_a_usage_error() {
+        for line in ["", "ops.txt --tree", "ops.txt --tree oak", "ops.txt --key bogus", "ops.txt --verbose 1"] {
+            assert_eq!(run_batch_command(&args(line)), EXIT_USAGE, "{}", line);
+        }
+        assert_eq!(run_batch_command(&args("/no/such/batch/file.txt")), EXIT_USAGE);
+    }
+}
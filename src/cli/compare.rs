@@ -0,0 +1,368 @@
+use std::str::FromStr;
+
+use ECE522_project::stats;
+use ECE522_project::total_float::TotalF64;
+use ECE522_project::BST::{BinarySearchTree, Node};
+
+use crate::cli::key::KeyType;
+use crate::cli::registry::{key_type_for, DynTree, TreeKind, TREE_KINDS};
+use crate::cli::{EXIT_OK, EXIT_USAGE};
+
+// Compare mode: every change is applied to each tree of TREE_KINDS and to the plain binary search tree,
+// then their shapes are shown next to each other
+//     cargo run compare
+//     cargo run compare --key char
+
+fn compare_usage() {
+    eprintln!("Usage: cargo run compare [--key int|unsigned|float|char|string]");
+}
+
+fn compare_help_list() {
+    println!("=========== COMPARE MODE ===========");
+    println!("insert <values>: insert values into all trees\n\
+              delete <values>: delete values from all trees\n\
+              update <old> <new>: replace old with new in all trees\n\
+              print: show the comparison again\n\
+              exit: leave compare mode");
+    println!("====================================");
+}
+
+// The plain binary search tree the balanced trees are compared with, keys are passed as text like for DynTree
+trait Baseline {
+    fn insert(&mut self, key: &str);
+    fn delete(&mut self, key: &str);
+    fn height(&self) -> i32;
+    fn leaves(&self) -> i32;
+}
+
+// The balanced trees check every key before it gets here, so a key that does not parse cannot happen
+impl<K: FromStr + Copy + Ord> Baseline for Node<K> {
+    fn insert(&mut self, key: &str) {
+        if let Ok(key) = key.parse() {
+            self.insert_node(key);
+        }
+    }
+
+    fn delete(&mut self, key: &str) {
+        if let Ok(key) = key.parse() {
+            self.delete_node(key);
+        }
+    }
+
+    fn height(&self) -> i32 {
+        self.height_of_tree()
+    }
+
+    fn leaves(&self) -> i32 {
+        self.number_of_leaves()
+    }
+}
+
+fn empty_bst<K: FromStr + Copy + Ord + 'static>() -> Option<Box<dyn Baseline>> {
+    let tree: Node<K> = BinarySearchTree::gen_empty_tree();
+    Some(Box::new(tree))
+}
+
+// The binary search tree needs Copy keys, so there is none for strings
+fn baseline(key_type: KeyType) -> Option<Box<dyn Baseline>> {
+    match key_type {
+        KeyType::Int => empty_bst::<i32>(),
+        KeyType::Unsigned => empty_bst::<u32>(),
+        KeyType::Float => empty_bst::<TotalF64>(),
+        KeyType::Char => empty_bst::<char>(),
+        KeyType::Str => None,
+    }
+}
+
+// One balanced tree of the comparison
+struct Column {
+    kind: &'static TreeKind,
+    tree: Box<dyn DynTree>,
+    // rotations done so far
+    rotations: u64,
+}
+
+impl Column {
+    // "AVL" for the "AVL tree"
+    fn name(&self) -> &'static str {
+        self.kind.title.trim_end_matches(" tree")
+    }
+}
+
+struct Comparison {
+    columns: Vec<Column>,
+    bst: Option<Box<dyn Baseline>>,
+    bst_rotations: u64,
+}
+
+impl Comparison {
+    fn new(key_type: KeyType) -> Self {
+        Comparison {
+            columns: TREE_KINDS.iter().map(|kind| Column { kind, tree: (kind.create)(key_type), rotations: 0 }).collect(),
+            bst: baseline(key_type),
+            bst_rotations: 0,
+        }
+    }
+
+    // Whether the trees hold the value, Err if it is not a valid key. All trees hold the same keys, so the first one answers.
+    fn contains(&mut self, val: &str) -> Result<bool, String> {
+        self.columns[0].tree.contains(val)
+    }
+
+    fn insert(&mut self, val: &str) {
+        match self.contains(val) {
+            Err(e) => println!("{}", e),
+            Ok(true) => println!("INSERT FAILED: Node({}) already exists!", val),
+            Ok(false) => {
+                for column in &mut self.columns {
+                    let (_, insert_stats) = stats::measure(|| column.tree.insert_quiet(val));
+                    column.rotations += insert_stats.rotations;
+                }
+                if let Some(bst) = &mut self.bst {
+                    let (_, insert_stats) = stats::measure(|| bst.insert(val));
+                    self.bst_rotations += insert_stats.rotations;
+                }
+                println!("Insert {} successfully.", val);
+            }
+        }
+    }
+
+    fn delete(&mut self, val: &str) {
+        match self.contains(val) {
+            Err(e) => println!("{}", e),
+            Ok(false) => println!("DELETE FAILED: No such node({}) to delete", val),
+            Ok(true) => {
+                for column in &mut self.columns {
+                    let (_, delete_stats) = stats::measure(|| column.tree.delete_quiet(val));
+                    column.rotations += delete_stats.rotations;
+                }
+                if let Some(bst) = &mut self.bst {
+                    let (_, delete_stats) = stats::measure(|| bst.delete(val));
+                    self.bst_rotations += delete_stats.rotations;
+                }
+                println!("Node({}) delete successfully.", val);
+            }
+        }
+    }
+
+    fn update(&mut self, old: &str, new: &str) {
+        match (self.contains(old), self.contains(new)) {
+            (Err(e), _) | (_, Err(e)) => println!("{}", e),
+            (Ok(false), _) => println!("UPDATE FAILED: Node({}) doesn't exist!", old),
+            (Ok(true), Ok(true)) if old == new => println!("UPDATE FAILED: New value and old value can not be same!"),
+            (Ok(true), Ok(true)) => println!("UPDATE FAILED: New Node({}) exist!", new),
+            (Ok(true), Ok(false)) => {
+                self.delete(old);
+                self.insert(new);
+            }
+        }
+    }
+
+    // A value of the binary search tree for the table, `-` when there is none
+    fn bst_cell<V: ToString>(&self, value: impl Fn(&dyn Baseline) -> V) -> String {
+        self.bst.as_ref().map_or("-".to_string(), |bst| value(bst.as_ref()).to_string())
+    }
+
+    fn show(&self) {
+        print!("\n{:<18}", "");
+        for column in &self.columns {
+            print!("{:>12}", column.name());
+        }
+        println!("{:>12}", "BST");
+        print!("{:<18}", "Height");
+        for column in &self.columns {
+            print!("{:>12}", column.tree.height());
+        }
+        println!("{:>12}", self.bst_cell(|bst| bst.height()));
+        print!("{:<18}", "Leaves");
+        for column in &self.columns {
+            print!("{:>12}", column.tree.leaves());
+        }
+        println!("{:>12}", self.bst_cell(|bst| bst.leaves()));
+        print!("{:<18}", "Rotations");
+        for column in &self.columns {
+            print!("{:>12}", column.rotations);
+        }
+        println!("{:>12}", self.bst_cell(|_| self.bst_rotations));
+        println!();
+        let diagrams: Vec<(&str, Vec<String>)> = self.columns.iter().map(|column| (column.kind.title, column.tree.diagram())).collect();
+        for row in side_by_side(&diagrams) {
+            println!("{}", row);
+        }
+    }
+}
+
+// Diagrams as columns next to each other, each one under its title
+fn side_by_side(diagrams: &[(&str, Vec<String>)]) -> Vec<String> {
+    let height = diagrams.iter().map(|(_, lines)| lines.len()).max().unwrap_or(0);
+    let mut rows = vec![String::new(); height + 1];
+    for (title, lines) in diagrams {
+        let width = lines.iter().map(|line| line.chars().count())
+            .chain(std::iter::once(title.chars().count()))
+            .max()
+            .unwrap_or(0) + 4;
+        let cells = std::iter::once(*title).chain(lines.iter().map(String::as_str)).chain(std::iter::repeat(""));
+        for (row, cell) in rows.iter_mut().zip(cells) {
+            row.push_str(cell);
+            row.push_str(&" ".repeat(width - cell.chars().count()));
+        }
+    }
+    rows.iter().map(|row| row.trim_end().to_string()).collect()
+}
+
+// The key type of `--key`, int when there is none. Every tree of the comparison has to support it.
+fn compare_key_type(flag: Option<&str>) -> Result<KeyType, String> {
+    let flag = flag.unwrap_or_else(|| KeyType::Int.flag());
+    let mut key_type = KeyType::Int;
+    for kind in TREE_KINDS.iter() {
+        key_type = key_type_for(kind, Some(flag))?;
+    }
+    Ok(key_type)
+}
+
+// `cargo run compare [--key <key type>]`, returns the exit code
+pub fn run_compare_command(args: &[String]) -> i32 {
+    let flag = match args {
+        [] => None,
+        [option, value] if option == "--key" => Some(value.as_str()),
+        _ => {
+            compare_usage();
+            return EXIT_USAGE;
+        }
+    };
+    match compare_key_type(flag) {
+        Ok(key_type) => {
+            run_compare_mode(key_type);
+            EXIT_OK
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            EXIT_USAGE
+        }
+    }
+}
+
+pub fn run_compare_mode(key_type: KeyType) {
+    let mut comparison = Comparison::new(key_type);
+    compare_help_list();
+    loop {
+        println!("Please input a command: ");
+        let mut input = String::new();
+        if std::io::stdin().read_line(&mut input).expect("Cannot read!") == 0 {
+            break;
+        }
+        let words: Vec<&str> = input.split_whitespace().collect();
+        match words.first().copied() {
+            None => continue,
+            Some("exit") | Some("0") => break,
+            Some("print") => comparison.show(),
+            Some("insert") => {
+                for i in &words[1..] {
+                    comparison.insert(i);
+                }
+                comparison.show();
+            }
+            Some("delete") => {
+                for i in &words[1..] {
+                    comparison.delete(i);
+                }
+                comparison.show();
+            }
+            Some("update") => {
+                if words.len() != 3 {
+                    eprintln!("Wrong number of input. Try again...");
+                } else {
+                    comparison.update(words[1], words[2]);
+                    comparison.show();
+                }
+            }
+            Some(_) => compare_help_list(),
+        }
+    }
+    println!("Thank you! Hope to see you again!");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comparison(key_type: KeyType, keys: &[&str]) -> Comparison {
+        let mut comparison = Comparison::new(key_type);
+        for key in keys {
+            comparison.insert(key);
+        }
+        comparison
+    }
+
+    fn in_order(comparison: &mut Comparison) -> Vec<String> {
+        comparison.columns.iter_mut().map(|column| column.tree.in_order()).collect()
+    }
+
+    #[test]
+    fn every_tree_of_the_registry_gets_every_change() {
+        let mut comparison = comparison(KeyType::Int, &["5", "3", "8", "1"]);
+        assert_eq!(comparison.columns.len(), TREE_KINDS.len());
+        comparison.delete("3");
+        comparison.update("8", "9");
+        // refused: not a key, already there, missing, the same value
+        comparison.insert("x");
+        comparison.insert("5");
+        comparison.delete("7");
+        comparison.update("1", "1");
+        for keys in in_order(&mut comparison) {
+            assert_eq!(keys, "[1, 5, 9]");
+        }
+        assert_eq!(comparison.bst.as_ref().unwrap().height(), 2);
+    }
+
+    #[test]
+    fn rotations_come_from_the_counters() {
+        let keys = ["1", "2", "3", "4", "5", "6", "7"];
+        let comparison = comparison(KeyType::Int, &keys);
+        let rotations: Vec<u64> = comparison.columns.iter().map(|column| column.rotations).collect();
+        // the AVL tree rotates at 1, 3, 2 and 5, the binary search tree never rotates and becomes a list
+        assert_eq!(rotations[0], 4);
+        assert!(rotations.iter().all(|&r| r > 0));
+        assert_eq!(comparison.bst_rotations, 0);
+        assert_eq!(comparison.bst_cell(|bst| bst.height()), "7");
+    }
+
+    #[test]
+    fn keys_follow_the_key_type() {
+        let mut chars = comparison(KeyType::Char, &["c", "a", "b"]);
+        for keys in in_order(&mut chars) {
+            assert_eq!(keys, "['a', 'b', 'c']");
+        }
+        // c, a below it and b below a: one leaf
+        assert_eq!(chars.bst.as_ref().unwrap().leaves(), 1);
+        // no binary search tree for strings, its column shows `-`
+        let mut words = comparison(KeyType::Str, &["pear", "apple"]);
+        assert!(words.bst.is_none());
+        assert_eq!(words.bst_cell(|bst| bst.height()), "-");
+        for keys in in_order(&mut words) {
+            assert_eq!(keys, r#"["apple", "pear"]"#);
+        }
+    }
+
+    #[test]
+    fn the_key_type_has_to_suit_every_tree() {
+        assert_eq!(compare_key_type(None), Ok(KeyType::Int));
+        assert_eq!(compare_key_type(Some("string")), Ok(KeyType::Str));
+        assert!(compare_key_type(Some("bogus")).is_err());
+    }
+
+    #[test]
+    fn wrong_arguments_are_a_usage_error() {
+        for args in [vec!["--key"], vec!["--seed", "1"], vec!["--key", "bogus"], vec!["--key", "int", "extra"]] {
+            let args: Vec<String> = args.into_iter().map(String::from).collect();
+            assert_eq!(run_compare_command(&args), EXIT_USAGE, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn diagrams_are_put_next_to_each_other() {
+        let left = vec!["a".to_string(), "bb".to_string()];
+        let right = vec!["c".to_string()];
+        assert_eq!(side_by_side(&[("L", left), ("R", right)]), vec!["L     R", "a     c", "bb"]);
+    }
+}
@@ -0,0 +1,199 @@
+use ECE522_project::trace::{self, Step};
+
+use crate::cli::line_editor::LineEditor;
+use crate::cli::registry::{self, DynTree, TreeKind};
+use crate::cli::{EXIT_OK, EXIT_USAGE};
+
+// Explain mode: every insert and delete is traced, each step is printed together with the tree after it
+//     cargo run explain avl
+//     cargo run explain rb --key string
+
+pub fn explain_usage() {
+    eprintln!("Usage: cargo run explain avl|rb [--key int|unsigned|float|char|string]");
+}
+
+fn explain_help_list() {
+    println!("=========== EXPLAIN MODE ===========");
+    println!("insert <values>: insert values one by one and explain each step\n\
+              delete <values>: delete values one by one and explain each step\n\
+              print: print this tree\n\
+              exit: leave explain mode");
+    println!("====================================");
+}
+
+// `cargo run explain <tree> [--key <key type>]`, `args` starts at the tree
+pub fn run_explain_command(args: &[String]) -> i32 {
+    let kind = match args.first().and_then(|tree| registry::find_tree_kind(tree)) {
+        Some(kind) => kind,
+        None => {
+            explain_usage();
+            return EXIT_USAGE;
+        }
+    };
+    let key = match &args[1..] {
+        [] => None,
+        [option, value] if option == "--key" => Some(value.as_str()),
+        _ => {
+            explain_usage();
+            return EXIT_USAGE;
+        }
+    };
+    match registry::key_type_for(kind, key) {
+        Ok(key_type) => {
+            run_explain_mode(kind, (kind.create)(key_type));
+            EXIT_OK
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            EXIT_USAGE
+        }
+    }
+}
+
+// Insert or delete one value with tracing on, Err when the value is not a key of this tree
+pub fn explain(tree: &mut dyn DynTree, command: &str, value: &str) -> Result<Vec<Step>, String> {
+    let (res, steps) = trace::record_steps(|| {
+        if command == "insert" { tree.insert(value) } else { tree.delete(value) }
+    });
+    res?;
+    Ok(steps)
+}
+
+fn print_steps(title: &str, steps: &[Step]) {
+    println!("----- {} ({} steps) -----", title, steps.len());
+    for (i, step) in steps.iter().enumerate() {
+        println!("{:>4}. {}", i + 1, step.event);
+        for line in step.diagram.iter().flatten() {
+            println!("      {}", line);
+        }
+    }
+}
+
+fn run_explain_mode(kind: &TreeKind, mut tree: Box<dyn DynTree>) {
+    println!("{} with {} keys", kind.title, tree.key_type().name());
+    explain_help_list();
+    let mut editor = LineEditor::new(vec!["insert", "delete", "print", "exit"]);
+    let prompt = format!("explain {}> ", kind.command);
+    while let Some(input) = editor.read_line(&prompt) {
+        let words: Vec<&str> = input.split_whitespace().collect();
+        match words.first().copied() {
+            None => continue,
+            Some("exit") | Some("0") => break,
+            Some("print") => tree.print(),
+            Some(command @ "insert") | Some(command @ "delete") => {
+                for value in &words[1..] {
+                    match explain(tree.as_mut(), command, value) {
+                        Ok(steps) => {
+                            print_steps(&format!("{} {}", command, value), &steps);
+                            tree.print();
+                        }
+                        Err(e) => println!("{}", e),
+                    }
+                }
+            }
+            Some(_) => explain_help_list(),
+        }
+    }
+    println!("Thank you! Hope to see you again!");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::key::KeyType;
+    use ECE522_project::trace::TraceEvent;
+
+    fn tree(command: &str, key_type: KeyType, values: &[&str]) -> Box<dyn DynTree> {
+        let kind = registry::find_tree_kind(command).unwrap();
+        let mut tree = (kind.create)(key_type);
+        for value in values {
+            tree.insert_quiet(value).unwrap();
+        }
+        tree
+    }
+
+    fn compare(key: &str, node: &str) -> TraceEvent {
+        TraceEvent::Compare { key: key.to_string(), node: node.to_string() }
+    }
+
+    fn height(node: &str, old: i32, new: i32) -> TraceEvent {
+        TraceEvent::HeightUpdate { node: node.to_string(), old, new }
+    }
+
+    fn recolor(node: &str, color: &str) -> TraceEvent {
+        TraceEvent::Recolor { node: node.to_string(), color: color.to_string() }
+    }
+
+    fn pivot(node: &str) -> String {
+        node.to_string()
+    }
+
+    #[test]
+    fn avl_left_right_rotation() {
+        let mut avl = tree("avl", KeyType::Int, &["3", "1"]);
+        let steps = explain(avl.as_mut(), "insert", "2").unwrap();
+        let events: Vec<TraceEvent> = steps.iter().map(|step| step.event.clone()).collect();
+        assert_eq!(events, vec![
+            compare("2", "3"),
+            compare("2", "1"),
+            height("1", 1, 2),
+            TraceEvent::RotateLR { pivot: pivot("3") },
+            TraceEvent::RotateLeft { pivot: pivot("1") },
+            height("1", 2, 1),
+            height("2", 1, 2),
+            TraceEvent::RotateRight { pivot: pivot("3") },
+            height("3", 2, 1),
+        ]);
+        // each single rotation shows the subtree it rotated
+        let drawn: Vec<usize> = (0..steps.len()).filter(|&i| steps[i].diagram.is_some()).collect();
+        assert_eq!(drawn, vec![6, 8]);
+        assert_eq!(steps[6].diagram.as_ref().unwrap()[0], "├ Subtree 2 : 2");
+        assert_eq!(steps[8].diagram.as_ref().unwrap()[4], "| └ R  3 : 1");
+        assert_eq!(avl.pre_order(), "[2, 1, 3]");
+    }
+
+    #[test]
+    fn rb_insert_case_3() {
+        let mut rb = tree("rb", KeyType::Unsigned, &["1", "2"]);
+        let steps = explain(rb.as_mut(), "insert", "3").unwrap();
+        let events: Vec<TraceEvent> = steps.iter().map(|step| step.event.clone()).collect();
+        assert_eq!(events, vec![
+            compare("3", "1"),
+            compare("3", "2"),
+            TraceEvent::Case { operation: "insert", number: 3, description: ECE522_project::RBTree::INSERT_CASES[2] },
+            recolor("2", "Black"),
+            recolor("1", "Red"),
+            TraceEvent::RotateLeft { pivot: pivot("1") },
+        ]);
+        // comparisons and cases change nothing, every recoloring and rotation shows the whole tree
+        let drawn: Vec<bool> = steps.iter().map(|step| step.diagram.is_some()).collect();
+        assert_eq!(drawn, vec![false, false, false, true, true, true]);
+        assert_eq!(steps[4].diagram.as_ref().unwrap()[0], "├ Root 1:Red");
+        assert_eq!(steps[5].diagram.as_ref().unwrap()[..2], ["├ Root 2:Black", "| ├ L 1:Red"]);
+        assert_eq!(rb.pre_order(), "[2, 1, 3]");
+    }
+
+    #[test]
+    fn keys_follow_the_key_type() {
+        let mut rb = tree("rb", KeyType::Str, &["pear", "apple"]);
+        let steps = explain(rb.as_mut(), "insert", "fig").unwrap();
+        assert_eq!(steps[0].event, compare("\"fig\"", "\"pear\""));
+        assert!(explain(tree("avl", KeyType::Int, &[]).as_mut(), "insert", "x").is_err());
+    }
+
+    #[test]
+    fn every_delete_case_can_be_drawn() {
+        // the red-black delete recolors while it holds on to nodes, drawing the tree must not clash with that
+        for command in ["avl", "rb"] {
+            let mut tree = tree(command, KeyType::Int, &[]);
+            let mut seed: u64 = 7;
+            for _ in 0..2000 {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                let value = ((seed >> 33) % 64).to_string();
+                let command = if (seed >> 20).is_multiple_of(3) { "delete" } else { "insert" };
+                explain(tree.as_mut(), command, &value).unwrap();
+                assert!(tree.validate());
+            }
+        }
+    }
+}
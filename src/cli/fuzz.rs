@@ -0,0 +1,254 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ECE522_project::codec::crc32;
+use ECE522_project::fuzz::{self, FuzzTarget};
+use ECE522_project::workload::Rng;
+
+use crate::cli::{EXIT_FAILED, EXIT_OK, EXIT_USAGE};
+
+// Fuzzing with a plain `cargo run`, no extra tools or network needed
+//     cargo run fuzz <avl|rb|bst|all> [<corpus dir>] [--runs N] [--seed S] [--max-len N]
+//     cargo run fuzz <avl|rb|bst|all> <input file>
+//
+// Every input of the corpus directory (fuzz/corpus/<target> by default) is checked first, then `--runs`
+// mutations of them are tried. A mutation that makes the tree do something new (a fix-up case, a rotation
+// after another one, an operation at a new tree size, see `fuzz::check_with_features`) is saved to the
+// corpus. A failing input is minimized and saved to fuzz/artifacts/<target>/, running it again with the
+// second form prints its operations and what went wrong.
+
+const DEFAULT_RUNS: u64 = 10000;
+const DEFAULT_MAX_LEN: usize = 256;
+const CORPUS_DIR: &str = "fuzz/corpus";
+const ARTIFACTS_DIR: &str = "fuzz/artifacts";
+
+fn fuzz_usage() {
+    eprintln!("Usage: cargo run fuzz <avl|rb|bst|all> [<corpus dir>] [--runs N] [--seed S] [--max-len N]\n\
+               \x20      cargo run fuzz <avl|rb|bst|all> <input file>");
+}
+
+// `cargo run fuzz ...`, `args` starts after `fuzz`
+pub fn run_fuzz_command(args: &[String]) -> i32 {
+    let targets: Vec<FuzzTarget> = match args.first().map(|s| s.as_str()) {
+        Some("all") => FuzzTarget::ALL.to_vec(),
+        Some(name) => match name.parse() {
+            Ok(target) => vec![target],
+            Err(e) => {
+                eprintln!("{}", e);
+                return EXIT_USAGE;
+            }
+        },
+        None => {
+            fuzz_usage();
+            return EXIT_USAGE;
+        }
+    };
+    let mut path = None;
+    let mut runs = DEFAULT_RUNS;
+    let mut seed = None;
+    let mut max_len = DEFAULT_MAX_LEN;
+    let mut i = 1;
+    while i < args.len() {
+        let value = args.get(i + 1);
+        match (args[i].as_str(), value) {
+            ("--runs", Some(value)) => match value.parse() {
+                Ok(n) => runs = n,
+                Err(_) => {
+                    eprintln!("'{}' is not a valid number of runs", value);
+                    return EXIT_USAGE;
+                }
+            },
+            ("--seed", Some(value)) => match value.parse() {
+                Ok(n) => seed = Some(n),
+                Err(_) => {
+                    eprintln!("'{}' is not a valid seed", value);
+                    return EXIT_USAGE;
+                }
+            },
+            ("--max-len", Some(value)) => match value.parse() {
+                Ok(n) if n > 0 => max_len = n,
+                _ => {
+                    eprintln!("'{}' is not a valid input length", value);
+                    return EXIT_USAGE;
+                }
+            },
+            (arg, _) if path.is_none() && !arg.starts_with("--") => {
+                path = Some(PathBuf::from(arg));
+                i += 1;
+                continue;
+            }
+            _ => {
+                fuzz_usage();
+                return EXIT_USAGE;
+            }
+        }
+        i += 2;
+    }
+    if let Some(file) = path.as_ref().filter(|path| path.is_file()) {
+        return run_input(&targets, file);
+    }
+
+    let seed = seed.unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64);
+    println!("Seed {} (use --seed {} to fuzz the same inputs again)", seed, seed);
+    // the trees are expected to panic on some inputs, the failures are reported without the panic messages
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let mut code = EXIT_OK;
+    for &target in targets.iter() {
+        let dir = path.clone().unwrap_or_else(|| Path::new(CORPUS_DIR).join(target.name()));
+        if let Err(e) = fuzz_target(target, &dir, runs, seed, max_len) {
+            eprintln!("{}", e);
+            code = EXIT_FAILED;
+        }
+    }
+    panic::set_hook(hook);
+    code
+}
+
+// Check one input on every target and show what it does
+fn run_input(targets: &[FuzzTarget], file: &Path) -> i32 {
+    let data = match fs::read(file) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("Cannot read {}: {}", file.display(), e);
+            return EXIT_FAILED;
+        }
+    };
+    let ops = fuzz::decode(&data);
+    println!("{} bytes, {} operations:", data.len(), ops.len());
+    for op in ops.iter() {
+        println!("    {}", op);
+    }
+    let mut code = EXIT_OK;
+    for &target in targets {
+        match fuzz::check(target, &data) {
+            Ok(()) => println!("{}: ok", target.name()),
+            Err(e) => {
+                println!("{}: FAILED at {}", target.name(), e);
+                code = EXIT_FAILED;
+            }
+        }
+    }
+    code
+}
+
+// The inputs of a corpus directory, sorted by file name so a seed always gives the same run
+fn load_corpus(dir: &Path) -> Result<Vec<Vec<u8>>, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| format!("Cannot read {}: {}", dir.display(), e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    files.sort();
+    files.iter()
+        .map(|file| fs::read(file).map_err(|e| format!("Cannot read {}: {}", file.display(), e)))
+        .collect()
+}
+
+// Saved under its checksum, so the same input is only saved once
+fn save_input(dir: &Path, prefix: &str, data: &[u8]) -> Result<PathBuf, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    let file = dir.join(format!("{}{:08x}", prefix, crc32(data)));
+    fs::write(&file, data).map_err(|e| format!("Cannot write {}: {}", file.display(), e))?;
+    Ok(file)
+}
+
+// One to four random changes to `input`: flip a bit, overwrite / insert / remove / duplicate bytes,
+// or join it with another input of the corpus
+fn mutate(rng: &mut Rng, input: &[u8], corpus: &[Vec<u8>], max_len: usize) -> Vec<u8> {
+    let mut data = input.to_vec();
+    for _ in 0..=rng.below(4) {
+        let len = data.len() as u64;
+        match rng.below(6) {
+            0 if len > 0 => {
+                let i = rng.below(len) as usize;
+                data[i] ^= 1 << rng.below(8);
+            }
+            1 if len > 0 => {
+                let i = rng.below(len) as usize;
+                data[i] = rng.below(256) as u8;
+            }
+            3 if len > 0 => {
+                let start = rng.below(len) as usize;
+                let end = start + 1 + rng.below(len - start as u64) as usize;
+                data.drain(start..end);
+            }
+            4 if len > 0 => {
+                let start = rng.below(len) as usize;
+                let end = start + 1 + rng.below(len - start as u64) as usize;
+                let copy = data[start..end].to_vec();
+                let at = rng.below(len + 1) as usize;
+                data.splice(at..at, copy);
+            }
+            5 if !corpus.is_empty() => {
+                let other = &corpus[rng.below(corpus.len() as u64) as usize];
+                let keep = rng.below(len + 1) as usize;
+                let from = rng.below(other.len() as u64 + 1) as usize;
+                data.truncate(keep);
+                data.extend_from_slice(&other[from..]);
+            }
+            _ => {
+                let at = rng.below(len + 1) as usize;
+                let bytes: Vec<u8> = (0..=rng.below(4)).map(|_| rng.below(256) as u8).collect();
+                data.splice(at..at, bytes);
+            }
+        }
+    }
+    data.truncate(max_len);
+    data
+}
+
+// Minimize a failing input, save it and explain the failure
+fn failure(target: FuzzTarget, data: &[u8]) -> String {
+    let minimal = fuzz::minimize(target, data);
+    let reason = fuzz::check(target, &minimal).err().unwrap_or_default();
+    let dir = Path::new(ARTIFACTS_DIR).join(target.name());
+    let saved = match save_input(&dir, "crash-", &minimal) {
+        Ok(file) => format!("saved to {}, run `cargo run fuzz {} {}` to see it again", file.display(), target.name(), file.display()),
+        Err(e) => e,
+    };
+    let ops: Vec<String> = fuzz::decode(&minimal).iter().map(|op| format!("    {}", op)).collect();
+    format!("{}: FAILED at {}\nminimized from {} to {} bytes, {}\n{}",
+            target.name(), reason, data.len(), minimal.len(), saved, ops.join("\n"))
+}
+
+fn fuzz_target(target: FuzzTarget, dir: &Path, runs: u64, seed: u64, max_len: usize) -> Result<(), String> {
+    let mut corpus = load_corpus(dir)?;
+    let mut seen = BTreeSet::new();
+    for data in corpus.iter() {
+        let (result, features) = fuzz::check_with_features(target, data);
+        if result.is_err() {
+            return Err(failure(target, data));
+        }
+        seen.extend(features);
+    }
+    println!("{}: {} inputs in {}, {} features", target.name(), corpus.len(), dir.display(), seen.len());
+
+    let mut rng = Rng::new(seed);
+    let mut added = 0;
+    for run in 1..=runs {
+        let input = match corpus.len() {
+            0 => Vec::new(),
+            n => corpus[rng.below(n as u64) as usize].clone(),
+        };
+        let data = mutate(&mut rng, &input, &corpus, max_len);
+        let (result, features) = fuzz::check_with_features(target, &data);
+        if result.is_err() {
+            return Err(failure(target, &data));
+        }
+        let before = seen.len();
+        seen.extend(features);
+        if seen.len() > before {
+            let file = save_input(dir, "", &data)?;
+            println!("#{} new {} ({} features)", run, file.display(), seen.len());
+            corpus.push(data);
+            added += 1;
+        }
+    }
+    println!("{}: {} runs, {} new inputs, {} features, no failures", target.name(), runs, added, seen.len());
+    Ok(())
+}
@@ -0,0 +1,140 @@
+// The key types the interactive menus can work with
+
+// The characters generated char keys are taken from, ranges in increasing order: only visible characters, none
+// of the controls and spaces. ASCII and Latin from '!' on (without the no-break space and the soft hyphen),
+// the CJK ideographs, the Hangul syllables and the CJK ideographs of extension B.
+const CHAR_KEYS: [(u32, u32); 6] = [
+    (0x21, 0x7E), (0xA1, 0xAC), (0xAE, 0x24F), (0x4E00, 0x9FFF), (0xAC00, 0xD7A3), (0x20000, 0x2A6DF),
+];
+const CHAR_KEY_COUNT: u64 = 94 + 12 + 418 + 20992 + 11172 + 42720;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeyType {
+    Int,
+    Unsigned,
+    Float,
+    Char,
+    Str,
+}
+
+impl KeyType {
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyType::Int => "int (i32)",
+            KeyType::Unsigned => "unsigned (u32)",
+            KeyType::Float => "float (f64, NaN and -0.0 are ordered too)",
+            KeyType::Char => "char",
+            KeyType::Str => "string",
+        }
+    }
+
+    // name used on the command line, e.g. `--key string`
+    pub fn flag(self) -> &'static str {
+        match self {
+            KeyType::Int => "int",
+            KeyType::Unsigned => "unsigned",
+            KeyType::Float => "float",
+            KeyType::Char => "char",
+            KeyType::Str => "string",
+        }
+    }
+
+    pub fn from_flag(flag: &str) -> Option<KeyType> {
+        [KeyType::Int, KeyType::Unsigned, KeyType::Float, KeyType::Char, KeyType::Str]
+            .iter()
+            .copied()
+            .find(|key_type| key_type.flag() == flag)
+    }
+
+    // what a single value looks like, for error messages
+    pub fn describe(self) -> &'static str {
+        match self {
+            KeyType::Int => "an integer",
+            KeyType::Unsigned => "a no-sign integer",
+            KeyType::Float => "a number",
+            KeyType::Char => "a single character",
+            KeyType::Str => "a word",
+        }
+    }
+
+    // The text of the n-th generated key, bigger n give bigger keys. Err when there is no n-th key: chars are
+    // taken from CHAR_KEYS in order, so there are only CHAR_KEY_COUNT of them.
+    pub fn key_text(self, n: u64) -> Result<String, String> {
+        match self {
+            KeyType::Int | KeyType::Unsigned | KeyType::Float => Ok(n.to_string()),
+            KeyType::Char => {
+                let mut rest = n;
+                for &(first, last) in CHAR_KEYS.iter() {
+                    let size = (last - first + 1) as u64;
+                    if rest < size {
+                        return Ok(char::from_u32(first + rest as u32).unwrap().to_string());
+                    }
+                    rest -= size;
+                }
+                Err(format!("There are only {} different char keys to generate, please use a smaller count", CHAR_KEY_COUNT))
+            }
+            KeyType::Str => Ok(format!("key{:08}", n)),
+        }
+    }
+
+    pub fn example(self) -> &'static str {
+        match self {
+            KeyType::Int => "1 2 3 4 5",
+            KeyType::Unsigned => "1 2 3 4 5",
+            KeyType::Float => "1.5 -2 3.25 NaN",
+            KeyType::Char => "a b c d e",
+            KeyType::Str => "apple banana cherry",
+        }
+    }
+}
+
+// Ask which key type to use, `available` are the types the tree supports, the first one is the default
+pub fn choose_key_type(available: &[KeyType]) -> KeyType {
+    loop {
+        println!("Please choose the key type (press enter for {}):", available[0].name());
+        for (i, key_type) in available.iter().enumerate() {
+            println!("{} - {}", i + 1, key_type.name());
+        }
+        let mut input = String::new();
+        if std::io::stdin().read_line(&mut input).expect("Cannot read!") == 0 || input.trim().is_empty() {
+            return available[0];
+        }
+        match input.trim().parse::<usize>() {
+            Ok(i) if (1..=available.len()).contains(&i) => return available[i - 1],
+            _ => println!("Wrong input! Input should be a number from the list, please try again..."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn char_key(n: u64) -> char {
+        KeyType::Char.key_text(n).unwrap().chars().next().unwrap()
+    }
+
+    #[test]
+    fn char_keys_are_visible_and_keep_the_order() {
+        let mut previous = None;
+        for n in 0..CHAR_KEY_COUNT {
+            let key = char_key(n);
+            assert!(!key.is_whitespace() && !key.is_control(), "{:?} for {}", key, n);
+            if key as u32 >= 0x4E00 {
+                assert!(key.is_alphabetic(), "{:?} for {}", key, n);
+            }
+            assert!(previous < Some(key), "{:?} for {}", key, n);
+            previous = Some(key);
+        }
+        assert_eq!(char_key(0), '!');
+        assert_eq!(char_key(93), '~');
+        assert_eq!(char_key(94), '¡');
+        assert!(KeyType::Char.key_text(CHAR_KEY_COUNT).is_err());
+    }
+
+    #[test]
+    fn other_keys_are_the_number() {
+        assert_eq!(KeyType::Int.key_text(42), Ok("42".to_string()));
+        assert_eq!(KeyType::Str.key_text(42), Ok("key00000042".to_string()));
+    }
+}
@@ -0,0 +1,375 @@
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::process::{Command, Stdio};
+
+// A small line editor for the interactive menu, without any dependency
+//
+//     Left / Right, Home / End, Ctrl-A / Ctrl-E   move in the line
+//     Backspace, Delete, Ctrl-U                    delete a character / the line up to the cursor
+//     Up / Down                                    go through the lines entered before
+//     Tab                                          complete the command name
+//     Ctrl-C                                       drop the line, Ctrl-D on an empty line ends the input
+//
+// The terminal is switched to character mode with `stty` only while a line is read. When the input is
+// not a terminal (a pipe or a file) or `stty` is not available, lines are read as they are.
+
+pub struct LineEditor {
+    // every line entered so far, oldest first
+    history: Vec<String>,
+    // what the first word of a line can be completed to
+    commands: Vec<&'static str>,
+}
+
+// The terminal settings to put back when the line is read
+struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    fn enable() -> Option<RawMode> {
+        let saved = stty(&["-g"])?;
+        stty(&["-icanon", "-echo", "-isig", "min", "1", "time", "0"])?;
+        Some(RawMode { saved: saved.trim().to_string() })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        stty(&[&self.saved]);
+    }
+}
+
+// Run stty on the terminal we read from, None if that does not work
+fn stty(args: &[&str]) -> Option<String> {
+    let output = Command::new("stty").args(args).stdin(Stdio::inherit()).stderr(Stdio::null()).output().ok()?;
+    if output.status.success() { String::from_utf8(output.stdout).ok() } else { None }
+}
+
+// A key press, as far as the editor cares
+enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    Tab,
+    ClearToStart,
+    Cancel,
+    EndOfInput,
+    Ignored,
+}
+
+fn read_byte(input: &mut impl Read) -> Option<u8> {
+    let mut byte = [0u8];
+    match input.read(&mut byte) {
+        Ok(1) => Some(byte[0]),
+        _ => None,
+    }
+}
+
+fn read_key(input: &mut impl Read) -> Key {
+    let byte = match read_byte(input) {
+        Some(byte) => byte,
+        None => return Key::EndOfInput,
+    };
+    match byte {
+        b'\r' | b'\n' => Key::Enter,
+        b'\t' => Key::Tab,
+        0x7f | 0x08 => Key::Backspace,
+        0x01 => Key::Home,
+        0x05 => Key::End,
+        0x15 => Key::ClearToStart,
+        0x03 => Key::Cancel,
+        0x04 => Key::EndOfInput,
+        // escape sequences of the arrow keys and friends: ESC [ x or ESC O x
+        0x1b => match (read_byte(input), read_byte(input)) {
+            (Some(b'['), Some(b'A')) | (Some(b'O'), Some(b'A')) => Key::Up,
+            (Some(b'['), Some(b'B')) | (Some(b'O'), Some(b'B')) => Key::Down,
+            (Some(b'['), Some(b'C')) | (Some(b'O'), Some(b'C')) => Key::Right,
+            (Some(b'['), Some(b'D')) | (Some(b'O'), Some(b'D')) => Key::Left,
+            (Some(b'['), Some(b'H')) | (Some(b'O'), Some(b'H')) => Key::Home,
+            (Some(b'['), Some(b'F')) | (Some(b'O'), Some(b'F')) => Key::End,
+            (Some(b'['), Some(code @ b'1'..=b'8')) => {
+                // ESC [ n ~ : 1 and 7 are Home, 4 and 8 are End, 3 is Delete
+                let _ = read_byte(input);
+                match code {
+                    b'1' | b'7' => Key::Home,
+                    b'4' | b'8' => Key::End,
+                    b'3' => Key::Delete,
+                    _ => Key::Ignored,
+                }
+            }
+            _ => Key::Ignored,
+        },
+        byte if byte < 0x20 => Key::Ignored,
+        byte => {
+            // put the rest of a multi-byte UTF-8 character together
+            let length = if byte >= 0xF0 { 4 } else if byte >= 0xE0 { 3 } else if byte >= 0xC0 { 2 } else { 1 };
+            let mut bytes = vec![byte];
+            for _ in 1..length {
+                match read_byte(input) {
+                    Some(next) => bytes.push(next),
+                    None => break,
+                }
+            }
+            match std::str::from_utf8(&bytes).ok().and_then(|s| s.chars().next()) {
+                Some(c) => Key::Char(c),
+                None => Key::Ignored,
+            }
+        }
+    }
+}
+
+// The longest start that all words share
+fn common_prefix(words: &[&str]) -> String {
+    let mut prefix: String = words[0].to_string();
+    for word in &words[1..] {
+        while !word.starts_with(&prefix) {
+            prefix.pop();
+        }
+    }
+    prefix
+}
+
+impl LineEditor {
+    pub fn new(commands: Vec<&'static str>) -> Self {
+        LineEditor { history: Vec::new(), commands }
+    }
+
+    // Read one line after printing `prompt`, None at the end of the input
+    pub fn read_line(&mut self, prompt: &str) -> Option<String> {
+        print!("{}", prompt);
+        io::stdout().flush().unwrap();
+        let line = if io::stdin().is_terminal() {
+            match RawMode::enable() {
+                Some(_raw) => self.edit(prompt, &mut io::stdin().lock()),
+                None => LineEditor::read_plain(),
+            }
+        } else {
+            LineEditor::read_plain()
+        }?;
+        let line = line.trim().to_string();
+        self.remember(&line);
+        Some(line)
+    }
+
+    // Add a line to the history, unless it is empty or the same as the last one
+    fn remember(&mut self, line: &str) {
+        if !line.is_empty() && self.history.last().map(String::as_str) != Some(line) {
+            self.history.push(line.to_string());
+        }
+    }
+
+    fn read_plain() -> Option<String> {
+        let mut input = String::new();
+        match io::stdin().lock().read_line(&mut input) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(input),
+        }
+    }
+
+    // Edit one line with the keys read from `input`
+    fn edit(&self, prompt: &str, input: &mut impl Read) -> Option<String> {
+        let mut line: Vec<char> = Vec::new();
+        let mut cursor = 0;
+        // where we are in the history, history.len() is the line being written
+        let mut position = self.history.len();
+        let mut draft: Vec<char> = Vec::new();
+        loop {
+            match read_key(input) {
+                Key::Char(c) => {
+                    line.insert(cursor, c);
+                    cursor += 1;
+                }
+                Key::Enter => {
+                    println!();
+                    return Some(line.into_iter().collect());
+                }
+                Key::Backspace if cursor > 0 => {
+                    cursor -= 1;
+                    line.remove(cursor);
+                }
+                Key::Delete if cursor < line.len() => {
+                    line.remove(cursor);
+                }
+                Key::Left if cursor > 0 => cursor -= 1,
+                Key::Right if cursor < line.len() => cursor += 1,
+                Key::Home => cursor = 0,
+                Key::End => cursor = line.len(),
+                Key::ClearToStart => {
+                    line.drain(..cursor);
+                    cursor = 0;
+                }
+                Key::Up if position > 0 => {
+                    if position == self.history.len() {
+                        draft = line.clone();
+                    }
+                    position -= 1;
+                    line = self.history[position].chars().collect();
+                    cursor = line.len();
+                }
+                Key::Down if position < self.history.len() => {
+                    position += 1;
+                    line = if position == self.history.len() {
+                        draft.clone()
+                    } else {
+                        self.history[position].chars().collect()
+                    };
+                    cursor = line.len();
+                }
+                Key::Tab => cursor = self.complete(prompt, &mut line, cursor),
+                Key::Cancel => {
+                    println!("^C");
+                    return Some(String::new());
+                }
+                Key::EndOfInput if line.is_empty() => {
+                    println!();
+                    return None;
+                }
+                _ => continue,
+            }
+            // draw the line again and put the cursor back where it belongs
+            let text: String = line.iter().collect();
+            print!("\r{}{}\x1b[K", prompt, text);
+            if cursor < line.len() {
+                print!("\x1b[{}D", line.len() - cursor);
+            }
+            io::stdout().flush().unwrap();
+        }
+    }
+
+    // Complete the command name under the cursor, lists the choices when there is more than one
+    // returns the new cursor position
+    fn complete(&self, prompt: &str, line: &mut Vec<char>, cursor: usize) -> usize {
+        let typed: String = line[..cursor].iter().collect();
+        // only the first word is a command name
+        if typed.contains(' ') || cursor != line.len() {
+            return cursor;
+        }
+        let matches: Vec<&str> = self.commands.iter().copied().filter(|c| c.starts_with(&typed)).collect();
+        match matches.len() {
+            0 => cursor,
+            1 => {
+                *line = format!("{} ", matches[0]).chars().collect();
+                line.len()
+            }
+            _ => {
+                let prefix = common_prefix(&matches);
+                if prefix.len() > typed.len() {
+                    *line = prefix.chars().collect();
+                } else {
+                    println!();
+                    println!("{}", matches.join("  "));
+                    print!("{}", prompt);
+                }
+                line.len()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor(history: &[&str]) -> LineEditor {
+        let mut editor = LineEditor::new(vec!["insert", "inorder", "delete", "help"]);
+        for line in history {
+            editor.remember(line);
+        }
+        editor
+    }
+
+    fn edit(editor: &LineEditor, keys: &str) -> Option<String> {
+        editor.edit("> ", &mut keys.as_bytes())
+    }
+
+    #[test]
+    fn keys_are_decoded() {
+        let keys = |bytes: &[u8]| {
+            let mut input = bytes;
+            let mut decoded = Vec::new();
+            loop {
+                match read_key(&mut input) {
+                    Key::EndOfInput => return decoded,
+                    Key::Char(c) => decoded.push(c.to_string()),
+                    Key::Enter => decoded.push("Enter".to_string()),
+                    Key::Backspace => decoded.push("Backspace".to_string()),
+                    Key::Delete => decoded.push("Delete".to_string()),
+                    Key::Left => decoded.push("Left".to_string()),
+                    Key::Right => decoded.push("Right".to_string()),
+                    Key::Up => decoded.push("Up".to_string()),
+                    Key::Down => decoded.push("Down".to_string()),
+                    Key::Home => decoded.push("Home".to_string()),
+                    Key::End => decoded.push("End".to_string()),
+                    Key::Tab => decoded.push("Tab".to_string()),
+                    Key::ClearToStart => decoded.push("ClearToStart".to_string()),
+                    Key::Cancel => decoded.push("Cancel".to_string()),
+                    Key::Ignored => decoded.push("Ignored".to_string()),
+                }
+            }
+        };
+        assert_eq!(keys(b"a\r\n\t\x7f\x08"), vec!["a", "Enter", "Enter", "Tab", "Backspace", "Backspace"]);
+        assert_eq!(keys(b"\x1b[A\x1b[B\x1bOC\x1bOD"), vec!["Up", "Down", "Right", "Left"]);
+        assert_eq!(keys(b"\x1b[H\x1b[F\x1b[1~\x1b[4~\x1b[7~\x1b[8~"), vec!["Home", "End", "Home", "End", "Home", "End"]);
+        assert_eq!(keys(b"\x1b[3~\x1b[5~\x1b[Z"), vec!["Delete", "Ignored", "Ignored"]);
+        assert_eq!(keys(b"\x01\x05\x15\x03\x02"), vec!["Home", "End", "ClearToStart", "Cancel", "Ignored"]);
+        assert_eq!(keys("é中😀".as_bytes()), vec!["é", "中", "😀"]);
+        // Ctrl-D ends the input like the end of the stream
+        assert!(keys(b"\x04a").is_empty());
+    }
+
+    #[test]
+    fn the_cursor_moves_and_edits_in_the_line() {
+        let editor = editor(&[]);
+        assert_eq!(edit(&editor, "bc\x1b[D\x1b[Da\r").as_deref(), Some("abc"));
+        assert_eq!(edit(&editor, "abc\x01\x1b[3~\x05d\r").as_deref(), Some("bcd"));
+        assert_eq!(edit(&editor, "abc\x1b[D\x7f\r").as_deref(), Some("ac"));
+        assert_eq!(edit(&editor, "abc\x1b[Dx\x15\r").as_deref(), Some("c"));
+        // Backspace at the start and Delete at the end do nothing
+        assert_eq!(edit(&editor, "\x7fab\x1b[3~\r").as_deref(), Some("ab"));
+    }
+
+    #[test]
+    fn cancel_drops_the_line_and_end_of_input_only_ends_an_empty_line() {
+        let editor = editor(&[]);
+        assert_eq!(edit(&editor, "abc\x03").as_deref(), Some(""));
+        assert_eq!(edit(&editor, "\x04"), None);
+        assert_eq!(edit(&editor, "ab\x04c\r").as_deref(), Some("abc"));
+        assert_eq!(edit(&editor, ""), None);
+    }
+
+    #[test]
+    fn up_and_down_go_through_the_history() {
+        let editor = editor(&["insert 1", "delete 2"]);
+        assert_eq!(edit(&editor, "\x1b[A\r").as_deref(), Some("delete 2"));
+        assert_eq!(edit(&editor, "\x1b[A\x1b[A\x1b[A\r").as_deref(), Some("insert 1"));
+        // going down past the newest line brings back what was being typed
+        assert_eq!(edit(&editor, "help\x1b[A\x1b[A\x1b[B\x1b[B\x1b[B\r").as_deref(), Some("help"));
+        assert_eq!(edit(&editor, "\x1b[A 3\r").as_deref(), Some("delete 2 3"));
+    }
+
+    #[test]
+    fn the_history_skips_empty_and_repeated_lines() {
+        let mut editor = editor(&["insert 1", "insert 1", "", "delete 1", "insert 1"]);
+        editor.remember("insert 1");
+        assert_eq!(editor.history, vec!["insert 1", "delete 1", "insert 1"]);
+    }
+
+    #[test]
+    fn tab_completes_the_command_name() {
+        let editor = editor(&[]);
+        assert_eq!(edit(&editor, "de\t5\r").as_deref(), Some("delete 5"));
+        // "in" is the start of two commands and can not grow, "ins" is only insert
+        assert_eq!(edit(&editor, "in\t\r").as_deref(), Some("in"));
+        assert_eq!(edit(&editor, "in\ts\t\r").as_deref(), Some("insert "));
+        assert_eq!(edit(&editor, "x\t\r").as_deref(), Some("x"));
+        // only the first word is completed
+        assert_eq!(edit(&editor, "help de\t\r").as_deref(), Some("help de"));
+        assert_eq!(common_prefix(&["inorder", "insert"]), "in");
+    }
+}
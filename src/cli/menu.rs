@@ -0,0 +1,582 @@
+use std::fs;
+
+use crate::cli::key::{self, KeyType};
+use crate::cli::line_editor::LineEditor;
+use crate::cli::registry::{DynTree, TreeKind};
+use crate::cli::workload::{check_options, run_churn, run_gen, CHURN_USAGE, GEN_USAGE};
+use crate::cli::{EXIT_FAILED, EXIT_OK};
+
+// The interactive menu, the same for every registered tree type
+
+// A menu command: its number in the old numbered menu, its name, other names, how to call it and what it does
+struct MenuCommand {
+    code: i32,
+    name: &'static str,
+    aliases: &'static [&'static str],
+    usage: &'static str,
+    about: &'static str,
+}
+
+const HELP: i32 = 23;
+// the commands that can change the tree, only these are recorded in the history
+const CHANGES_TREE: [i32; 5] = [1, 2, 10, 21, 22];
+
+const COMMANDS: [MenuCommand; 24] = [
+    MenuCommand { code: 0, name: "exit", aliases: &["quit"], usage: "exit", about: "leave the menu" },
+    MenuCommand { code: 1, name: "insert", aliases: &[], usage: "insert <values>", about: "insert a node/some nodes" },
+    MenuCommand { code: 2, name: "delete", aliases: &[], usage: "delete <values>", about: "delete a node/some nodes" },
+    MenuCommand { code: 3, name: "leaves", aliases: &[], usage: "leaves", about: "count the number of leaves" },
+    MenuCommand { code: 4, name: "height", aliases: &[], usage: "height", about: "check the height of the tree" },
+    MenuCommand { code: 5, name: "inorder", aliases: &["in-order"], usage: "inorder", about: "print the in-order traversal" },
+    MenuCommand { code: 6, name: "preorder", aliases: &["pre-order"], usage: "preorder", about: "print the pre-order traversal" },
+    MenuCommand { code: 7, name: "postorder", aliases: &["post-order"], usage: "postorder", about: "print the post-order traversal" },
+    MenuCommand { code: 8, name: "empty", aliases: &[], usage: "empty", about: "check it is empty or not" },
+    MenuCommand { code: 9, name: "print", aliases: &[], usage: "print", about: "print this tree" },
+    MenuCommand { code: 10, name: "update", aliases: &[], usage: "update <old> <new>", about: "replace the value old with new" },
+    MenuCommand { code: 11, name: "contains", aliases: &["exist"], usage: "contains <values>", about: "check whether values exist" },
+    MenuCommand { code: 12, name: "validate", aliases: &[], usage: "validate", about: "check the tree is valid" },
+    MenuCommand { code: 13, name: "count", aliases: &["total"], usage: "count", about: "total number of elements" },
+    MenuCommand { code: 14, name: "undo", aliases: &[], usage: "undo", about: "undo the last change" },
+    MenuCommand { code: 15, name: "redo", aliases: &[], usage: "redo", about: "redo the last undone change" },
+    MenuCommand { code: 16, name: "history", aliases: &[], usage: "history", about: "show the changes made in this session" },
+    MenuCommand {
+        code: 17, name: "replay", aliases: &[], usage: "replay",
+        about: "rebuild the tree from scratch by running the history again",
+    },
+    MenuCommand {
+        code: 18, name: "save-history", aliases: &[], usage: "save-history <file>",
+        about: "save the history as a command script (see `cargo run run`)",
+    },
+    MenuCommand { code: 19, name: "save", aliases: &[], usage: "save <file>", about: "save the tree to a file" },
+    MenuCommand {
+        code: 20, name: "load", aliases: &[], usage: "load <file>",
+        about: "replace the tree with one saved before (starts a new history)",
+    },
+    MenuCommand { code: 21, name: "gen", aliases: &[], usage: GEN_USAGE, about: "insert reproducible keys" },
+    MenuCommand {
+        code: 22, name: "churn", aliases: &[], usage: CHURN_USAGE,
+        about: "run a reproducible mix of inserts, deletes and updates, validating as it goes",
+    },
+    MenuCommand { code: HELP, name: "help", aliases: &["?"], usage: "help [command]", about: "show all commands, or more about one" },
+];
+
+// A command by its name, one of its other names or its number
+fn find_command(word: &str) -> Option<&'static MenuCommand> {
+    let code = word.parse::<i32>().ok();
+    COMMANDS.iter().find(|c| c.name == word || c.aliases.contains(&word) || Some(c.code) == code)
+}
+
+fn help_list(kind: &TreeKind) {
+    println!("=========== {} HELP MANUAL ===========", kind.title);
+    for command in COMMANDS.iter() {
+        println!("{:>2} - {}: {}", command.code, command.name, command.about);
+    }
+    println!("Type a command or its number. Values can follow on the same line (e.g. insert 5 3 8),\n\
+              otherwise they are asked for. `help <command>` tells more about one command.\n\
+              Tab completes command names, Up and Down go through the lines entered before.");
+    println!("=======================================");
+}
+
+fn help_command(kind: &TreeKind, word: &str) {
+    let command = match find_command(word) {
+        Some(command) => command,
+        None => {
+            println!("Unknown command '{}', type `help` to see all commands.", word);
+            return;
+        }
+    };
+    println!("Usage: {}", command.usage);
+    let about = if command.code == 12 { kind.validation } else { command.about };
+    println!("  {}", about);
+    let mut names: Vec<String> = command.aliases.iter().map(|a| a.to_string()).collect();
+    names.push(command.code.to_string());
+    println!("  also: {}", names.join(", "));
+}
+
+// One line from the user, None at the end of the input
+fn read_line() -> Option<String> {
+    let mut input = String::new();
+    match std::io::stdin().read_line(&mut input) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(input),
+    }
+}
+
+fn read_words() -> Vec<String> {
+    read_line().unwrap_or_default().split_whitespace().map(String::from).collect()
+}
+
+// A change made to the tree, as a batch command (e.g. "insert 1 2 3")
+// How many of the last changes can be undone. Each one keeps a copy of the tree, older changes only keep
+// their command, for `replay` and `save-history`.
+const MAX_UNDO: usize = 50;
+
+struct Step {
+    command: String,
+    // the tree on the other side of the change: before it while the step is in the history, after it once undone,
+    // None once the step is too old to be undone
+    other: Option<Vec<u8>>,
+}
+
+struct Session {
+    kind: &'static TreeKind,
+    key_type: KeyType,
+    tree: Box<dyn DynTree>,
+    // the tree the history starts from, and the file it was loaded from if any
+    base: Vec<u8>,
+    base_file: Option<String>,
+    // changes that can be undone, oldest first
+    history: Vec<Step>,
+    // undone changes, the next one to redo last
+    undone: Vec<Step>,
+}
+
+// Run the menu, starting from the tree saved in `load` if given, returns the exit code
+pub fn run_menu(kind: &'static TreeKind, load: Option<&str>) -> i32 {
+    let key_type = key::choose_key_type(kind.key_types);
+    let tree = (kind.create)(key_type);
+    let base = tree.snapshot();
+    let mut session = Session {
+        kind,
+        key_type,
+        tree,
+        base,
+        base_file: None,
+        history: Vec::new(),
+        undone: Vec::new(),
+    };
+    if let Some(file) = load {
+        if !session.load(file) {
+            return EXIT_FAILED;
+        }
+    }
+    let mut editor = LineEditor::new(COMMANDS.iter().map(|c| c.name).collect());
+    let prompt = format!("{}> ", kind.command);
+    help_list(kind);
+    loop {
+        let line = match editor.read_line(&prompt) {
+            None => break,
+            Some(line) => line,
+        };
+        let words: Vec<String> = line.split_whitespace().map(String::from).collect();
+        let (first, args) = match words.split_first() {
+            None => continue,
+            Some(split) => split,
+        };
+        let command = match find_command(first) {
+            Some(command) => command,
+            None => {
+                println!("Unknown command '{}', type `help` to see all commands.", first);
+                continue;
+            }
+        };
+        match command.code {
+            0 => break,
+            HELP => match args.first() {
+                None => help_list(kind),
+                Some(word) => help_command(kind, word),
+            },
+            14 => session.undo(),
+            15 => session.redo(),
+            16 => session.print_history(),
+            17 => session.replay(),
+            18 => {
+                if let Some(file) = file_name(args) {
+                    session.save_history(&file);
+                }
+            }
+            19 => {
+                if let Some(file) = file_name(args) {
+                    session.save(&file);
+                }
+            }
+            20 => {
+                if let Some(file) = file_name(args) {
+                    session.load(&file);
+                }
+            }
+            code => session.run(code, args.to_vec()),
+        }
+    }
+    println!("Thank you! Hope to see you again!");
+    EXIT_OK
+}
+
+// The file name given after the command, or asked for
+fn file_name(args: &[String]) -> Option<String> {
+    if let Some(file) = args.first() {
+        return Some(file.clone());
+    }
+    println!("Please input the file name:");
+    let file = read_words().first().cloned();
+    if file.is_none() {
+        println!("File name can not be empty!");
+    }
+    file
+}
+
+impl Session {
+    fn restore(&mut self, snapshot: &[u8]) {
+        // the snapshots were taken from this very tree, so they can always be read back
+        self.tree.restore(snapshot).expect("invalid snapshot");
+    }
+
+    // Run a menu command, a change to the tree goes into the history
+    fn run(&mut self, code: i32, args: Vec<String>) {
+        if !CHANGES_TREE.contains(&code) {
+            run_choice(self.kind, self.key_type, self.tree.as_mut(), code, args);
+            return;
+        }
+        let before = self.tree.snapshot();
+        if let Some(command) = run_choice(self.kind, self.key_type, self.tree.as_mut(), code, args) {
+            self.history.push(Step { command, other: Some(before) });
+            self.undone.clear();
+            // the copy of the tree that just left the undo window is dropped, its command stays
+            if self.history.len() > MAX_UNDO {
+                let too_old = self.history.len() - MAX_UNDO - 1;
+                self.history[too_old].other = None;
+            }
+        }
+    }
+
+    // Put the other side of `step` in place of the tree, the step keeps the tree it replaced
+    fn swap(&mut self, mut step: Step) -> Step {
+        let current = self.tree.snapshot();
+        // only steps that still have their copy get here
+        self.restore(step.other.as_ref().unwrap());
+        step.other = Some(current);
+        step
+    }
+
+    fn undo(&mut self) {
+        match self.history.pop() {
+            None => println!("UNDO FAILED: Nothing to undo!"),
+            Some(step) if step.other.is_none() => {
+                println!("UNDO FAILED: Only the last {} changes can be undone!", MAX_UNDO);
+                self.history.push(step);
+            }
+            Some(step) => {
+                let step = self.swap(step);
+                println!("Undo '{}' successfully.", step.command);
+                self.undone.push(step);
+            }
+        }
+    }
+
+    fn redo(&mut self) {
+        match self.undone.pop() {
+            None => println!("REDO FAILED: Nothing to redo!"),
+            Some(step) => {
+                let step = self.swap(step);
+                println!("Redo '{}' successfully.", step.command);
+                self.history.push(step);
+            }
+        }
+    }
+
+    fn print_history(&self) {
+        if self.history.is_empty() && self.undone.is_empty() {
+            println!("No changes yet.");
+            return;
+        }
+        for (i, step) in self.history.iter().enumerate() {
+            println!("{:>4}. {}", i + 1, step.command);
+        }
+        // the undone changes in the order they would be redone
+        for step in self.undone.iter().rev() {
+            println!("      {} (undone)", step.command);
+        }
+    }
+
+    fn save(&self, file: &str) {
+        match fs::write(file, self.tree.snapshot()) {
+            Ok(()) => println!("Saved the tree to {}.", file),
+            Err(e) => println!("SAVE FAILED: {}", e),
+        }
+    }
+
+    // Replace the tree with the one saved in `file`, false if it can not be loaded
+    fn load(&mut self, file: &str) -> bool {
+        let loaded = fs::read(file).map_err(|e| e.to_string()).and_then(|bytes| {
+            self.tree.restore(&bytes)?;
+            Ok(bytes)
+        });
+        match loaded {
+            Ok(bytes) => {
+                self.base = bytes;
+                self.base_file = Some(file.to_string());
+                self.history.clear();
+                self.undone.clear();
+                println!("Loaded a tree of {} elements from {}.", self.tree.count(), file);
+                true
+            }
+            Err(e) => {
+                println!("LOAD FAILED: Cannot load {}: {}", file, e);
+                false
+            }
+        }
+    }
+
+    // Run the history again on the tree it started from, printing the tree after every step
+    fn replay(&mut self) {
+        let base = self.base.clone();
+        self.restore(&base);
+        for (i, step) in self.history.iter().enumerate() {
+            println!("----- step {}: {} -----", i + 1, step.command);
+            run_command(self.tree.as_mut(), &step.command);
+            self.tree.print();
+        }
+        println!("Replayed {} steps.", self.history.len());
+    }
+
+    fn save_history(&self, path: &str) {
+        let load = match &self.base_file {
+            Some(file) => format!(" --load {}", file),
+            None => String::new(),
+        };
+        let mut script = format!("# session of `cargo run {}` with {} keys\n\
+                                  # run it again with: cargo run run {} --tree {} --key {}{}\n",
+                                 self.kind.command, self.key_type.name(), path, self.kind.command, self.key_type.flag(), load);
+        for step in &self.history {
+            script.push_str(&step.command);
+            script.push('\n');
+        }
+        match fs::write(path, script) {
+            Ok(()) => println!("Saved {} steps to {}.", self.history.len(), path),
+            Err(e) => println!("SAVE FAILED: {}", e),
+        }
+    }
+}
+
+// Run a recorded command (insert / delete / update / gen / churn) again
+fn run_command(tree: &mut dyn DynTree, command: &str) {
+    let words: Vec<&str> = command.split_whitespace().collect();
+    // recorded commands only contain keys that were valid when they were recorded
+    match words[0] {
+        "insert" => words[1..].iter().for_each(|word| { tree.insert(word).unwrap(); }),
+        "delete" => words[1..].iter().for_each(|word| { tree.delete(word).unwrap(); }),
+        "update" => { tree.update(words[1], words[2]).unwrap(); }
+        "gen" | "churn" => { run_workload(tree, command); }
+        _ => unreachable!(),
+    }
+}
+
+// Run `gen ...` or `churn ...`, returns the command to record (with every option filled in)
+// or None if the options are wrong and nothing was done
+fn run_workload(tree: &mut dyn DynTree, command: &str) -> Option<String> {
+    let words: Vec<&str> = command.split_whitespace().collect();
+    if let Err(e) = check_options(words[0], &words[1..]) {
+        println!("{} FAILED: {}", words[0].to_uppercase(), e);
+        return None;
+    }
+    let result = if words[0] == "gen" { run_gen(tree, &words[1..]) } else { run_churn(tree, &words[1..]) };
+    match result {
+        Ok(command) => Some(command),
+        Err(e) => {
+            println!("{} FAILED: {}", words[0].to_uppercase(), e);
+            // the options were fine, so the run failed half way and may have changed the tree already:
+            // keep it in the history so it can be undone
+            Some(command.trim().to_string())
+        }
+    }
+}
+
+// The values given after the command, or asked for with `question`
+fn values_or_ask(args: Vec<String>, question: &str) -> Vec<String> {
+    if !args.is_empty() {
+        return args;
+    }
+    println!("{}", question);
+    read_words()
+}
+
+// Run one menu command (1 - 13, 21 and 22) with the values typed after it,
+// returns the command to record if the tree was changed
+fn run_choice(kind: &TreeKind, key_type: KeyType, tree: &mut dyn DynTree, code: i32, args: Vec<String>) -> Option<String> {
+    match code {
+        1 => {
+            let question = format!("Please input what kind of value you want to add. Separate by one whitespace.\n\
+            e.g.{}", key_type.example());
+            let mut output = Vec::new();
+            for word in values_or_ask(args, &question) {
+                match tree.insert(&word) {
+                    Ok(true) => output.push(word),
+                    Ok(false) => {}
+                    Err(e) => println!("{}", e),
+                }
+            }
+            if !output.is_empty() {
+                println!("Insert [{}] successfully.", output.join(", "));
+                return Some(format!("insert {}", output.join(" ")));
+            }
+        }
+        2 => {
+            if args.is_empty() {
+                println!("Current tree contains {}", tree.in_order());
+            }
+            let question = format!("Please input what kind of value you want to delete. Separate by one whitespace.\n\
+            e.g.{}", key_type.example());
+            let mut deleted = Vec::new();
+            for word in values_or_ask(args, &question) {
+                match tree.delete(&word) {
+                    Ok(true) => deleted.push(word),
+                    Ok(false) => {}
+                    Err(e) => println!("{}", e),
+                }
+            }
+            if !deleted.is_empty() {
+                return Some(format!("delete {}", deleted.join(" ")));
+            }
+        }
+        3 => println!("Number of leaves: {}", tree.leaves()),
+        4 => println!("Height of tree: {}", tree.height()),
+        5 => println!("In Order Traverse: {}", tree.in_order()),
+        6 => println!("Pre Order Traverse: {}", tree.pre_order()),
+        7 => println!("Post Order Traverse: {}", tree.post_order()),
+        8 => {
+            if tree.is_empty() { println!("Tree is Empty") } else { println!("Tree is not empty!") }
+        }
+        9 => tree.print(),
+        10 => {
+            let input = values_or_ask(args, "Please input the node you want to update. Separate by one whitespace\n\
+            e.g.A B(replace A with B)");
+            if input.len() != 2 {
+                eprintln!("Wrong number of input. Try again...")
+            } else {
+                match tree.update(&input[0], &input[1]) {
+                    Ok(true) => return Some(format!("update {} {}", input[0], input[1])),
+                    Ok(false) => {}
+                    Err(e) => println!("{}", e),
+                }
+            }
+        }
+        11 => {
+            let input = values_or_ask(args, "Please input the node/nodes you want to check. Separate by one whitespace.");
+            if input.is_empty() {
+                println!("Numbers of node can not be zero!")
+            } else {
+                for word in input {
+                    match tree.contains(&word) {
+                        Ok(exists) => println!("Does {} exist? {}", word, exists),
+                        Err(e) => println!("{}", e),
+                    }
+                }
+            }
+        }
+        12 => println!("Valid {}? {}", kind.title, tree.validate()),
+        13 => println!("This {} has a total of {} elements.", kind.title, tree.count()),
+        21 | 22 => {
+            let (name, usage) = if code == 21 { ("gen", GEN_USAGE) } else { ("churn", CHURN_USAGE) };
+            let question = format!("Please input the options, or press enter for the defaults.\n\
+            Options: {}", usage.trim_start_matches(name).trim());
+            let options = values_or_ask(args, &question).join(" ");
+            return run_workload(tree, &format!("{} {}", name, options));
+        }
+        _ => unreachable!(),
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::registry::find_tree_kind;
+
+    fn session(command: &str) -> Session {
+        let kind = find_tree_kind(command).unwrap();
+        let tree = (kind.create)(kind.key_types[0]);
+        let base = tree.snapshot();
+        Session { kind, key_type: kind.key_types[0], tree, base, base_file: None, history: Vec::new(), undone: Vec::new() }
+    }
+
+    fn run(session: &mut Session, line: &str) {
+        let words: Vec<String> = line.split_whitespace().map(String::from).collect();
+        session.run(find_command(&words[0]).unwrap().code, words[1..].to_vec());
+    }
+
+    fn commands(steps: &[Step]) -> Vec<&str> {
+        steps.iter().map(|step| step.command.as_str()).collect()
+    }
+
+    #[test]
+    fn only_changes_are_recorded() {
+        let mut session = session("avl");
+        for line in ["insert 5 3 8", "inorder", "height", "contains 3", "validate", "count", "print", "delete 7", "update 8 9"] {
+            run(&mut session, line);
+        }
+        assert_eq!(commands(&session.history), vec!["insert 5 3 8", "update 8 9"]);
+    }
+
+    #[test]
+    fn undo_and_redo_keep_one_tree_per_step() {
+        for tree in ["avl", "rb"] {
+            let mut session = session(tree);
+            run(&mut session, "insert 5 3 8");
+            run(&mut session, "delete 3");
+            run(&mut session, "gen --count 20 --seed 1");
+            let generated = session.tree.in_order();
+            session.undo();
+            session.undo();
+            assert_eq!(session.tree.in_order(), "[3, 5, 8]");
+            assert_eq!(commands(&session.undone), vec!["gen --count 20 --dist uniform --seed 1", "delete 3"]);
+            session.redo();
+            assert_eq!(session.tree.in_order(), "[5, 8]");
+            session.redo();
+            assert_eq!(session.tree.in_order(), generated);
+            assert!(session.undone.is_empty());
+            // back to the start and forward again, every step still restores the right tree
+            for _ in 0..3 {
+                session.undo();
+            }
+            assert!(session.tree.is_empty());
+            for _ in 0..3 {
+                session.redo();
+            }
+            assert_eq!(session.tree.in_order(), generated);
+        }
+    }
+
+    #[test]
+    fn a_new_change_drops_the_undone_steps() {
+        let mut session = session("rb");
+        run(&mut session, "insert 1 2");
+        session.undo();
+        run(&mut session, "insert 4");
+        assert!(session.undone.is_empty());
+        session.redo();
+        assert_eq!(session.tree.in_order(), "[4]");
+        session.undo();
+        session.undo();
+        assert_eq!(session.tree.in_order(), "[]");
+    }
+
+    #[test]
+    fn only_the_last_changes_keep_a_tree() {
+        let mut session = session("avl");
+        for key in 0..MAX_UNDO + 5 {
+            run(&mut session, &format!("insert {}", key));
+        }
+        let kept = session.history.iter().filter(|step| step.other.is_some()).count();
+        assert_eq!(kept, MAX_UNDO);
+        for _ in 0..MAX_UNDO + 1 {
+            session.undo();
+        }
+        // the last undo failed, the tree is the one after the first 5 inserts
+        assert_eq!(session.tree.in_order(), "[0, 1, 2, 3, 4]");
+        assert_eq!(session.history.len(), 5);
+        // the commands of the old steps are all still there
+        session.redo();
+        session.replay();
+        assert_eq!(session.tree.count(), 6);
+    }
+
+    #[test]
+    fn a_workload_with_wrong_options_is_not_recorded() {
+        let mut session = session("rb");
+        run(&mut session, "gen --count x");
+        run(&mut session, "churn --dist nowhere");
+        assert!(session.history.is_empty());
+        run(&mut session, "gen --count 10 --seed 3");
+        assert_eq!(commands(&session.history), vec!["gen --count 10 --dist uniform --seed 3"]);
+    }
+}
@@ -0,0 +1,20 @@
+// Command line front-end, everything here is only used by the `ECE522_project` binary
+pub mod batch;
+pub mod compare;
+pub mod explain;
+pub mod fuzz;
+pub mod key;
+pub mod line_editor;
+pub mod menu;
+pub mod quiz;
+pub mod registry;
+pub mod script;
+pub mod tree;
+pub mod workload;
+
+// Exit codes of the command line app
+pub const EXIT_OK: i32 = 0;
+// a command failed, e.g. deleting a value that does not exist or a failed validation
+pub const EXIT_FAILED: i32 = 1;
+// the command line itself is wrong
+pub const EXIT_USAGE: i32 = 2;
@@ -0,0 +1,441 @@
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ECE522_project::trace::{self, TraceEvent};
+use ECE522_project::workload::Rng;
+use ECE522_project::AVL::{AvlTree, AvlTreeNode, TracedAvlTree};
+use ECE522_project::RBTree::{NodeColor, RBTree, DELETE_CASES, INSERT_CASES};
+
+use crate::cli::{EXIT_OK, EXIT_USAGE};
+
+// Quiz mode: a random tree, one insert or delete, and two questions about it
+//     cargo run quiz [avl|rb] [--rounds N] [--seed S]
+//
+// 1. Which fix-up cases happen, in order? (LL / LR / RL / RR for AVL, the numbered cases of
+//    `insert_rebalance` and `adjust_double_black` for red-black trees)
+// 2. What does the tree look like afterwards? Answered with its pre-order traversal, red-black
+//    keys carry their color (e.g. 8B 3R 10R).
+//
+// Both answers are graded against what the tree really did, which is recorded with a tracer.
+
+const DEFAULT_ROUNDS: usize = 5;
+const MAX_KEY: u64 = 99;
+// how many random operations are tried to find one that needs a fix-up
+const ATTEMPTS: usize = 50;
+
+fn quiz_usage() {
+    eprintln!("Usage: cargo run quiz [avl|rb] [--rounds N] [--seed S]");
+}
+
+// `cargo run quiz ...`, `args` starts after `quiz`
+pub fn run_quiz_command(args: &[String]) -> i32 {
+    let mut tree = None;
+    let mut rounds = DEFAULT_ROUNDS;
+    let mut seed = None;
+    let mut i = 0;
+    while i < args.len() {
+        let value = args.get(i + 1);
+        match (args[i].as_str(), value) {
+            ("avl", _) | ("rb", _) if tree.is_none() => {
+                tree = Some(args[i].as_str());
+                i += 1;
+                continue;
+            }
+            ("--rounds", Some(value)) => match value.parse::<usize>() {
+                Ok(n) if n > 0 => rounds = n,
+                _ => {
+                    eprintln!("'{}' is not a valid number of rounds", value);
+                    return EXIT_USAGE;
+                }
+            },
+            ("--seed", Some(value)) => match value.parse::<u64>() {
+                Ok(n) => seed = Some(n),
+                Err(_) => {
+                    eprintln!("'{}' is not a valid seed", value);
+                    return EXIT_USAGE;
+                }
+            },
+            _ => {
+                quiz_usage();
+                return EXIT_USAGE;
+            }
+        }
+        i += 2;
+    }
+    let seed = seed.unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64);
+    run_quiz(tree, rounds, seed);
+    EXIT_OK
+}
+
+// One quiz question, everything needed to ask it and to grade the answers
+struct Question {
+    title: &'static str,
+    before: Vec<String>,
+    operation: String,
+    // the cases the user can pick from and how they are written
+    choices: Vec<String>,
+    cases: Vec<String>,
+    // the pre-order traversal afterwards, as the user should type it
+    result: Vec<String>,
+    example: &'static str,
+    // rotations, recolorings and cases, in the order they happened
+    steps: Vec<String>,
+    after: Vec<String>,
+}
+
+// The rotation cases of an AVL operation, a double rotation is followed by its two single rotations
+fn avl_cases(events: &[TraceEvent]) -> Vec<String> {
+    let mut cases = Vec::new();
+    let mut skip = 0;
+    for event in events {
+        let case = match event {
+            TraceEvent::RotateLR { .. } => "LR",
+            TraceEvent::RotateRL { .. } => "RL",
+            TraceEvent::RotateRight { .. } => "LL",
+            TraceEvent::RotateLeft { .. } => "RR",
+            _ => continue,
+        };
+        if skip > 0 {
+            skip -= 1;
+            continue;
+        }
+        if case == "LR" || case == "RL" {
+            skip = 2;
+        }
+        cases.push(case.to_string());
+    }
+    cases
+}
+
+fn rb_cases(events: &[TraceEvent]) -> Vec<String> {
+    events.iter().filter_map(|event| match event {
+        TraceEvent::Case { number, .. } => Some(number.to_string()),
+        _ => None,
+    }).collect()
+}
+
+// The steps worth showing, comparisons and height updates are left out
+fn steps(events: &[TraceEvent]) -> Vec<String> {
+    events.iter().filter(|event| !matches!(event, TraceEvent::Compare { .. } | TraceEvent::HeightUpdate { .. }))
+        .map(|event| event.to_string()).collect()
+}
+
+fn random_keys(rng: &mut Rng) -> Vec<i32> {
+    let size = 5 + rng.below(5) as usize;
+    let mut keys = Vec::new();
+    while keys.len() < size {
+        let key = 1 + rng.below(MAX_KEY) as i32;
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    keys
+}
+
+// Pick an insert of a new key or a delete of an existing one, trying a few until one needs a fix-up.
+// `apply` runs the operation on a copy of the tree and returns the cases it went through.
+fn pick_operation(rng: &mut Rng, keys: &[i32], mut apply: impl FnMut(bool, i32) -> Vec<String>) -> (bool, i32) {
+    let mut fallback = None;
+    for _ in 0..ATTEMPTS {
+        let insert = rng.below(2) == 0;
+        let key = if insert { 1 + rng.below(MAX_KEY) as i32 } else { keys[rng.below(keys.len() as u64) as usize] };
+        if insert && keys.contains(&key) {
+            continue;
+        }
+        if !apply(insert, key).is_empty() {
+            return (insert, key);
+        }
+        fallback.get_or_insert((insert, key));
+    }
+    fallback.unwrap_or((false, keys[0]))
+}
+
+fn describe(insert: bool, key: i32) -> String {
+    if insert { format!("Insert {}", key) } else { format!("Delete {}", key) }
+}
+
+fn avl_tree(keys: &[i32]) -> AvlTreeNode<i32> {
+    let mut tree: AvlTreeNode<i32> = AvlTree::generate_empty_tree();
+    for &key in keys {
+        tree.insert_node(key);
+    }
+    tree
+}
+
+fn run_avl(tree: &mut AvlTreeNode<i32>, insert: bool, key: i32) -> Vec<TraceEvent> {
+    trace::record(|| if insert { tree.insert_node_traced(key) } else { tree.remove_node_traced(&key); }).1
+}
+
+fn avl_question(rng: &mut Rng) -> Question {
+    let keys = random_keys(rng);
+    let tree = avl_tree(&keys);
+    let (insert, key) = pick_operation(rng, &keys, |insert, key| avl_cases(&run_avl(&mut tree.clone(), insert, key)));
+    avl_question_on(tree, insert, key)
+}
+
+// The question about one insert or delete on this tree
+fn avl_question_on(mut tree: AvlTreeNode<i32>, insert: bool, key: i32) -> Question {
+    let before = tree.tree_diagram();
+    let events = run_avl(&mut tree, insert, key);
+    Question {
+        title: "AVL tree",
+        before,
+        operation: describe(insert, key),
+        choices: vec![
+            "LL: right rotation".to_string(),
+            "LR: left-right double rotation".to_string(),
+            "RL: right-left double rotation".to_string(),
+            "RR: left rotation".to_string(),
+        ],
+        cases: avl_cases(&events),
+        result: tree.pre_order_traverse().iter().map(|key| key.to_string()).collect(),
+        example: "8 3 10",
+        steps: steps(&events),
+        after: tree.tree_diagram(),
+    }
+}
+
+fn rb_tree(keys: &[i32]) -> RBTree<i32> {
+    let mut tree: RBTree<i32> = RBTree::new();
+    for &key in keys {
+        tree.insert_node(key);
+    }
+    tree
+}
+
+fn run_rb(tree: &mut RBTree<i32>, insert: bool, key: i32) -> Vec<TraceEvent> {
+    trace::record(|| if insert { tree.insert_node(key); } else { let _ = tree.delete(key); }).1
+}
+
+fn rb_question(rng: &mut Rng) -> Question {
+    let keys = random_keys(rng);
+    let tree = rb_tree(&keys);
+    let copy = |tree: &RBTree<i32>| RBTree::from_pre_order(&tree.print_pre_order_traversal(), &tree.pre_order_colors()).unwrap();
+    let (insert, key) = pick_operation(rng, &keys, |insert, key| rb_cases(&run_rb(&mut copy(&tree), insert, key)));
+    rb_question_on(tree, insert, key)
+}
+
+// The question about one insert or delete on this tree
+fn rb_question_on(mut tree: RBTree<i32>, insert: bool, key: i32) -> Question {
+    let before = tree.tree_diagram();
+    let events = run_rb(&mut tree, insert, key);
+    let cases = if insert { INSERT_CASES.iter() } else { DELETE_CASES.iter() };
+    let colors = tree.pre_order_colors();
+    Question {
+        title: "Red-Black tree",
+        before,
+        operation: describe(insert, key),
+        choices: cases.enumerate().map(|(i, case)| format!("{}: {}", i + 1, case)).collect(),
+        cases: rb_cases(&events),
+        result: tree.print_pre_order_traversal().iter().zip(colors.iter())
+            .map(|(key, color)| format!("{}{}", key, if *color == NodeColor::Black { "B" } else { "R" })).collect(),
+        example: "8B 3R 10R",
+        steps: steps(&events),
+        after: tree.tree_diagram(),
+    }
+}
+
+// Print the question and read the answer, None at the end of the input
+fn ask(question: &str) -> Option<String> {
+    println!("{}", question);
+    print!("> ");
+    io::stdout().flush().unwrap();
+    let mut input = String::new();
+    match io::stdin().read_line(&mut input) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(input),
+    }
+}
+
+// How the two answers to a question were graded
+#[derive(Debug, PartialEq)]
+struct Grade {
+    cases: bool,
+    tree: bool,
+}
+
+impl Grade {
+    fn points(&self) -> u32 {
+        self.cases as u32 + self.tree as u32
+    }
+}
+
+// The answers as typed, words are separated by any whitespace and upper and lower case do not matter
+fn words(answer: &str) -> Vec<String> {
+    answer.split_whitespace().map(|word| word.to_uppercase()).collect()
+}
+
+// The cases as they have to be answered, `NONE` when there was nothing to fix
+fn expected_cases(question: &Question) -> Vec<String> {
+    if question.cases.is_empty() { vec!["NONE".to_string()] } else { question.cases.clone() }
+}
+
+fn grade(question: &Question, cases: &str, tree: &str) -> Grade {
+    Grade {
+        cases: words(cases) == expected_cases(question),
+        tree: words(tree) == words(&question.result.join(" ")),
+    }
+}
+
+fn show(lines: &[String]) {
+    for line in lines {
+        println!("    {}", line);
+    }
+}
+
+// Ask one question, returns the points scored (0 - 2) or None if the input ended
+fn run_question(number: usize, rounds: usize, question: &Question) -> Option<u32> {
+    println!("\n=========== QUESTION {} of {} ({}) ===========", number, rounds, question.title);
+    show(&question.before);
+    println!("{} in this {}.", question.operation, question.title);
+    println!("The fix-up cases are:");
+    for choice in &question.choices {
+        println!("    {}", choice);
+    }
+    let cases = ask("Which cases happen? Give them in order separated by spaces, or `none`:")?;
+    let tree = ask(&format!("What is the pre-order traversal of the tree afterwards? e.g. {}", question.example))?;
+
+    let grade = grade(question, &cases, &tree);
+    if grade.cases {
+        println!("Cases: correct!");
+    } else {
+        println!("Cases: wrong, it was {}", expected_cases(question).join(" "));
+    }
+    if grade.tree {
+        println!("Tree: correct!");
+    } else {
+        println!("Tree: wrong, it was {}", question.result.join(" "));
+    }
+    println!("----- what the tree did -----");
+    if question.steps.is_empty() {
+        println!("    nothing to fix");
+    }
+    for (i, step) in question.steps.iter().enumerate() {
+        println!("{:>4}. {}", i + 1, step);
+    }
+    show(&question.after);
+    Some(grade.points())
+}
+
+// `tree` is "avl", "rb" or None for a random one every round
+fn run_quiz(tree: Option<&str>, rounds: usize, seed: u64) {
+    let again = match tree {
+        Some(tree) => format!("cargo run quiz {} --rounds {} --seed {}", tree, rounds, seed),
+        None => format!("cargo run quiz --rounds {} --seed {}", rounds, seed),
+    };
+    println!("Quiz with seed {} (`{}` asks the same questions again)", seed, again);
+    let mut rng = Rng::new(seed);
+    let mut score = 0;
+    let mut asked = 0;
+    for number in 1..=rounds {
+        let avl = match tree {
+            Some(tree) => tree == "avl",
+            None => rng.below(2) == 0,
+        };
+        let question = if avl { avl_question(&mut rng) } else { rb_question(&mut rng) };
+        match run_question(number, rounds, &question) {
+            Some(points) => {
+                score += points;
+                asked += 1;
+            }
+            None => break,
+        }
+    }
+    println!("\n=========== QUIZ RESULT ===========");
+    println!("Score: {} / {} after {} questions", score, 2 * asked, asked);
+    println!("===================================");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rotation(case: &str, pivot: i32) -> TraceEvent {
+        let pivot = pivot.to_string();
+        match case {
+            "LR" => TraceEvent::RotateLR { pivot },
+            "RL" => TraceEvent::RotateRL { pivot },
+            "LL" => TraceEvent::RotateRight { pivot },
+            _ => TraceEvent::RotateLeft { pivot },
+        }
+    }
+
+    #[test]
+    fn avl_left_right_rotation() {
+        let question = avl_question_on(avl_tree(&[3, 1]), true, 2);
+        assert_eq!(question.cases, vec!["LR"]);
+        assert_eq!(question.result, vec!["2", "1", "3"]);
+        assert_eq!(grade(&question, "lr", "2 1 3"), Grade { cases: true, tree: true });
+        // the two single rotations are part of the double rotation, not cases of their own
+        assert_eq!(grade(&question, "LR RR LL", "2 3 1"), Grade { cases: false, tree: false });
+    }
+
+    #[test]
+    fn avl_right_left_rotation() {
+        let question = avl_question_on(avl_tree(&[1, 3]), true, 2);
+        assert_eq!(question.cases, vec!["RL"]);
+        assert_eq!(grade(&question, "RL", "2  1\t3\n").points(), 2);
+        assert_eq!(grade(&question, "LR", "2 1 3").points(), 1);
+    }
+
+    #[test]
+    fn avl_delete_and_nothing_to_fix() {
+        let question = avl_question_on(avl_tree(&[2, 1, 3, 4]), false, 1);
+        assert_eq!(question.cases, vec!["RR"]);
+        assert_eq!(grade(&question, "rr", "3 2 4").points(), 2);
+        let question = avl_question_on(avl_tree(&[2, 1, 3]), true, 4);
+        assert!(question.cases.is_empty());
+        assert_eq!(grade(&question, "none", "2 1 3 4").points(), 2);
+        assert_eq!(grade(&question, "", "2 1 3 4").points(), 1);
+    }
+
+    #[test]
+    fn double_rotations_skip_the_next_two_rotations_only() {
+        let events = vec![
+            rotation("LR", 8), rotation("RR", 3), rotation("LL", 8),
+            TraceEvent::HeightUpdate { node: "5".to_string(), old: 1, new: 2 },
+            rotation("RR", 20),
+            rotation("RL", 30), rotation("LL", 40), rotation("RR", 30),
+            rotation("LL", 50),
+        ];
+        assert_eq!(avl_cases(&events), vec!["LR", "RR", "RL", "LL"]);
+    }
+
+    #[test]
+    fn rb_insert_cases() {
+        let question = rb_question_on(rb_tree(&[1, 2]), true, 3);
+        assert_eq!(question.cases, vec!["3"]);
+        assert_eq!(question.result, vec!["2B", "1R", "3R"]);
+        assert_eq!(grade(&question, "3", "2b 1r 3r").points(), 2);
+        assert_eq!(grade(&question, "3", "2B 1B 3B").points(), 1);
+        assert_eq!(grade(&question, "3", "2 1 3").points(), 1);
+
+        let question = rb_question_on(rb_tree(&[8, 3, 10]), true, 1);
+        assert_eq!(question.cases, vec!["1"]);
+        assert_eq!(grade(&question, "1", "8B 3B 1R 10B").points(), 2);
+    }
+
+    #[test]
+    fn rb_results_are_keys_with_their_color() {
+        let question = rb_question_on(rb_tree(&[8, 3]), true, 10);
+        assert!(question.cases.is_empty());
+        assert_eq!(question.result.join(" "), question.example);
+        assert_eq!(grade(&question, "NONE", "8B 3R 10R"), Grade { cases: true, tree: true });
+        assert_eq!(grade(&question, "none", "8 B 3 R 10 R"), Grade { cases: true, tree: false });
+    }
+
+    #[test]
+    fn rb_delete_cases() {
+        let question = rb_question_on(rb_tree(&[8, 3, 10, 1]), false, 10);
+        assert_eq!(question.cases, vec!["4"]);
+        assert_eq!(grade(&question, "4", "3B 1B 8B").points(), 2);
+
+        let question = rb_question_on(rb_tree(&[8, 3, 10, 12, 14, 16]), false, 3);
+        assert_eq!(question.cases, vec!["2", "3"]);
+        assert_eq!(grade(&question, "2 3", "12B 8B 10R 14B 16R").points(), 2);
+        assert_eq!(grade(&question, "3 2", "12B 8B 10R 14B 16R").points(), 1);
+
+        let question = rb_question_on(rb_tree(&(1..=10).collect::<Vec<i32>>()), false, 1);
+        assert_eq!(question.cases, vec!["3", "7"]);
+    }
+}
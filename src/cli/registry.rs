@@ -0,0 +1,295 @@
+use std::fmt::Debug;
+use std::str::FromStr;
+
+use ECE522_project::codec::{BinaryKey, TreeCodec};
+use ECE522_project::total_float::TotalF64;
+use ECE522_project::AVL::{AvlTree, AvlTreeNode};
+use ECE522_project::RBTree::RBTree;
+
+use crate::cli::{batch, script};
+use crate::cli::key::KeyType;
+use crate::cli::tree::CliTree;
+use crate::cli::EXIT_FAILED;
+
+// A tree of any kind and any key type behind one interface, keys are passed as the text the user typed
+//
+// Every method taking keys returns Err with a message when the text is not a valid key.
+pub trait DynTree {
+    fn key_type(&self) -> KeyType;
+    fn insert(&mut self, key: &str) -> Result<bool, String>;
+    fn delete(&mut self, key: &str) -> Result<bool, String>;
+    fn update(&mut self, old: &str, new: &str) -> Result<bool, String>;
+    fn insert_quiet(&mut self, key: &str) -> Result<bool, String>;
+    fn delete_quiet(&mut self, key: &str) -> Result<bool, String>;
+    fn contains(&mut self, key: &str) -> Result<bool, String>;
+    fn leaves(&self) -> u32;
+    fn height(&self) -> u32;
+    fn in_order(&mut self) -> String;
+    fn pre_order(&mut self) -> String;
+    fn post_order(&mut self) -> String;
+    fn is_empty(&self) -> bool;
+    fn count(&mut self) -> usize;
+    fn validate(&self) -> bool;
+    fn print(&mut self);
+    fn diagram(&self) -> Vec<String>;
+    // the whole tree as bytes (see codec.rs), restore puts exactly this tree back
+    fn snapshot(&self) -> Vec<u8>;
+    fn restore(&mut self, snapshot: &[u8]) -> Result<(), String>;
+}
+
+// Any CliTree together with the key type it was created for
+pub struct Keyed<T> {
+    pub tree: T,
+    pub key_type: KeyType,
+}
+
+impl<T: CliTree> Keyed<T> {
+    fn parse(&self, key: &str) -> Result<T::Key, String> {
+        key.parse().map_err(|_| format!("Please replace '{}' with {}!", key, self.key_type.describe()))
+    }
+}
+
+impl<T: CliTree + TreeCodec> DynTree for Keyed<T> {
+    fn key_type(&self) -> KeyType {
+        self.key_type
+    }
+
+    fn insert(&mut self, key: &str) -> Result<bool, String> {
+        let key = self.parse(key)?;
+        Ok(self.tree.insert(key))
+    }
+
+    fn delete(&mut self, key: &str) -> Result<bool, String> {
+        let key = self.parse(key)?;
+        Ok(self.tree.delete(key))
+    }
+
+    fn update(&mut self, old: &str, new: &str) -> Result<bool, String> {
+        let old = self.parse(old)?;
+        let new = self.parse(new)?;
+        Ok(self.tree.update(old, new))
+    }
+
+    fn insert_quiet(&mut self, key: &str) -> Result<bool, String> {
+        let key = self.parse(key)?;
+        Ok(self.tree.insert_quiet(key))
+    }
+
+    fn delete_quiet(&mut self, key: &str) -> Result<bool, String> {
+        let key = self.parse(key)?;
+        Ok(self.tree.delete_quiet(key))
+    }
+
+    fn contains(&mut self, key: &str) -> Result<bool, String> {
+        let key = self.parse(key)?;
+        Ok(self.tree.contains(key))
+    }
+
+    fn leaves(&self) -> u32 {
+        self.tree.leaves()
+    }
+
+    fn height(&self) -> u32 {
+        self.tree.height()
+    }
+
+    fn in_order(&mut self) -> String {
+        format!("{:?}", self.tree.in_order())
+    }
+
+    fn pre_order(&mut self) -> String {
+        format!("{:?}", self.tree.pre_order())
+    }
+
+    fn post_order(&mut self) -> String {
+        format!("{:?}", self.tree.post_order())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    fn count(&mut self) -> usize {
+        self.tree.count()
+    }
+
+    fn validate(&self) -> bool {
+        self.tree.validate()
+    }
+
+    fn print(&mut self) {
+        self.tree.print()
+    }
+
+    fn diagram(&self) -> Vec<String> {
+        self.tree.diagram()
+    }
+
+    fn snapshot(&self) -> Vec<u8> {
+        self.tree.to_bytes()
+    }
+
+    fn restore(&mut self, snapshot: &[u8]) -> Result<(), String> {
+        self.tree = T::from_bytes(snapshot).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+fn keyed<T: CliTree + TreeCodec + 'static>(tree: T, key_type: KeyType) -> Box<dyn DynTree> {
+    Box::new(Keyed { tree, key_type })
+}
+
+// A tree type the interactive menu can work with
+//
+// To add a new tree type, implement CliTree for it and add an entry to TREE_KINDS.
+pub struct TreeKind {
+    // command line keyword, e.g. `cargo run avl`
+    pub command: &'static str,
+    pub title: &'static str,
+    // what `validate` checks
+    pub validation: &'static str,
+    // the key types the tree supports, the first one is the default
+    pub key_types: &'static [KeyType],
+    pub create: fn(KeyType) -> Box<dyn DynTree>,
+    // run a batch file (see batch.rs) on a new tree or on one loaded from a file, returns the exit code
+    pub run_batch: fn(KeyType, &str, Option<&str>) -> i32,
+    // run the commands given on the command line (see script.rs) on a new tree, returns the exit code
+    pub run_script: fn(KeyType, &[String]) -> i32,
+}
+
+pub const TREE_KINDS: [TreeKind; 2] = [
+    TreeKind {
+        command: "avl",
+        title: "AVL tree",
+        validation: "Check whether it is a balanced tree",
+        key_types: &[KeyType::Int, KeyType::Unsigned, KeyType::Float, KeyType::Char, KeyType::Str],
+        create: new_avl_tree,
+        run_batch: run_avl_batch,
+        run_script: run_avl_script,
+    },
+    TreeKind {
+        command: "rb",
+        title: "Red-Black tree",
+        validation: "Check whether it is a valid red-black tree",
+        key_types: &[KeyType::Unsigned, KeyType::Int, KeyType::Float, KeyType::Char, KeyType::Str],
+        create: new_rb_tree,
+        run_batch: run_rb_batch,
+        run_script: run_rb_script,
+    },
+];
+
+// Key types the AVL tree can be used with
+trait AvlKey: FromStr + Clone + Ord + Debug + BinaryKey + 'static {}
+impl<K: FromStr + Clone + Ord + Debug + BinaryKey + 'static> AvlKey for K {}
+
+pub fn find_tree_kind(command: &str) -> Option<&'static TreeKind> {
+    TREE_KINDS.iter().find(|kind| kind.command == command)
+}
+
+// The key type given with `--key`, the default one of the tree when there is none
+pub fn key_type_for(kind: &TreeKind, flag: Option<&str>) -> Result<KeyType, String> {
+    match flag {
+        None => Ok(kind.key_types[0]),
+        Some(flag) => match KeyType::from_flag(flag) {
+            Some(key_type) if kind.key_types.contains(&key_type) => Ok(key_type),
+            _ => Err(format!("The {} does not support '{}' keys", kind.title, flag)),
+        },
+    }
+}
+
+fn empty_avl_tree<K: AvlKey>(key_type: KeyType) -> Box<dyn DynTree> {
+    let tree: AvlTreeNode<K> = AvlTree::generate_empty_tree();
+    keyed(tree, key_type)
+}
+
+// The tree saved in `load`, or `empty` if there is nothing to load
+fn start_tree<T: TreeCodec>(empty: T, load: Option<&str>) -> Result<T, String> {
+    match load {
+        None => Ok(empty),
+        Some(file) => T::load_from_file(file).map_err(|e| format!("Cannot load {}: {}", file, e)),
+    }
+}
+
+fn batch_on<T: CliTree + TreeCodec>(empty: T, key_type: KeyType, path: &str, load: Option<&str>) -> i32 {
+    match start_tree(empty, load) {
+        Ok(tree) => batch::run_batch(&mut Keyed { tree, key_type }, path),
+        Err(e) => {
+            eprintln!("{}", e);
+            EXIT_FAILED
+        }
+    }
+}
+
+fn avl_batch<K: AvlKey>(key_type: KeyType, path: &str, load: Option<&str>) -> i32 {
+    let tree: AvlTreeNode<K> = AvlTree::generate_empty_tree();
+    batch_on(tree, key_type, path, load)
+}
+
+fn run_avl_batch(key_type: KeyType, path: &str, load: Option<&str>) -> i32 {
+    match key_type {
+        KeyType::Int => avl_batch::<i32>(key_type, path, load),
+        KeyType::Unsigned => avl_batch::<u32>(key_type, path, load),
+        KeyType::Float => avl_batch::<TotalF64>(key_type, path, load),
+        KeyType::Char => avl_batch::<char>(key_type, path, load),
+        KeyType::Str => avl_batch::<String>(key_type, path, load),
+    }
+}
+
+fn avl_script<K: AvlKey>(args: &[String]) -> i32 {
+    let mut tree: AvlTreeNode<K> = AvlTree::generate_empty_tree();
+    script::run_script("avl", &mut tree, args)
+}
+
+fn run_avl_script(key_type: KeyType, args: &[String]) -> i32 {
+    match key_type {
+        KeyType::Int => avl_script::<i32>(args),
+        KeyType::Unsigned => avl_script::<u32>(args),
+        KeyType::Float => avl_script::<TotalF64>(args),
+        KeyType::Char => avl_script::<char>(args),
+        KeyType::Str => avl_script::<String>(args),
+    }
+}
+
+fn new_avl_tree(key_type: KeyType) -> Box<dyn DynTree> {
+    match key_type {
+        KeyType::Int => empty_avl_tree::<i32>(key_type),
+        KeyType::Unsigned => empty_avl_tree::<u32>(key_type),
+        KeyType::Float => empty_avl_tree::<TotalF64>(key_type),
+        KeyType::Char => empty_avl_tree::<char>(key_type),
+        KeyType::Str => empty_avl_tree::<String>(key_type),
+    }
+}
+
+fn rb_batch<K: FromStr + Ord + Clone + Debug + BinaryKey>(key_type: KeyType, path: &str, load: Option<&str>) -> i32 {
+    batch_on(RBTree::<K>::new(), key_type, path, load)
+}
+
+fn run_rb_batch(key_type: KeyType, path: &str, load: Option<&str>) -> i32 {
+    match key_type {
+        KeyType::Int => rb_batch::<i32>(key_type, path, load),
+        KeyType::Unsigned => rb_batch::<u32>(key_type, path, load),
+        KeyType::Float => rb_batch::<TotalF64>(key_type, path, load),
+        KeyType::Char => rb_batch::<char>(key_type, path, load),
+        KeyType::Str => rb_batch::<String>(key_type, path, load),
+    }
+}
+
+fn run_rb_script(key_type: KeyType, args: &[String]) -> i32 {
+    match key_type {
+        KeyType::Int => script::run_script("rb", &mut RBTree::<i32>::new(), args),
+        KeyType::Unsigned => script::run_script("rb", &mut RBTree::<u32>::new(), args),
+        KeyType::Float => script::run_script("rb", &mut RBTree::<TotalF64>::new(), args),
+        KeyType::Char => script::run_script("rb", &mut RBTree::<char>::new(), args),
+        KeyType::Str => script::run_script("rb", &mut RBTree::<String>::new(), args),
+    }
+}
+
+fn new_rb_tree(key_type: KeyType) -> Box<dyn DynTree> {
+    match key_type {
+        KeyType::Int => keyed(RBTree::<i32>::new(), key_type),
+        KeyType::Unsigned => keyed(RBTree::<u32>::new(), key_type),
+        KeyType::Float => keyed(RBTree::<TotalF64>::new(), key_type),
+        KeyType::Char => keyed(RBTree::<char>::new(), key_type),
+        KeyType::Str => keyed(RBTree::<String>::new(), key_type),
+    }
+}
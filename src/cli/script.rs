@@ -0,0 +1,264 @@
+use crate::cli::registry::{key_type_for, TreeKind};
+use crate::cli::tree::CliTree;
+use crate::cli::{EXIT_FAILED, EXIT_OK, EXIT_USAGE};
+
+// Non-interactive usage, e.g.
+//     cargo run avl insert 5 3 8 --print --validate
+//     cargo run rb insert 1 12 15 30 range 10 20
+//     cargo run avl --key string insert pear apple --print
+// Commands run in the given order on one tree, values belong to the command before them.
+
+const COMMANDS: [&str; 14] = [
+    "insert", "delete", "update", "contains", "range", "leaves", "height",
+    "inorder", "preorder", "postorder", "empty", "count", "validate", "print",
+];
+
+pub fn script_usage(tree_type: &str) {
+    eprintln!("Usage: cargo run {} <command> [values] [<command> [values]...] [--key <type>] [--print] [--validate]\n\
+               Commands: insert <values>, delete <values>, update <old> <new>, contains <values>,\n\
+               \x20         range <low> <high>, leaves, height, inorder, preorder, postorder,\n\
+               \x20         empty, count, validate, print\n\
+               --key: int, unsigned, float, char or string, the tree's default key type when left out\n\
+               --print: print the tree at the end\n\
+               --validate: check the tree at the end, fail if it is broken", tree_type);
+}
+
+// `cargo run <tree> <command> [values]...`, `args` starts at the first command
+pub fn run_script_command(kind: &TreeKind, args: &[String]) -> i32 {
+    let mut key = None;
+    let mut rest = Vec::new();
+    let mut words = args.iter();
+    while let Some(word) = words.next() {
+        if word != "--key" {
+            rest.push(word.clone());
+            continue;
+        }
+        match words.next() {
+            Some(value) if key.is_none() => key = Some(value.as_str()),
+            _ => {
+                script_usage(kind.command);
+                return EXIT_USAGE;
+            }
+        }
+    }
+    match key_type_for(kind, key) {
+        Ok(key_type) => (kind.run_script)(key_type, &rest),
+        Err(e) => {
+            eprintln!("{}", e);
+            EXIT_USAGE
+        }
+    }
+}
+
+// Run the commands given on the command line, returns the exit code
+pub fn run_script<T: CliTree>(tree_type: &str, tree: &mut T, args: &[String]) -> i32 {
+    let mut commands: Vec<(&str, Vec<T::Key>)> = Vec::new();
+    let mut print = false;
+    let mut validate = false;
+    for arg in args {
+        match arg.as_str() {
+            "--print" => print = true,
+            "--validate" => validate = true,
+            flag if flag.starts_with("--") => {
+                eprintln!("Unknown option '{}'", flag);
+                script_usage(tree_type);
+                return EXIT_USAGE;
+            }
+            word if is_command(word) => commands.push((word, Vec::new())),
+            value => match commands.last_mut() {
+                None => {
+                    eprintln!("Unknown command '{}'", value);
+                    script_usage(tree_type);
+                    return EXIT_USAGE;
+                }
+                Some((_, values)) => match value.parse() {
+                    Ok(v) => values.push(v),
+                    Err(_) => {
+                        eprintln!("'{}' is not a valid value for a {} tree", value, tree_type);
+                        return EXIT_USAGE;
+                    }
+                },
+            },
+        }
+    }
+    // Check every command before running any of them
+    for (command, values) in &commands {
+        if !arity_ok(command, values.len()) {
+            eprintln!("Wrong number of values for '{}'", command);
+            script_usage(tree_type);
+            return EXIT_USAGE;
+        }
+    }
+
+    let mut code = EXIT_OK;
+    for (command, values) in commands {
+        if !run_command(tree, command, &values) {
+            code = EXIT_FAILED;
+        }
+    }
+    if print {
+        tree.print();
+    }
+    if validate {
+        let valid = tree.validate();
+        println!("Valid tree? {}", valid);
+        if !valid {
+            code = EXIT_FAILED;
+        }
+    }
+    code
+}
+
+pub fn is_command(word: &str) -> bool {
+    COMMANDS.contains(&word)
+}
+
+// Does the command take this many values?
+pub fn arity_ok(command: &str, count: usize) -> bool {
+    match command {
+        "insert" | "delete" | "contains" => count > 0,
+        "update" | "range" => count == 2,
+        _ => count == 0,
+    }
+}
+
+// Run one command, false if it failed
+pub fn run_command<T: CliTree>(tree: &mut T, command: &str, values: &[T::Key]) -> bool {
+    match command {
+        "insert" => {
+            let mut ok = true;
+            for i in values {
+                ok &= tree.insert(i.clone());
+            }
+            ok
+        }
+        "delete" => {
+            let mut ok = true;
+            for i in values {
+                ok &= tree.delete(i.clone());
+            }
+            ok
+        }
+        "update" => tree.update(values[0].clone(), values[1].clone()),
+        "contains" => {
+            let mut ok = true;
+            for i in values {
+                let exists = tree.contains(i.clone());
+                println!("Does {:?} exist? {}", i, exists);
+                ok &= exists;
+            }
+            ok
+        }
+        "range" => {
+            println!("Values in [{:?}, {:?}]: {:?}", values[0], values[1], tree.range(values[0].clone(), values[1].clone()));
+            true
+        }
+        "leaves" => {
+            println!("Number of leaves: {}", tree.leaves());
+            true
+        }
+        "height" => {
+            println!("Height of tree: {}", tree.height());
+            true
+        }
+        "inorder" => {
+            println!("In Order Traverse: {:?}", tree.in_order());
+            true
+        }
+        "preorder" => {
+            println!("Pre Order Traverse: {:?}", tree.pre_order());
+            true
+        }
+        "postorder" => {
+            println!("Post Order Traverse: {:?}", tree.post_order());
+            true
+        }
+        "empty" => {
+            if tree.is_empty() { println!("Tree is Empty") } else { println!("Tree is not empty!") }
+            true
+        }
+        "count" => {
+            println!("This tree has a total of {} elements.", tree.count());
+            true
+        }
+        "validate" => {
+            let valid = tree.validate();
+            println!("Valid tree? {}", valid);
+            valid
+        }
+        "print" => {
+            tree.print();
+            true
+        }
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::registry::find_tree_kind;
+    use ECE522_project::AVL::{AvlTree, AvlTreeNode};
+    use ECE522_project::RBTree::RBTree;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    fn avl() -> AvlTreeNode<i32> {
+        AvlTree::generate_empty_tree()
+    }
+
+    #[test]
+    fn commands_run_in_order_on_one_tree() {
+        let mut tree = avl();
+        assert_eq!(run_script("avl", &mut tree, &args("insert 5 3 8 delete 3 update 8 9 contains 5 --validate")), EXIT_OK);
+        assert_eq!(tree.in_order(), vec![5, 9]);
+        let mut tree = RBTree::<u32>::new();
+        assert_eq!(run_script("rb", &mut tree, &args("insert 1 12 15 30 range 10 20 count height --print")), EXIT_OK);
+        assert_eq!(tree.in_order(), vec![1, 12, 15, 30]);
+    }
+
+    #[test]
+    fn a_failed_command_fails_the_run_but_the_others_still_run() {
+        let mut tree = avl();
+        assert_eq!(run_script("avl", &mut tree, &args("insert 1 delete 7 insert 2")), EXIT_FAILED);
+        assert_eq!(tree.in_order(), vec![1, 2]);
+        for line in ["contains 3", "insert 1", "update 1 2", "update 5 6"] {
+            assert_eq!(run_script("avl", &mut tree, &args(line)), EXIT_FAILED, "{}", line);
+        }
+        assert_eq!(tree.in_order(), vec![1, 2]);
+    }
+
+    #[test]
+    fn a_wrong_command_line_runs_nothing() {
+        for line in ["insert 1 --verbose", "5 insert 1", "insert 1 x", "insert 1 range 1", "insert 1 update 2", "insert 1 height 2", "insert"] {
+            let mut tree = avl();
+            assert_eq!(run_script("avl", &mut tree, &args(line)), EXIT_USAGE, "{}", line);
+            assert!(tree.is_empty(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn commands_take_the_right_number_of_values() {
+        assert!(arity_ok("insert", 3) && !arity_ok("insert", 0));
+        assert!(arity_ok("update", 2) && !arity_ok("update", 1) && !arity_ok("range", 3));
+        assert!(arity_ok("height", 0) && !arity_ok("print", 1));
+        assert!(is_command("postorder") && !is_command("assert-valid"));
+    }
+
+    #[test]
+    fn the_key_type_comes_from_the_key_flag() {
+        let avl = find_tree_kind("avl").unwrap();
+        let rb = find_tree_kind("rb").unwrap();
+        assert_eq!(run_script_command(avl, &args("--key string insert pear apple contains pear")), EXIT_OK);
+        assert_eq!(run_script_command(rb, &args("insert a --key char contains a")), EXIT_OK);
+        assert_eq!(run_script_command(avl, &args("insert -1 contains -1")), EXIT_OK);
+        // the red-black tree has no-sign keys by default
+        assert_eq!(run_script_command(rb, &args("insert -1")), EXIT_USAGE);
+        assert_eq!(run_script_command(avl, &args("--key char insert ab")), EXIT_USAGE);
+        for line in ["--key", "insert 1 --key", "--key bogus insert 1", "--key int --key char insert 1"] {
+            assert_eq!(run_script_command(avl, &args(line)), EXIT_USAGE, "{}", line);
+        }
+    }
+}
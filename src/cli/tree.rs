@@ -0,0 +1,202 @@
+use std::fmt::Debug;
+use std::str::FromStr;
+
+use ECE522_project::AVL::{AvlTree, AvlTreeNode, TracedAvlTree};
+use ECE522_project::RBTree::RBTree;
+
+// What the command line needs from a tree, so the commands can be written once for every tree type
+pub trait CliTree {
+    type Key: FromStr + Clone + PartialOrd + Debug;
+
+    fn insert(&mut self, key: Self::Key) -> bool;
+    // insert a value, false if it already exists
+    fn delete(&mut self, key: Self::Key) -> bool;
+    // delete a value, false if it does not exist
+    fn update(&mut self, old: Self::Key, new: Self::Key) -> bool;
+    // replace old with new, false if old does not exist or new already exists
+    fn insert_quiet(&mut self, key: Self::Key) -> bool;
+    fn delete_quiet(&mut self, key: Self::Key) -> bool;
+    // like insert / delete but without printing anything, for generated workloads
+    fn contains(&mut self, key: Self::Key) -> bool;
+    fn range(&self, low: Self::Key, high: Self::Key) -> Vec<Self::Key>;
+    fn leaves(&self) -> u32;
+    fn height(&self) -> u32;
+    fn in_order(&mut self) -> Vec<Self::Key>;
+    fn pre_order(&mut self) -> Vec<Self::Key>;
+    fn post_order(&mut self) -> Vec<Self::Key>;
+    fn is_empty(&self) -> bool;
+    fn count(&mut self) -> usize;
+    fn validate(&self) -> bool;
+    fn print(&mut self);
+    // the lines print shows, to put trees next to each other
+    fn diagram(&self) -> Vec<String>;
+}
+
+impl<K: FromStr + Clone + Ord + Debug> CliTree for AvlTreeNode<K> {
+    type Key = K;
+
+    fn insert(&mut self, key: K) -> bool {
+        if !self.insert_quiet(key.clone()) {
+            println!("INSERT FAILED: Node({:?}) already exists!", key);
+            return false;
+        }
+        true
+    }
+
+    fn delete(&mut self, key: K) -> bool {
+        // delete_node reports success and failure itself
+        self.delete_node(&key).is_some()
+    }
+
+    fn update(&mut self, old: K, new: K) -> bool {
+        let possible = old != new && self.exist_or_not_traced(&old) && !self.exist_or_not_traced(&new);
+        self.update_node(&old, new);
+        possible
+    }
+
+    fn insert_quiet(&mut self, key: K) -> bool {
+        // insert_node leaves the tree alone for a key it already holds, so one search is enough
+        let before = self.total_number_elements();
+        self.insert_node_traced(key);
+        self.total_number_elements() != before
+    }
+
+    fn delete_quiet(&mut self, key: K) -> bool {
+        self.remove_node_traced(&key)
+    }
+
+    fn contains(&mut self, key: K) -> bool {
+        self.exist_or_not_traced(&key)
+    }
+
+    fn range(&self, low: K, high: K) -> Vec<K> {
+        AvlTree::range(self, &low, &high).into_iter().cloned().collect()
+    }
+
+    fn leaves(&self) -> u32 {
+        self.number_of_leaves() as u32
+    }
+
+    fn height(&self) -> u32 {
+        self.height_of_tree() as u32
+    }
+
+    fn in_order(&mut self) -> Vec<K> {
+        self.in_order_traverse().into_iter().cloned().collect()
+    }
+
+    fn pre_order(&mut self) -> Vec<K> {
+        self.pre_order_traverse().into_iter().cloned().collect()
+    }
+
+    fn post_order(&mut self) -> Vec<K> {
+        self.post_order_traverse().into_iter().cloned().collect()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_tree_empty()
+    }
+
+    fn count(&mut self) -> usize {
+        self.total_number_elements() as usize
+    }
+
+    fn validate(&self) -> bool {
+        self.validate_tree()
+    }
+
+    fn print(&mut self) {
+        self.print_tree_diagram()
+    }
+
+    fn diagram(&self) -> Vec<String> {
+        self.tree_diagram()
+    }
+}
+
+impl<K: FromStr + Ord + Clone + Debug> CliTree for RBTree<K> {
+    type Key = K;
+
+    fn insert(&mut self, key: K) -> bool {
+        if !self.insert_node(key.clone()) {
+            println!("INSERT FAILED: Node({:?}) already exists!", key);
+            return false;
+        }
+        true
+    }
+
+    fn delete(&mut self, key: K) -> bool {
+        match RBTree::delete(self, key.clone()) {
+            Ok(()) => {
+                println!("Node({:?}) delete successfully.", key);
+                true
+            }
+            Err(_) => {
+                println!("DELETE FAILED: No such node({:?}) to delete", key);
+                false
+            }
+        }
+    }
+
+    fn update(&mut self, old: K, new: K) -> bool {
+        let possible = old != new && self.exist_or_not(old.clone()) && !self.exist_or_not(new.clone());
+        self.update_node(old, new);
+        possible
+    }
+
+    fn insert_quiet(&mut self, key: K) -> bool {
+        self.insert_node(key)
+    }
+
+    fn delete_quiet(&mut self, key: K) -> bool {
+        RBTree::delete(self, key).is_ok()
+    }
+
+    fn contains(&mut self, key: K) -> bool {
+        self.exist_or_not(key)
+    }
+
+    fn range(&self, low: K, high: K) -> Vec<K> {
+        RBTree::range(self, low, high)
+    }
+
+    fn leaves(&self) -> u32 {
+        self.get_number_leaves()
+    }
+
+    fn height(&self) -> u32 {
+        self.get_height()
+    }
+
+    fn in_order(&mut self) -> Vec<K> {
+        self.print_in_order_traversal()
+    }
+
+    fn pre_order(&mut self) -> Vec<K> {
+        self.print_pre_order_traversal()
+    }
+
+    fn post_order(&mut self) -> Vec<K> {
+        self.print_post_order_traversal()
+    }
+
+    fn is_empty(&self) -> bool {
+        RBTree::is_empty(self)
+    }
+
+    fn count(&mut self) -> usize {
+        self.total_number_elements() as usize
+    }
+
+    fn validate(&self) -> bool {
+        self.validate_tree()
+    }
+
+    fn print(&mut self) {
+        self.print_tree()
+    }
+
+    fn diagram(&self) -> Vec<String> {
+        self.tree_diagram()
+    }
+}
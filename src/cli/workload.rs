@@ -0,0 +1,262 @@
+use ECE522_project::workload::{Distribution, KeyGenerator, Rng};
+
+use crate::cli::registry::DynTree;
+
+// Generated workloads, the same options and seed always give the same keys and the same operations
+//     gen --count 1000 --dist zipf --seed 7
+//     churn --ops 5000 --dist clustered --seed 7 --check-every 250
+//
+// Both return the command with every option spelled out, so a recorded session replays exactly
+// even if the defaults change.
+
+const DEFAULT_COUNT: usize = 100;
+const DEFAULT_OPS: usize = 1000;
+const DEFAULT_CHECK_EVERY: usize = 100;
+const DEFAULT_SEED: u64 = 42;
+
+pub const GEN_USAGE: &str = "gen [--count N] [--dist uniform|sequential|reverse|zipf|clustered] [--seed S]";
+pub const CHURN_USAGE: &str =
+    "churn [--ops N] [--dist uniform|sequential|reverse|zipf|clustered] [--seed S] [--check-every K]";
+
+struct Options {
+    count: usize,
+    distribution: Distribution,
+    seed: u64,
+    check_every: usize,
+}
+
+// `count_flag` is --count for gen and --ops for churn, --check-every is only allowed for churn
+fn parse_options(args: &[&str], count_flag: &str, count: usize, churn: bool) -> Result<Options, String> {
+    let mut options = Options {
+        count,
+        distribution: Distribution::Uniform,
+        seed: DEFAULT_SEED,
+        check_every: DEFAULT_CHECK_EVERY,
+    };
+    let mut words = args.iter();
+    while let Some(&flag) = words.next() {
+        let value = match words.next() {
+            Some(value) => *value,
+            None => return Err(format!("'{}' needs a value", flag)),
+        };
+        let number = || value.parse::<usize>().map_err(|_| format!("'{}' is not a valid number for {}", value, flag));
+        match flag {
+            _ if flag == count_flag => options.count = number()?,
+            "--dist" => options.distribution = value.parse()?,
+            "--seed" => {
+                options.seed = value.parse().map_err(|_| format!("'{}' is not a valid seed", value))?;
+            }
+            "--check-every" if churn => {
+                options.check_every = number()?;
+                if options.check_every == 0 {
+                    return Err("--check-every must be at least 1".to_string());
+                }
+            }
+            _ => return Err(format!("unknown option '{}'", flag)),
+        }
+    }
+    Ok(options)
+}
+
+// Err when the options of `gen` or `churn` are wrong, without touching any tree
+pub fn check_options(command: &str, args: &[&str]) -> Result<(), String> {
+    match command {
+        "gen" => parse_options(args, "--count", DEFAULT_COUNT, false).map(|_| ()),
+        _ => parse_options(args, "--ops", DEFAULT_OPS, true).map(|_| ()),
+    }
+}
+
+// Insert `--count` generated keys, keys that are already in the tree are skipped
+pub fn run_gen(tree: &mut dyn DynTree, args: &[&str]) -> Result<String, String> {
+    let options = parse_options(args, "--count", DEFAULT_COUNT, false)?;
+    let key_type = tree.key_type();
+    let mut generator = KeyGenerator::new(options.distribution, options.count, options.seed);
+    let mut inserted = 0;
+    for _ in 0..options.count {
+        if tree.insert_quiet(&key_type.key_text(generator.next_key())?)? {
+            inserted += 1;
+        }
+    }
+    println!(
+        "Generated {} {} keys (seed {}): {} inserted, {} already existed.",
+        options.count,
+        options.distribution.name(),
+        options.seed,
+        inserted,
+        options.count - inserted
+    );
+    Ok(format!(
+        "gen --count {} --dist {} --seed {}",
+        options.count,
+        options.distribution.name(),
+        options.seed
+    ))
+}
+
+// A seeded mix of about 50% inserts, 30% deletes and 20% updates, the tree is validated every `--check-every`
+// operations and at the end, and its size is compared with the number of keys that should be in it
+pub fn run_churn(tree: &mut dyn DynTree, args: &[&str]) -> Result<String, String> {
+    let options = parse_options(args, "--ops", DEFAULT_OPS, true)?;
+    let key_type = tree.key_type();
+    let mut generator = KeyGenerator::new(options.distribution, options.count, options.seed);
+    // a separate stream for choosing the operations, so the keys are the same as `gen` with the same options
+    let mut rng = Rng::new(options.seed ^ 0x5DEE_CE66_D1CE_5EED);
+    let command = format!(
+        "churn --ops {} --dist {} --seed {} --check-every {}",
+        options.count,
+        options.distribution.name(),
+        options.seed,
+        options.check_every
+    );
+
+    let mut expected = tree.count();
+    // the keys this run inserted and that are still in the tree
+    let mut live: Vec<u64> = Vec::new();
+    let (mut inserts, mut duplicates, mut deletes, mut updates, mut checks) = (0, 0, 0, 0, 0);
+    for op in 1..=options.count {
+        let roll = rng.below(10);
+        if live.is_empty() || roll < 5 {
+            let key = generator.next_key();
+            if tree.insert_quiet(&key_type.key_text(key)?)? {
+                live.push(key);
+                expected += 1;
+            } else {
+                duplicates += 1;
+            }
+            inserts += 1;
+        } else {
+            let old = live.swap_remove(rng.below(live.len() as u64) as usize);
+            let text = key_type.key_text(old)?;
+            if !tree.delete_quiet(&text)? {
+                return Err(format!("operation {} of `{}`: {} was inserted but could not be deleted", op, command, text));
+            }
+            if roll < 8 {
+                expected -= 1;
+                deletes += 1;
+            } else {
+                // update = the old key goes and a new one takes its place, unless the new one is already there
+                let new = generator.next_key();
+                if tree.insert_quiet(&key_type.key_text(new)?)? {
+                    live.push(new);
+                } else {
+                    expected -= 1;
+                    duplicates += 1;
+                }
+                updates += 1;
+            }
+        }
+
+        if op % options.check_every == 0 || op == options.count {
+            checks += 1;
+            if !tree.validate() {
+                return Err(format!("operation {} of `{}`: the tree is not valid any more", op, command));
+            }
+            let count = tree.count();
+            if count != expected {
+                return Err(format!(
+                    "operation {} of `{}`: the tree holds {} keys, expected {}",
+                    op, command, count, expected
+                ));
+            }
+        }
+    }
+    println!(
+        "Churn with {} {} operations (seed {}): {} inserts, {} deletes, {} updates, {} keys already existed.",
+        options.count,
+        options.distribution.name(),
+        options.seed,
+        inserts,
+        deletes,
+        updates,
+        duplicates
+    );
+    println!("The tree was validated {} times and holds {} keys.", checks, expected);
+    Ok(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::key::KeyType;
+    use crate::cli::registry::TREE_KINDS;
+
+    fn tree(command: &str, key_type: KeyType) -> Box<dyn DynTree> {
+        let kind = TREE_KINDS.iter().find(|kind| kind.command == command).unwrap();
+        (kind.create)(key_type)
+    }
+
+    fn words(line: &str) -> Vec<&str> {
+        line.split_whitespace().collect()
+    }
+
+    #[test]
+    fn gen_is_reproducible_for_every_distribution() {
+        for distribution in Distribution::ALL {
+            let args = format!("--count 200 --dist {} --seed 9", distribution.name());
+            let mut trees: Vec<Box<dyn DynTree>> = vec![tree("avl", KeyType::Int), tree("rb", KeyType::Int), tree("avl", KeyType::Int)];
+            for tree in &mut trees {
+                assert_eq!(run_gen(tree.as_mut(), &words(&args)), Ok(format!("gen {}", args)));
+            }
+            let keys: Vec<String> = trees.iter_mut().map(|tree| tree.in_order()).collect();
+            assert!(keys.iter().all(|k| k == &keys[0]), "{}", distribution.name());
+        }
+        let mut other_seed = tree("avl", KeyType::Int);
+        run_gen(other_seed.as_mut(), &words("--count 200 --seed 10")).unwrap();
+        let mut same_seed = tree("avl", KeyType::Int);
+        run_gen(same_seed.as_mut(), &words("--count 200 --seed 9")).unwrap();
+        assert_ne!(other_seed.in_order(), same_seed.in_order());
+    }
+
+    #[test]
+    fn churn_is_reproducible_for_every_distribution() {
+        for distribution in Distribution::ALL {
+            let args = format!("--ops 500 --dist {} --seed 4 --check-every 50", distribution.name());
+            let mut trees: Vec<Box<dyn DynTree>> = vec![tree("avl", KeyType::Str), tree("rb", KeyType::Str)];
+            for tree in &mut trees {
+                run_gen(tree.as_mut(), &words("--count 20 --seed 1")).unwrap();
+                assert_eq!(run_churn(tree.as_mut(), &words(&args)), Ok(format!("churn {}", args)));
+                assert!(tree.validate());
+            }
+            assert_eq!(trees[0].in_order(), trees[1].in_order(), "{}", distribution.name());
+        }
+    }
+
+    #[test]
+    fn the_recorded_command_spells_out_every_option() {
+        let mut avl = tree("avl", KeyType::Unsigned);
+        assert_eq!(run_gen(avl.as_mut(), &[]), Ok("gen --count 100 --dist uniform --seed 42".to_string()));
+        assert_eq!(
+            run_churn(avl.as_mut(), &words("--seed 3")),
+            Ok("churn --ops 1000 --dist uniform --seed 3 --check-every 100".to_string())
+        );
+    }
+
+    #[test]
+    fn sequential_keys_are_all_new() {
+        for key_type in [KeyType::Int, KeyType::Float, KeyType::Char, KeyType::Str] {
+            let mut avl = tree("avl", key_type);
+            run_gen(avl.as_mut(), &words("--count 300 --dist sequential")).unwrap();
+            assert_eq!(avl.count(), 300, "{:?}", key_type);
+        }
+    }
+
+    #[test]
+    fn wrong_options_are_refused_before_anything_runs() {
+        for (command, args) in [
+            ("gen", "--count"),
+            ("gen", "--count -1"),
+            ("gen", "--dist gauss"),
+            ("gen", "--seed x"),
+            ("gen", "--check-every 5"),
+            ("churn", "--count 5"),
+            ("churn", "--check-every 0"),
+            ("churn", "--ops 10 --verbose 1"),
+        ] {
+            assert!(check_options(command, &words(args)).is_err(), "{} {}", command, args);
+            let mut avl = tree("avl", KeyType::Int);
+            let result = if command == "gen" { run_gen(avl.as_mut(), &words(args)) } else { run_churn(avl.as_mut(), &words(args)) };
+            assert!(result.is_err() && avl.is_empty(), "{} {}", command, args);
+        }
+        assert!(check_options("churn", &words("--ops 10 --check-every 5 --dist zipf --seed 1")).is_ok());
+    }
+}
@@ -0,0 +1,611 @@
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+use crate::total_float::TotalF64;
+
+// Compact binary format shared by the AVL tree and the red-black tree
+//
+//   +-------+---------+----------+------------+--------------+----------------------+----------+
+//   | magic | version | key type | node count | payload size | pre-order node data  | checksum |
+//   | 4 B   | 1 B     | 1 B      | u32 (LE)   | u32 (LE)     | ...                  | u32 (LE) |
+//   +-------+---------+----------+------------+--------------+----------------------+----------+
+//
+// The key type byte is the `KEY_TAG` of the stored keys, so a file of i32 keys cannot be loaded as a tree of chars.
+// Every position in the pre-order stream starts with a tag byte: 0 for an empty subtree,
+// 1 for a node. A node then stores its balancing information (height for AVL, color for RB),
+// its key, and finally its left and right subtrees. The checksum is a CRC-32 of everything before it.
+
+pub const FORMAT_VERSION: u8 = 1;
+
+pub(crate) const TAG_EMPTY: u8 = 0;
+pub(crate) const TAG_NODE: u8 = 1;
+
+// Deepest tree we are willing to decode, protects the recursive decoder from hostile input
+pub(crate) const MAX_DEPTH: usize = 128;
+
+#[derive(Debug)]
+pub enum CodecError {
+    Io(std::io::Error),
+    // The file does not start with the magic bytes of the expected tree type
+    BadMagic,
+    // The file was written by a newer version of the format
+    UnsupportedVersion(u8),
+    // The file holds keys of another type than the tree it is loaded into
+    KeyTypeMismatch { expected: u8, found: u8 },
+    // The data ended before the tree was complete
+    Truncated,
+    // The stored checksum does not match the data
+    ChecksumMismatch { expected: u32, found: u32 },
+    // An unknown tag or color byte was found
+    InvalidTag(u8),
+    // A key could not be decoded (e.g. invalid UTF-8 in a string key)
+    InvalidKey,
+    // There is data left after the tree
+    TrailingBytes,
+    // The data decoded fine, but the result breaks the tree's invariants
+    InvalidTree(String),
+}
+
+impl Display for CodecError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodecError::Io(e) => write!(f, "I/O error: {}", e),
+            CodecError::BadMagic => write!(f, "not a tree file of the expected type (bad magic bytes)"),
+            CodecError::UnsupportedVersion(v) => write!(f, "unsupported format version {} (expected {})", v, FORMAT_VERSION),
+            CodecError::KeyTypeMismatch { expected, found } => {
+                write!(f, "the file holds {} keys, expected {} keys", key_type_name(*found), key_type_name(*expected))
+            }
+            CodecError::Truncated => write!(f, "file is truncated"),
+            CodecError::ChecksumMismatch { expected, found } => {
+                write!(f, "checksum mismatch: stored {:08x}, computed {:08x}", expected, found)
+            }
+            CodecError::InvalidTag(t) => write!(f, "invalid tag byte {}", t),
+            CodecError::InvalidKey => write!(f, "invalid key data"),
+            CodecError::TrailingBytes => write!(f, "unexpected data after the end of the tree"),
+            CodecError::InvalidTree(reason) => write!(f, "invalid tree: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+impl From<std::io::Error> for CodecError {
+    fn from(e: std::io::Error) -> Self {
+        CodecError::Io(e)
+    }
+}
+
+// Cursor over a byte slice, every read fails with `Truncated` instead of panicking
+pub struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        ByteReader { bytes, pos: 0 }
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, CodecError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub fn read_bytes(&mut self, n: usize) -> Result<&'a [u8], CodecError> {
+        if self.bytes.len() - self.pos < n {
+            return Err(CodecError::Truncated);
+        }
+        let res = &self.bytes[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(res)
+    }
+
+    pub fn read_array<const N: usize>(&mut self) -> Result<[u8; N], CodecError> {
+        let mut arr = [0u8; N];
+        arr.copy_from_slice(self.read_bytes(N)?);
+        Ok(arr)
+    }
+
+    // LEB128 unsigned integer
+    pub fn read_varint(&mut self) -> Result<u64, CodecError> {
+        let mut res = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_u8()?;
+            res |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(res);
+            }
+        }
+        Err(CodecError::InvalidKey)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+}
+
+pub fn write_varint(out: &mut Vec<u8>, mut val: u64) {
+    while val >= 0x80 {
+        out.push((val as u8) | 0x80);
+        val >>= 7;
+    }
+    out.push(val as u8);
+}
+
+// Keys that can be stored in a tree file
+pub trait BinaryKey: Sized {
+    // Written into the header, every key type has its own tag
+    const KEY_TAG: u8;
+    fn write_key(&self, out: &mut Vec<u8>);
+    fn read_key(input: &mut ByteReader) -> Result<Self, CodecError>;
+}
+
+macro_rules! impl_binary_key_for_number {
+    ($($t:ty => $tag:expr),*) => {
+        $(
+            impl BinaryKey for $t {
+                const KEY_TAG: u8 = $tag;
+                fn write_key(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes());
+                }
+                fn read_key(input: &mut ByteReader) -> Result<Self, CodecError> {
+                    Ok(<$t>::from_le_bytes(input.read_array()?))
+                }
+            }
+        )*
+    };
+}
+
+impl_binary_key_for_number!(u8 => 1, u16 => 2, u32 => 3, u64 => 4, i8 => 5, i16 => 6, i32 => 7, i64 => 8, f32 => 9, f64 => 10);
+
+impl BinaryKey for bool {
+    const KEY_TAG: u8 = 11;
+    fn write_key(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+    fn read_key(input: &mut ByteReader) -> Result<Self, CodecError> {
+        match input.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(CodecError::InvalidKey),
+        }
+    }
+}
+
+impl BinaryKey for char {
+    const KEY_TAG: u8 = 12;
+    fn write_key(&self, out: &mut Vec<u8>) {
+        (*self as u32).write_key(out);
+    }
+    fn read_key(input: &mut ByteReader) -> Result<Self, CodecError> {
+        char::from_u32(u32::read_key(input)?).ok_or(CodecError::InvalidKey)
+    }
+}
+
+impl BinaryKey for TotalF64 {
+    const KEY_TAG: u8 = 13;
+    fn write_key(&self, out: &mut Vec<u8>) {
+        self.0.write_key(out);
+    }
+    fn read_key(input: &mut ByteReader) -> Result<Self, CodecError> {
+        f64::read_key(input).map(TotalF64)
+    }
+}
+
+impl BinaryKey for String {
+    const KEY_TAG: u8 = 14;
+    fn write_key(&self, out: &mut Vec<u8>) {
+        write_varint(out, self.len() as u64);
+        out.extend_from_slice(self.as_bytes());
+    }
+    fn read_key(input: &mut ByteReader) -> Result<Self, CodecError> {
+        let len = input.read_varint()? as usize;
+        let bytes = input.read_bytes(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| CodecError::InvalidKey)
+    }
+}
+
+// Save / load a tree, implemented by every tree type that can be persisted
+pub trait TreeCodec: Sized {
+    fn to_bytes(&self) -> Vec<u8>;
+    // encode the tree
+    fn from_bytes(bytes: &[u8]) -> Result<Self, CodecError>;
+    // decode a tree, checking magic, version, checksum and the tree's invariants
+
+    fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), CodecError> {
+        std::fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, CodecError> {
+        let bytes = std::fs::read(path)?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+// Name of the key type behind a `KEY_TAG`, for error messages
+pub fn key_type_name(tag: u8) -> &'static str {
+    match tag {
+        1 => "u8",
+        2 => "u16",
+        3 => "u32",
+        4 => "u64",
+        5 => "i8",
+        6 => "i16",
+        7 => "i32",
+        8 => "i64",
+        9 => "f32",
+        10 => "f64",
+        11 => "bool",
+        12 => "char",
+        13 => "TotalF64",
+        14 => "String",
+        _ => "unknown",
+    }
+}
+
+const HEADER_LEN: usize = 4 + 1 + 1 + 4 + 4;
+
+// Start a new file: magic, version, key type, node count and a placeholder for the payload size
+pub(crate) fn write_header(magic: &[u8; 4], key_tag: u8, node_count: u32) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(magic);
+    out.push(FORMAT_VERSION);
+    out.push(key_tag);
+    out.extend_from_slice(&node_count.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out
+}
+
+// Seal a file: fill in the payload size and append the checksum of everything written so far
+pub(crate) fn finish(out: &mut Vec<u8>) {
+    let payload_len = (out.len() - HEADER_LEN) as u32;
+    out[HEADER_LEN - 4..HEADER_LEN].copy_from_slice(&payload_len.to_le_bytes());
+    let checksum = crc32(out);
+    out.extend_from_slice(&checksum.to_le_bytes());
+}
+
+// Check the envelope of a file and return the node count and a reader over the payload
+pub(crate) fn open<'a>(bytes: &'a [u8], magic: &[u8; 4], key_tag: u8) -> Result<(u32, ByteReader<'a>), CodecError> {
+    let mut header = ByteReader::new(bytes);
+    if header.read_bytes(4)? != magic {
+        return Err(CodecError::BadMagic);
+    }
+    let version = header.read_u8()?;
+    if version != FORMAT_VERSION {
+        return Err(CodecError::UnsupportedVersion(version));
+    }
+    let found = header.read_u8()?;
+    if found != key_tag {
+        return Err(CodecError::KeyTypeMismatch { expected: key_tag, found });
+    }
+    let node_count = u32::from_le_bytes(header.read_array()?);
+    let payload_len = u32::from_le_bytes(header.read_array()?) as usize;
+    match bytes.len().checked_sub(HEADER_LEN + payload_len + 4) {
+        None => return Err(CodecError::Truncated),
+        Some(0) => {}
+        Some(_) => return Err(CodecError::TrailingBytes),
+    }
+    let (data, tail) = bytes.split_at(HEADER_LEN + payload_len);
+    let expected = u32::from_le_bytes([tail[0], tail[1], tail[2], tail[3]]);
+    let found = crc32(data);
+    if expected != found {
+        return Err(CodecError::ChecksumMismatch { expected, found });
+    }
+    Ok((node_count, ByteReader::new(&data[HEADER_LEN..])))
+}
+
+// CRC-32 (IEEE 802.3), bitwise so we do not need a lookup table
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workload::Rng;
+    use crate::AVL::{AvlTree, AvlTreeNode};
+    use crate::RBTree::RBTree;
+
+    const AVL_MAGIC: &[u8; 4] = b"AVLT";
+    const RB_MAGIC: &[u8; 4] = b"RBTR";
+    const RED: u8 = 0;
+    const BLACK: u8 = 1;
+
+    fn avl_of(keys: &[i32]) -> AvlTreeNode<i32> {
+        let mut tree: AvlTreeNode<i32> = AvlTree::generate_empty_tree();
+        for &key in keys {
+            tree.insert_node(key);
+        }
+        tree
+    }
+
+    fn rb_of(keys: &[u32]) -> RBTree<u32> {
+        let mut tree = RBTree::new();
+        for &key in keys {
+            tree.insert_node(key);
+        }
+        tree
+    }
+
+    fn shuffled(n: u32, seed: u64) -> Vec<u32> {
+        let mut keys: Vec<u32> = (0..n).collect();
+        Rng::new(seed).shuffle(&mut keys);
+        keys
+    }
+
+    // A complete file around a hand-written payload
+    fn sealed(magic: &[u8; 4], key_tag: u8, node_count: u32, payload: &[u8]) -> Vec<u8> {
+        let mut out = write_header(magic, key_tag, node_count);
+        out.extend_from_slice(payload);
+        finish(&mut out);
+        out
+    }
+
+    // The start of an AVL node holding an i32, its two subtrees have to follow
+    fn avl_node(payload: &mut Vec<u8>, height: u8, key: i32) {
+        payload.extend_from_slice(&[TAG_NODE, height]);
+        key.write_key(payload);
+    }
+
+    // The start of a red-black node holding a u32, its two subtrees have to follow
+    fn rb_node(payload: &mut Vec<u8>, color: u8, key: u32) {
+        payload.extend_from_slice(&[TAG_NODE, color]);
+        key.write_key(payload);
+    }
+
+    #[test]
+    fn avl_trees_round_trip_with_their_shape() {
+        for seed in 0..10 {
+            let keys: Vec<i32> = shuffled(200, seed).into_iter().map(|key| key as i32 - 100).collect();
+            let tree = avl_of(&keys);
+            let bytes = tree.to_bytes();
+            let loaded = AvlTreeNode::<i32>::from_bytes(&bytes).unwrap();
+            assert_eq!(loaded.pre_order_traverse(), tree.pre_order_traverse());
+            assert_eq!(loaded.height_of_tree(), tree.height_of_tree());
+            // heights are part of the encoding, so equal bytes mean an equal shape
+            assert_eq!(loaded.to_bytes(), bytes);
+        }
+        let empty = avl_of(&[]);
+        assert!(AvlTreeNode::<i32>::from_bytes(&empty.to_bytes()).unwrap().is_tree_empty());
+    }
+
+    #[test]
+    fn rb_trees_round_trip_with_their_shape() {
+        for seed in 0..10 {
+            let tree = rb_of(&shuffled(200, seed));
+            let bytes = tree.to_bytes();
+            let loaded = RBTree::<u32>::from_bytes(&bytes).unwrap();
+            assert_eq!(loaded.print_pre_order_traversal(), tree.print_pre_order_traversal());
+            assert_eq!(loaded.pre_order_colors(), tree.pre_order_colors());
+            assert_eq!(loaded.to_bytes(), bytes);
+        }
+        assert!(RBTree::<u32>::from_bytes(&rb_of(&[]).to_bytes()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn string_keys_round_trip() {
+        let words = ["pear", "apple", "", "fig", "äpfel", "banana", "kiwi"];
+        let mut avl: AvlTreeNode<String> = AvlTree::generate_empty_tree();
+        let mut rb = RBTree::new();
+        for word in words {
+            avl.insert_node(word.to_string());
+            rb.insert_node(word.to_string());
+        }
+        let loaded = AvlTreeNode::<String>::from_bytes(&avl.to_bytes()).unwrap();
+        assert_eq!(loaded.in_order_traverse(), avl.in_order_traverse());
+        let loaded = RBTree::<String>::from_bytes(&rb.to_bytes()).unwrap();
+        assert_eq!(loaded.print_in_order_traversal(), rb.print_in_order_traversal());
+    }
+
+    #[test]
+    fn every_truncation_is_reported() {
+        let avl = avl_of(&[5, 3, 8, 1]).to_bytes();
+        for len in 0..avl.len() {
+            assert!(matches!(AvlTreeNode::<i32>::from_bytes(&avl[..len]), Err(CodecError::Truncated)), "{} bytes", len);
+        }
+        let rb = rb_of(&[5, 3, 8, 1]).to_bytes();
+        for len in 0..rb.len() {
+            assert!(matches!(RBTree::<u32>::from_bytes(&rb[..len]), Err(CodecError::Truncated)), "{} bytes", len);
+        }
+    }
+
+    #[test]
+    fn a_tree_of_the_other_type_has_bad_magic() {
+        let mut bytes = avl_of(&[1, 2, 3]).to_bytes();
+        // same key type, so only the magic bytes differ
+        bytes[5] = u32::KEY_TAG;
+        assert!(matches!(RBTree::<u32>::from_bytes(&bytes), Err(CodecError::BadMagic)));
+        assert!(matches!(AvlTreeNode::<i32>::from_bytes(&rb_of(&[1, 2, 3]).to_bytes()), Err(CodecError::BadMagic)));
+        assert!(matches!(AvlTreeNode::<i32>::from_bytes(b"not a tree file"), Err(CodecError::BadMagic)));
+    }
+
+    #[test]
+    fn other_versions_are_unsupported() {
+        let mut bytes = avl_of(&[1, 2, 3]).to_bytes();
+        bytes[4] = FORMAT_VERSION + 1;
+        assert!(matches!(AvlTreeNode::<i32>::from_bytes(&bytes), Err(CodecError::UnsupportedVersion(v)) if v == FORMAT_VERSION + 1));
+        bytes[4] = 0;
+        assert!(matches!(AvlTreeNode::<i32>::from_bytes(&bytes), Err(CodecError::UnsupportedVersion(0))));
+    }
+
+    #[test]
+    fn keys_of_another_type_are_refused() {
+        let bytes = avl_of(&[65, 66, 67]).to_bytes();
+        match AvlTreeNode::<char>::from_bytes(&bytes) {
+            Err(e @ CodecError::KeyTypeMismatch { .. }) => {
+                assert_eq!(e.to_string(), "the file holds i32 keys, expected char keys");
+            }
+            other => panic!("expected a key type mismatch, got {:?}", other.err()),
+        }
+        assert!(matches!(
+            AvlTreeNode::<u32>::from_bytes(&bytes),
+            Err(CodecError::KeyTypeMismatch { expected, found }) if expected == u32::KEY_TAG && found == i32::KEY_TAG
+        ));
+        assert!(matches!(RBTree::<i32>::from_bytes(&rb_of(&[1]).to_bytes()), Err(CodecError::KeyTypeMismatch { .. })));
+    }
+
+    #[test]
+    fn a_changed_byte_breaks_the_checksum() {
+        let bytes = rb_of(&[5, 3, 8, 1]).to_bytes();
+        for pos in HEADER_LEN..bytes.len() - 4 {
+            let mut corrupt = bytes.clone();
+            corrupt[pos] ^= 0x40;
+            assert!(matches!(RBTree::<u32>::from_bytes(&corrupt), Err(CodecError::ChecksumMismatch { .. })), "byte {}", pos);
+        }
+        let mut corrupt = bytes;
+        let last = corrupt.len() - 1;
+        corrupt[last] ^= 1;
+        assert!(matches!(RBTree::<u32>::from_bytes(&corrupt), Err(CodecError::ChecksumMismatch { .. })));
+    }
+
+    #[test]
+    fn data_after_the_tree_is_refused() {
+        // after the checksum
+        let mut bytes = avl_of(&[1, 2, 3]).to_bytes();
+        bytes.push(0);
+        assert!(matches!(AvlTreeNode::<i32>::from_bytes(&bytes), Err(CodecError::TrailingBytes)));
+        // inside the payload, covered by the checksum
+        let mut payload = Vec::new();
+        avl_node(&mut payload, 1, 7);
+        payload.extend_from_slice(&[TAG_EMPTY, TAG_EMPTY, TAG_EMPTY]);
+        assert!(matches!(AvlTreeNode::<i32>::from_bytes(&sealed(AVL_MAGIC, i32::KEY_TAG, 1, &payload)), Err(CodecError::TrailingBytes)));
+        let mut payload = Vec::new();
+        rb_node(&mut payload, BLACK, 7);
+        payload.extend_from_slice(&[TAG_EMPTY, TAG_EMPTY, 42]);
+        assert!(matches!(RBTree::<u32>::from_bytes(&sealed(RB_MAGIC, u32::KEY_TAG, 1, &payload)), Err(CodecError::TrailingBytes)));
+    }
+
+    #[test]
+    fn unknown_tags_and_colors_are_refused() {
+        assert!(matches!(AvlTreeNode::<i32>::from_bytes(&sealed(AVL_MAGIC, i32::KEY_TAG, 0, &[7])), Err(CodecError::InvalidTag(7))));
+        let mut payload = Vec::new();
+        rb_node(&mut payload, 2, 7);
+        payload.extend_from_slice(&[TAG_EMPTY, TAG_EMPTY]);
+        assert!(matches!(RBTree::<u32>::from_bytes(&sealed(RB_MAGIC, u32::KEY_TAG, 1, &payload)), Err(CodecError::InvalidTag(2))));
+    }
+
+    #[test]
+    fn undecodable_keys_are_refused() {
+        // a string that is not UTF-8
+        let payload = [TAG_NODE, 1, 2, 0xff, 0xfe, TAG_EMPTY, TAG_EMPTY];
+        assert!(matches!(AvlTreeNode::<String>::from_bytes(&sealed(AVL_MAGIC, String::KEY_TAG, 1, &payload)), Err(CodecError::InvalidKey)));
+        // a surrogate is not a char
+        let mut payload = vec![TAG_NODE, BLACK];
+        0xd800u32.write_key(&mut payload);
+        payload.extend_from_slice(&[TAG_EMPTY, TAG_EMPTY]);
+        assert!(matches!(RBTree::<char>::from_bytes(&sealed(RB_MAGIC, char::KEY_TAG, 1, &payload)), Err(CodecError::InvalidKey)));
+    }
+
+    #[test]
+    fn avl_payloads_breaking_the_invariants_are_refused() {
+        let decode = |node_count, payload: &[u8]| AvlTreeNode::<i32>::from_bytes(&sealed(AVL_MAGIC, i32::KEY_TAG, node_count, payload));
+
+        // 1 -> 2 -> 3, heights are right but the tree is not balanced
+        let mut payload = Vec::new();
+        avl_node(&mut payload, 3, 1);
+        payload.push(TAG_EMPTY);
+        avl_node(&mut payload, 2, 2);
+        payload.push(TAG_EMPTY);
+        avl_node(&mut payload, 1, 3);
+        payload.extend_from_slice(&[TAG_EMPTY, TAG_EMPTY]);
+        assert!(matches!(decode(3, &payload), Err(CodecError::InvalidTree(_))));
+
+        // 2(1, 3) with a wrong height
+        let mut payload = Vec::new();
+        avl_node(&mut payload, 3, 2);
+        avl_node(&mut payload, 1, 1);
+        payload.extend_from_slice(&[TAG_EMPTY, TAG_EMPTY]);
+        avl_node(&mut payload, 1, 3);
+        payload.extend_from_slice(&[TAG_EMPTY, TAG_EMPTY]);
+        assert!(matches!(decode(3, &payload), Err(CodecError::InvalidTree(_))));
+
+        // 2(3, 1): children on the wrong side
+        let mut payload = Vec::new();
+        avl_node(&mut payload, 2, 2);
+        avl_node(&mut payload, 1, 3);
+        payload.extend_from_slice(&[TAG_EMPTY, TAG_EMPTY]);
+        avl_node(&mut payload, 1, 1);
+        payload.extend_from_slice(&[TAG_EMPTY, TAG_EMPTY]);
+        assert!(matches!(decode(3, &payload), Err(CodecError::InvalidTree(_))));
+
+        // 2(2, -): a duplicate key
+        let mut payload = Vec::new();
+        avl_node(&mut payload, 2, 2);
+        avl_node(&mut payload, 1, 2);
+        payload.extend_from_slice(&[TAG_EMPTY, TAG_EMPTY, TAG_EMPTY]);
+        assert!(matches!(decode(2, &payload), Err(CodecError::InvalidTree(_))));
+
+        // a fine tree, but the header counts another number of nodes
+        let mut payload = Vec::new();
+        avl_node(&mut payload, 1, 2);
+        payload.extend_from_slice(&[TAG_EMPTY, TAG_EMPTY]);
+        assert!(decode(1, &payload).is_ok());
+        assert!(matches!(decode(2, &payload), Err(CodecError::InvalidTree(_))));
+
+        // a height of 0 is not a node
+        let mut payload = Vec::new();
+        avl_node(&mut payload, 0, 2);
+        payload.extend_from_slice(&[TAG_EMPTY, TAG_EMPTY]);
+        assert!(matches!(decode(1, &payload), Err(CodecError::InvalidTree(_))));
+    }
+
+    #[test]
+    fn rb_payloads_breaking_the_invariants_are_refused() {
+        let decode = |node_count, payload: &[u8]| RBTree::<u32>::from_bytes(&sealed(RB_MAGIC, u32::KEY_TAG, node_count, payload));
+        let leaf = |payload: &mut Vec<u8>, color, key| {
+            rb_node(payload, color, key);
+            payload.extend_from_slice(&[TAG_EMPTY, TAG_EMPTY]);
+        };
+
+        // black 2 with red children 1 and 3 is fine
+        let mut payload = Vec::new();
+        rb_node(&mut payload, BLACK, 2);
+        leaf(&mut payload, RED, 1);
+        leaf(&mut payload, RED, 3);
+        assert!(decode(3, &payload).is_ok());
+
+        // a red root
+        let mut payload = Vec::new();
+        leaf(&mut payload, RED, 2);
+        assert!(matches!(decode(1, &payload), Err(CodecError::InvalidTree(_))));
+
+        // a red node with a red child
+        let mut payload = Vec::new();
+        rb_node(&mut payload, BLACK, 2);
+        rb_node(&mut payload, RED, 1);
+        leaf(&mut payload, RED, 0);
+        payload.push(TAG_EMPTY);
+        payload.push(TAG_EMPTY);
+        assert!(matches!(decode(3, &payload), Err(CodecError::InvalidTree(_))));
+
+        // one black child only, the paths have different black heights
+        let mut payload = Vec::new();
+        rb_node(&mut payload, BLACK, 2);
+        leaf(&mut payload, BLACK, 1);
+        payload.push(TAG_EMPTY);
+        assert!(matches!(decode(2, &payload), Err(CodecError::InvalidTree(_))));
+
+        // the grandchild 4 is on the left of its grandparent 3
+        let mut payload = Vec::new();
+        rb_node(&mut payload, BLACK, 3);
+        rb_node(&mut payload, BLACK, 1);
+        payload.push(TAG_EMPTY);
+        leaf(&mut payload, RED, 4);
+        leaf(&mut payload, BLACK, 5);
+        assert!(matches!(decode(4, &payload), Err(CodecError::InvalidTree(_))));
+    }
+
+    #[test]
+    fn missing_files_are_io_errors() {
+        let path = std::env::temp_dir().join("no-such-dir-for-codec-tests").join("tree.avl");
+        assert!(matches!(AvlTreeNode::<i32>::load_from_file(&path), Err(CodecError::Io(_))));
+    }
+}
@@ -0,0 +1,370 @@
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+use std::panic::{self, AssertUnwindSafe};
+use std::str::FromStr;
+
+use crate::trace::{self, TraceEvent};
+use crate::AVL::{AvlTree, AvlTreeNode};
+use crate::BST::{BinarySearchTree, Node};
+use crate::RBTree::RBTree;
+
+// Fuzz entry points: any byte string is decoded into a sequence of tree operations, which is applied to
+// one tree and to a BTreeSet model. After every operation the tree has to give the same answer as the
+// model, hold the same keys and pass its validator. `cargo run fuzz` (src/cli/fuzz.rs) drives these
+// with a corpus directory, they can also be called from any other fuzzer.
+//
+// Decoding, one opcode byte followed by its key bytes, a truncated last operation is dropped:
+//     opcode % 8 = 0, 1   insert <key>
+//                  2      delete <key>
+//                  3      update <old> <new>
+//                  4      search <key>
+//                  5      insert <key>, <key> + 1, ... (opcode / 8 + 1 keys)
+//                  6      delete <key>, <key> + 1, ... (opcode / 8 + 1 keys)
+//                  7      insert <key>, <key> - 1, ... (opcode / 8 + 1 keys, down to 0)
+// Keys are one byte, so short inputs hit existing keys often. The runs reach deep trees and long
+// chains of rotations and fix-ups with only a few bytes.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FuzzOp {
+    Insert(u32),
+    Delete(u32),
+    Update(u32, u32),
+    Search(u32),
+}
+
+impl Display for FuzzOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FuzzOp::Insert(key) => write!(f, "insert {}", key),
+            FuzzOp::Delete(key) => write!(f, "delete {}", key),
+            FuzzOp::Update(old, new) => write!(f, "update {} {}", old, new),
+            FuzzOp::Search(key) => write!(f, "search {}", key),
+        }
+    }
+}
+
+pub fn decode(data: &[u8]) -> Vec<FuzzOp> {
+    let mut ops = Vec::new();
+    let mut i = 0;
+    while i + 1 < data.len() {
+        let opcode = data[i];
+        let key = data[i + 1] as u32;
+        let run = (opcode / 8) as u32 + 1;
+        match opcode % 8 {
+            0 | 1 => ops.push(FuzzOp::Insert(key)),
+            2 => ops.push(FuzzOp::Delete(key)),
+            3 => match data.get(i + 2) {
+                Some(&new) => {
+                    ops.push(FuzzOp::Update(key, new as u32));
+                    i += 1;
+                }
+                None => break,
+            },
+            4 => ops.push(FuzzOp::Search(key)),
+            5 => ops.extend((key..key + run).map(FuzzOp::Insert)),
+            6 => ops.extend((key..key + run).map(FuzzOp::Delete)),
+            _ => ops.extend((key.saturating_sub(run - 1)..=key).rev().map(FuzzOp::Insert)),
+        }
+        i += 2;
+    }
+    ops
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FuzzTarget {
+    Avl,
+    RedBlack,
+    Bst,
+}
+
+impl FuzzTarget {
+    pub const ALL: [FuzzTarget; 3] = [FuzzTarget::Avl, FuzzTarget::RedBlack, FuzzTarget::Bst];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FuzzTarget::Avl => "avl",
+            FuzzTarget::RedBlack => "rb",
+            FuzzTarget::Bst => "bst",
+        }
+    }
+}
+
+impl FromStr for FuzzTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        FuzzTarget::ALL.iter().copied().find(|target| target.name() == s)
+            .ok_or_else(|| format!("'{}' is not a fuzz target, expected avl, rb or bst", s))
+    }
+}
+
+// What a fuzzed tree has to offer, every operation answers like the BTreeSet model does
+trait FuzzTree {
+    fn insert(&mut self, key: u32) -> bool;
+    fn delete(&mut self, key: u32) -> bool;
+    fn update(&mut self, old: u32, new: u32) -> bool;
+    fn search(&mut self, key: u32) -> bool;
+    fn keys(&mut self) -> Vec<u32>;
+    fn count(&mut self) -> usize;
+    fn valid(&self) -> bool;
+}
+
+impl FuzzTree for AvlTreeNode<u32> {
+    fn insert(&mut self, key: u32) -> bool {
+        if self.exist_or_not(&key) {
+            return false;
+        }
+        self.insert_node(key);
+        true
+    }
+
+    fn delete(&mut self, key: u32) -> bool {
+        self.remove_node(&key)
+    }
+
+    fn update(&mut self, old: u32, new: u32) -> bool {
+        self.replace_node(&old, new)
+    }
+
+    fn search(&mut self, key: u32) -> bool {
+        self.exist_or_not(&key)
+    }
+
+    fn keys(&mut self) -> Vec<u32> {
+        self.in_order_traverse().into_iter().copied().collect()
+    }
+
+    fn count(&mut self) -> usize {
+        self.total_number_elements() as usize
+    }
+
+    fn valid(&self) -> bool {
+        self.validate_tree()
+    }
+}
+
+impl FuzzTree for RBTree<u32> {
+    fn insert(&mut self, key: u32) -> bool {
+        self.insert_node(key)
+    }
+
+    fn delete(&mut self, key: u32) -> bool {
+        RBTree::delete(self, key).is_ok()
+    }
+
+    fn update(&mut self, old: u32, new: u32) -> bool {
+        self.replace_node(old, new)
+    }
+
+    fn search(&mut self, key: u32) -> bool {
+        self.exist_or_not(key)
+    }
+
+    fn keys(&mut self) -> Vec<u32> {
+        self.print_in_order_traversal()
+    }
+
+    fn count(&mut self) -> usize {
+        self.total_number_elements() as usize
+    }
+
+    fn valid(&self) -> bool {
+        self.validate_tree()
+    }
+}
+
+impl FuzzTree for Node<u32> {
+    fn insert(&mut self, key: u32) -> bool {
+        if self.search_node(key) {
+            return false;
+        }
+        self.insert_node(key);
+        true
+    }
+
+    fn delete(&mut self, key: u32) -> bool {
+        self.delete_node(key)
+    }
+
+    // the plain binary search tree has no update, this is what replace_node does in the other trees
+    fn update(&mut self, old: u32, new: u32) -> bool {
+        if old == new || !self.search_node(old) || self.search_node(new) {
+            return false;
+        }
+        self.delete_node(old);
+        self.insert_node(new);
+        true
+    }
+
+    fn search(&mut self, key: u32) -> bool {
+        let found = self.search_node(key);
+        assert_eq!(found, self.search_node_optimize(key), "search_node and search_node_optimize disagree on {}", key);
+        found
+    }
+
+    fn keys(&mut self) -> Vec<u32> {
+        self.in_order_traverse()
+    }
+
+    fn count(&mut self) -> usize {
+        self.in_order_traverse().len()
+    }
+
+    // no balance to check, only the search order
+    fn valid(&self) -> bool {
+        self.in_order_traverse().windows(2).all(|w| w[0] < w[1])
+    }
+}
+
+fn apply_to_model(model: &mut BTreeSet<u32>, op: FuzzOp) -> bool {
+    match op {
+        FuzzOp::Insert(key) => model.insert(key),
+        FuzzOp::Delete(key) => model.remove(&key),
+        FuzzOp::Update(old, new) => {
+            if old == new || !model.contains(&old) || model.contains(&new) {
+                return false;
+            }
+            model.remove(&old);
+            model.insert(new)
+        }
+        FuzzOp::Search(key) => model.contains(&key),
+    }
+}
+
+fn apply_to_tree<T: FuzzTree>(tree: &mut T, op: FuzzOp) -> bool {
+    match op {
+        FuzzOp::Insert(key) => tree.insert(key),
+        FuzzOp::Delete(key) => tree.delete(key),
+        FuzzOp::Update(old, new) => tree.update(old, new),
+        FuzzOp::Search(key) => tree.search(key),
+    }
+}
+
+// Run `ops` on the tree and the model, `features` collects what happened for coverage
+fn execute<T: FuzzTree>(mut tree: T, ops: &[FuzzOp], mut features: Option<&mut BTreeSet<String>>) -> Result<(), String> {
+    let mut model = BTreeSet::new();
+    for (step, &op) in ops.iter().enumerate() {
+        let fail = |reason: String| Err(format!("step {} `{}`: {}", step + 1, op, reason));
+        let expected = apply_to_model(&mut model, op);
+        let answer = apply_to_tree(&mut tree, op);
+        if answer != expected {
+            return fail(format!("answered {}, expected {}", answer, expected));
+        }
+        let keys = tree.keys();
+        if !keys.iter().eq(model.iter()) {
+            return fail(format!("holds {:?}, expected {:?}", keys, model));
+        }
+        let count = tree.count();
+        if count != model.len() {
+            return fail(format!("counts {} keys, expected {}", count, model.len()));
+        }
+        if !tree.valid() {
+            return fail("the tree is not valid".to_string());
+        }
+        if let Some(features) = features.as_mut() {
+            // the operation, whether it changed / found something and the tree size in powers of two
+            let name = op.to_string();
+            let name = name.split(' ').next().unwrap();
+            features.insert(format!("{} {} size~{}", name, answer, usize::BITS - model.len().leading_zeros()));
+        }
+    }
+    Ok(())
+}
+
+fn execute_target(target: FuzzTarget, ops: &[FuzzOp], features: Option<&mut BTreeSet<String>>) -> Result<(), String> {
+    match target {
+        FuzzTarget::Avl => execute::<AvlTreeNode<u32>>(AvlTree::generate_empty_tree(), ops, features),
+        FuzzTarget::RedBlack => execute(RBTree::<u32>::new(), ops, features),
+        FuzzTarget::Bst => execute::<Node<u32>>(Node::gen_empty_tree(), ops, features),
+    }
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "unknown panic".to_string(),
+        },
+    }
+}
+
+// The fuzz entry point: Err explains the first operation where the tree and the model differ,
+// a panic in the tree is caught and reported the same way
+pub fn check(target: FuzzTarget, data: &[u8]) -> Result<(), String> {
+    let ops = decode(data);
+    panic::catch_unwind(AssertUnwindSafe(|| execute_target(target, &ops, None)))
+        .unwrap_or_else(|payload| Err(format!("panicked: {}", panic_message(payload))))
+}
+
+fn event_feature(event: &TraceEvent) -> Option<String> {
+    match event {
+        TraceEvent::RotateLeft { .. } => Some("rotate left".to_string()),
+        TraceEvent::RotateRight { .. } => Some("rotate right".to_string()),
+        TraceEvent::RotateLR { .. } => Some("rotate left-right".to_string()),
+        TraceEvent::RotateRL { .. } => Some("rotate right-left".to_string()),
+        TraceEvent::Recolor { color, .. } => Some(format!("recolor {}", color)),
+        TraceEvent::Case { operation, number, .. } => Some(format!("{} case {}", operation, number)),
+        TraceEvent::Compare { .. } | TraceEvent::HeightUpdate { .. } => None,
+    }
+}
+
+// `check` that also returns what the input exercised: the rotations, recolorings and fix-up cases the tree
+// went through (each one and each pair of consecutive ones) and the kinds of operations at each tree size.
+// An input with a feature no other input has is worth keeping in the corpus.
+pub fn check_with_features(target: FuzzTarget, data: &[u8]) -> (Result<(), String>, BTreeSet<String>) {
+    let ops = decode(data);
+    let mut features = BTreeSet::new();
+    let (result, events) = trace::record(|| {
+        panic::catch_unwind(AssertUnwindSafe(|| execute_target(target, &ops, Some(&mut features))))
+            .unwrap_or_else(|payload| Err(format!("panicked: {}", panic_message(payload))))
+    });
+    let steps: Vec<String> = events.iter().filter_map(event_feature).collect();
+    for pair in steps.windows(2) {
+        features.insert(format!("{} -> {}", pair[0], pair[1]));
+    }
+    features.extend(steps);
+    (result, features)
+}
+
+// A smaller input that still fails `check`: byte ranges are removed (halves, quarters, ... single bytes)
+// and bytes lowered towards 0 (an opcode by 8 first, which shortens its run) until neither changes anything
+pub fn minimize(target: FuzzTarget, data: &[u8]) -> Vec<u8> {
+    let fails = |data: &[u8]| check(target, data).is_err();
+    let mut data = data.to_vec();
+    loop {
+        let mut progress = false;
+        let mut chunk = data.len() / 2;
+        while chunk > 0 {
+            let mut start = 0;
+            while start < data.len() {
+                let mut candidate = data.clone();
+                candidate.drain(start..(start + chunk).min(data.len()));
+                if fails(&candidate) {
+                    data = candidate;
+                    progress = true;
+                } else {
+                    start += chunk;
+                }
+            }
+            chunk /= 2;
+        }
+        for i in 0..data.len() {
+            for smaller in [0, data[i] / 2, data[i].saturating_sub(8), data[i].saturating_sub(1)].iter().copied() {
+                if smaller >= data[i] {
+                    continue;
+                }
+                let mut candidate = data.clone();
+                candidate[i] = smaller;
+                if fails(&candidate) {
+                    data = candidate;
+                    progress = true;
+                    break;
+                }
+            }
+        }
+        if !progress {
+            return data;
+        }
+    }
+}
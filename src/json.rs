@@ -0,0 +1,589 @@
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter, Write};
+
+use crate::total_float::TotalF64;
+
+// Minimal JSON support for exporting / importing trees, no external crates needed
+//
+// Trees are exported in two shapes:
+//   nested: {"key":2,"height":2,"left":{"key":1,...},"right":null}   (an empty tree is `null`)
+//   sorted: [1,2,3]
+
+// Deepest nesting we accept while parsing
+const MAX_DEPTH: usize = 512;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    // Numbers keep their original text so that large integers do not lose precision
+    Number(String),
+    String(String),
+    Array(Vec<JsonValue>),
+    // Fields keep their original order, a name can only appear once
+    Object(Vec<(String, JsonValue)>),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum JsonError {
+    // The text is not valid JSON
+    Syntax { position: usize, message: String },
+    // Valid JSON, but not in the shape we expect
+    Schema(String),
+    // The structure describes a tree that breaks the AVL / red-black rules
+    InvalidTree(String),
+}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonError::Syntax { position, message } => write!(f, "JSON syntax error at byte {}: {}", position, message),
+            JsonError::Schema(message) => write!(f, "unexpected JSON structure: {}", message),
+            JsonError::InvalidTree(message) => write!(f, "invalid tree: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+impl JsonValue {
+    pub fn parse(text: &str) -> Result<JsonValue, JsonError> {
+        let mut parser = Parser { bytes: text.as_bytes(), pos: 0 };
+        let value = parser.parse_value(0)?;
+        parser.skip_whitespace();
+        if parser.pos != parser.bytes.len() {
+            return Err(parser.error("unexpected data after the end of the document"));
+        }
+        Ok(value)
+    }
+
+    // Look up a field of an object
+    pub fn get(&self, field: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(fields) => fields.iter().find(|(name, _)| name == field).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            JsonValue::Null => "null",
+            JsonValue::Bool(_) => "a boolean",
+            JsonValue::Number(_) => "a number",
+            JsonValue::String(_) => "a string",
+            JsonValue::Array(_) => "an array",
+            JsonValue::Object(_) => "an object",
+        }
+    }
+}
+
+// Compact output, no extra whitespace
+impl Display for JsonValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonValue::Null => f.write_str("null"),
+            JsonValue::Bool(b) => write!(f, "{}", b),
+            JsonValue::Number(n) => f.write_str(n),
+            JsonValue::String(s) => write_string(f, s),
+            JsonValue::Array(items) => {
+                f.write_char('[')?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_char(']')
+            }
+            JsonValue::Object(fields) => {
+                f.write_char('{')?;
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(f, name)?;
+                    write!(f, ":{}", value)?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut Formatter<'_>, s: &str) -> std::fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> JsonError {
+        JsonError::Syntax { position: self.pos, message: message.to_string() }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.bytes.get(self.pos) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), JsonError> {
+        if self.bytes.get(self.pos) == Some(&byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", byte as char)))
+        }
+    }
+
+    fn parse_literal(&mut self, literal: &str, value: JsonValue) -> Result<JsonValue, JsonError> {
+        if self.bytes[self.pos..].starts_with(literal.as_bytes()) {
+            self.pos += literal.len();
+            Ok(value)
+        } else {
+            Err(self.error("invalid literal"))
+        }
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<JsonValue, JsonError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("document is nested too deeply"));
+        }
+        self.skip_whitespace();
+        match self.bytes.get(self.pos) {
+            None => Err(self.error("unexpected end of input")),
+            Some(b'n') => self.parse_literal("null", JsonValue::Null),
+            Some(b't') => self.parse_literal("true", JsonValue::Bool(true)),
+            Some(b'f') => self.parse_literal("false", JsonValue::Bool(false)),
+            Some(b'"') => Ok(JsonValue::String(self.parse_string()?)),
+            Some(b'[') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                self.skip_whitespace();
+                if self.bytes.get(self.pos) == Some(&b']') {
+                    self.pos += 1;
+                    return Ok(JsonValue::Array(items));
+                }
+                loop {
+                    items.push(self.parse_value(depth + 1)?);
+                    self.skip_whitespace();
+                    match self.bytes.get(self.pos) {
+                        Some(b',') => self.pos += 1,
+                        Some(b']') => {
+                            self.pos += 1;
+                            return Ok(JsonValue::Array(items));
+                        }
+                        _ => return Err(self.error("expected ',' or ']'")),
+                    }
+                }
+            }
+            Some(b'{') => {
+                self.pos += 1;
+                let mut fields = Vec::new();
+                self.skip_whitespace();
+                if self.bytes.get(self.pos) == Some(&b'}') {
+                    self.pos += 1;
+                    return Ok(JsonValue::Object(fields));
+                }
+                loop {
+                    self.skip_whitespace();
+                    if self.bytes.get(self.pos) != Some(&b'"') {
+                        return Err(self.error("expected a field name"));
+                    }
+                    let name = self.parse_string()?;
+                    // `get` would only ever see the first of two fields with the same name
+                    if fields.iter().any(|(field, _)| *field == name) {
+                        return Err(self.error("duplicate field name"));
+                    }
+                    self.skip_whitespace();
+                    self.expect(b':')?;
+                    let value = self.parse_value(depth + 1)?;
+                    fields.push((name, value));
+                    self.skip_whitespace();
+                    match self.bytes.get(self.pos) {
+                        Some(b',') => self.pos += 1,
+                        Some(b'}') => {
+                            self.pos += 1;
+                            return Ok(JsonValue::Object(fields));
+                        }
+                        _ => return Err(self.error("expected ',' or '}'")),
+                    }
+                }
+            }
+            Some(b'-' | b'0'..=b'9') => self.parse_number(),
+            Some(_) => Err(self.error("unexpected character")),
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<JsonValue, JsonError> {
+        let start = self.pos;
+        if self.bytes.get(self.pos) == Some(&b'-') {
+            self.pos += 1;
+        }
+        let digits = |p: &mut Parser| {
+            let begin = p.pos;
+            while let Some(b'0'..=b'9') = p.bytes.get(p.pos) {
+                p.pos += 1;
+            }
+            p.pos > begin
+        };
+        if !digits(self) {
+            return Err(self.error("expected a digit"));
+        }
+        if self.bytes.get(self.pos) == Some(&b'.') {
+            self.pos += 1;
+            if !digits(self) {
+                return Err(self.error("expected a digit after '.'"));
+            }
+        }
+        if let Some(b'e' | b'E') = self.bytes.get(self.pos) {
+            self.pos += 1;
+            if let Some(b'+' | b'-') = self.bytes.get(self.pos) {
+                self.pos += 1;
+            }
+            if !digits(self) {
+                return Err(self.error("expected a digit in the exponent"));
+            }
+        }
+        // Only ASCII was consumed, so this cannot fail
+        Ok(JsonValue::Number(String::from_utf8_lossy(&self.bytes[start..self.pos]).into_owned()))
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, JsonError> {
+        let hex = self.bytes.get(self.pos..self.pos + 4).ok_or_else(|| self.error("truncated \\u escape"))?;
+        let code = std::str::from_utf8(hex)
+            .ok()
+            .and_then(|h| u32::from_str_radix(h, 16).ok())
+            .ok_or_else(|| self.error("invalid \\u escape"))?;
+        self.pos += 4;
+        Ok(code)
+    }
+
+    fn parse_string(&mut self) -> Result<String, JsonError> {
+        self.expect(b'"')?;
+        let mut out = String::new();
+        loop {
+            // Copy everything up to the next quote or escape in one go
+            let start = self.pos;
+            while let Some(&b) = self.bytes.get(self.pos) {
+                if b == b'"' || b == b'\\' || b < 0x20 {
+                    break;
+                }
+                self.pos += 1;
+            }
+            out.push_str(std::str::from_utf8(&self.bytes[start..self.pos]).map_err(|_| self.error("invalid UTF-8"))?);
+            match self.bytes.get(self.pos) {
+                None => return Err(self.error("unterminated string")),
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(out);
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    let escaped = *self.bytes.get(self.pos).ok_or_else(|| self.error("unterminated string"))?;
+                    self.pos += 1;
+                    match escaped {
+                        b'"' => out.push('"'),
+                        b'\\' => out.push('\\'),
+                        b'/' => out.push('/'),
+                        b'b' => out.push('\u{8}'),
+                        b'f' => out.push('\u{c}'),
+                        b'n' => out.push('\n'),
+                        b'r' => out.push('\r'),
+                        b't' => out.push('\t'),
+                        b'u' => {
+                            let mut code = self.parse_hex4()?;
+                            // UTF-16 surrogate pair
+                            if (0xd800..0xdc00).contains(&code) {
+                                if !self.bytes[self.pos..].starts_with(b"\\u") {
+                                    return Err(self.error("unpaired surrogate"));
+                                }
+                                self.pos += 2;
+                                let low = self.parse_hex4()?;
+                                if !(0xdc00..0xe000).contains(&low) {
+                                    return Err(self.error("unpaired surrogate"));
+                                }
+                                code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                            }
+                            out.push(char::from_u32(code).ok_or_else(|| self.error("invalid \\u escape"))?);
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    }
+                }
+                Some(_) => return Err(self.error("control character in string")),
+            }
+        }
+    }
+}
+
+// Keys that can be written to / read from JSON
+pub trait JsonKey: Sized {
+    fn to_json(&self) -> JsonValue;
+    fn from_json(value: &JsonValue) -> Result<Self, JsonError>;
+}
+
+fn expect_number(value: &JsonValue) -> Result<&str, JsonError> {
+    match value {
+        JsonValue::Number(n) => Ok(n),
+        other => Err(JsonError::Schema(format!("expected a number as key, found {}", other.type_name()))),
+    }
+}
+
+macro_rules! impl_json_key_for_integer {
+    ($($t:ty),*) => {
+        $(
+            impl JsonKey for $t {
+                fn to_json(&self) -> JsonValue {
+                    JsonValue::Number(self.to_string())
+                }
+                fn from_json(value: &JsonValue) -> Result<Self, JsonError> {
+                    let n = expect_number(value)?;
+                    n.parse().map_err(|_| JsonError::Schema(format!("{} is not a valid {}", n, stringify!($t))))
+                }
+            }
+        )*
+    };
+}
+
+impl_json_key_for_integer!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+// JSON has no NaN or infinity, those are written as the strings "NaN", "inf" and "-inf"
+macro_rules! impl_json_key_for_float {
+    ($($t:ty),*) => {
+        $(
+            impl JsonKey for $t {
+                fn to_json(&self) -> JsonValue {
+                    if self.is_finite() {
+                        JsonValue::Number(format!("{:?}", self))
+                    } else {
+                        JsonValue::String(self.to_string())
+                    }
+                }
+                fn from_json(value: &JsonValue) -> Result<Self, JsonError> {
+                    let n = match value {
+                        JsonValue::String(s) if s == "NaN" || s == "inf" || s == "-inf" => s.as_str(),
+                        other => expect_number(other)?,
+                    };
+                    n.parse().map_err(|_| JsonError::Schema(format!("{} is not a valid {}", n, stringify!($t))))
+                }
+            }
+        )*
+    };
+}
+
+impl_json_key_for_float!(f32, f64);
+
+impl JsonKey for TotalF64 {
+    fn to_json(&self) -> JsonValue {
+        self.0.to_json()
+    }
+    fn from_json(value: &JsonValue) -> Result<Self, JsonError> {
+        f64::from_json(value).map(TotalF64)
+    }
+}
+
+impl JsonKey for bool {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Bool(*self)
+    }
+    fn from_json(value: &JsonValue) -> Result<Self, JsonError> {
+        match value {
+            JsonValue::Bool(b) => Ok(*b),
+            other => Err(JsonError::Schema(format!("expected a boolean as key, found {}", other.type_name()))),
+        }
+    }
+}
+
+impl JsonKey for char {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::String(self.to_string())
+    }
+    fn from_json(value: &JsonValue) -> Result<Self, JsonError> {
+        let s = String::from_json(value)?;
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(JsonError::Schema(format!("expected a single character, found {:?}", s))),
+        }
+    }
+}
+
+impl JsonKey for String {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::String(self.clone())
+    }
+    fn from_json(value: &JsonValue) -> Result<Self, JsonError> {
+        match value {
+            JsonValue::String(s) => Ok(s.clone()),
+            other => Err(JsonError::Schema(format!("expected a string as key, found {}", other.type_name()))),
+        }
+    }
+}
+
+// JSON import / export, implemented by every tree type
+pub trait TreeJson: Sized {
+    fn to_json_nested(&self) -> String;
+    // export the whole structure as nested objects
+    fn to_json_sorted(&self) -> String;
+    // export the keys as a flat sorted array
+    fn from_json_nested(text: &str) -> Result<Self, JsonError>;
+    // import a nested structure, it is only accepted if it is a valid tree of this kind
+    fn from_json_sorted(text: &str) -> Result<Self, JsonError>;
+    // import a flat array of strictly increasing keys
+}
+
+// Read the keys of a flat sorted array, shared by all trees
+pub(crate) fn parse_sorted_keys<T: JsonKey + PartialOrd>(text: &str) -> Result<Vec<T>, JsonError> {
+    let items = match JsonValue::parse(text)? {
+        JsonValue::Array(items) => items,
+        other => return Err(JsonError::Schema(format!("expected an array, found {}", other.type_name()))),
+    };
+    let mut keys: Vec<T> = Vec::with_capacity(items.len());
+    for item in &items {
+        let key = T::from_json(item)?;
+        if let Some(last) = keys.last() {
+            if last.partial_cmp(&key) != Some(Ordering::Less) {
+                return Err(JsonError::Schema(format!("keys must be strictly increasing (item {})", keys.len())));
+            }
+        }
+        keys.push(key);
+    }
+    Ok(keys)
+}
+
+// Build a nested node object: {"key":..,<extra fields>,"left":..,"right":..}
+pub(crate) fn node_object(key: JsonValue, extra: (&str, JsonValue), left: JsonValue, right: JsonValue) -> JsonValue {
+    JsonValue::Object(vec![
+        ("key".to_string(), key),
+        (extra.0.to_string(), extra.1),
+        ("left".to_string(), left),
+        ("right".to_string(), right),
+    ])
+}
+
+// Required field of a nested node object
+pub(crate) fn field<'a>(node: &'a JsonValue, name: &str) -> Result<&'a JsonValue, JsonError> {
+    match node {
+        JsonValue::Object(_) => node.get(name).ok_or_else(|| JsonError::Schema(format!("node is missing the \"{}\" field", name))),
+        other => Err(JsonError::Schema(format!("expected a node object or null, found {}", other.type_name()))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn syntax_error(text: &str) -> String {
+        match JsonValue::parse(text) {
+            Err(JsonError::Syntax { message, .. }) => message,
+            other => panic!("{:?} should be a syntax error, got {:?}", text, other),
+        }
+    }
+
+    #[test]
+    fn values_survive_a_round_trip() {
+        let text = r#"{"a":[1,-2.5,3e10,true,false,null],"b":{"c":"quote \" backslash \\ tab \t é"},"d":[]}"#;
+        let value = JsonValue::parse(text).unwrap();
+        assert_eq!(value.to_string(), text);
+        assert_eq!(JsonValue::parse(&value.to_string()).unwrap(), value);
+        assert_eq!(JsonValue::parse(" [ 1 , 2 ] \n").unwrap(), JsonValue::Array(vec![
+            JsonValue::Number("1".to_string()),
+            JsonValue::Number("2".to_string()),
+        ]));
+    }
+
+    #[test]
+    fn escapes_and_surrogate_pairs() {
+        assert_eq!(JsonValue::parse(r#""\u0041\ud83d\ude00\/""#).unwrap(), JsonValue::String("A\u{1F600}/".to_string()));
+        let control = JsonValue::String("\u{1}".to_string());
+        assert_eq!(control.to_string(), r#""\u0001""#);
+        assert_eq!(JsonValue::parse(&control.to_string()).unwrap(), control);
+    }
+
+    #[test]
+    fn malformed_documents_are_rejected() {
+        assert_eq!(syntax_error(""), "unexpected end of input");
+        assert_eq!(syntax_error("[1,2"), "expected ',' or ']'");
+        assert_eq!(syntax_error("[1,]"), "unexpected character");
+        assert_eq!(syntax_error(r#"{"a" 1}"#), "expected ':'");
+        assert_eq!(syntax_error(r#"{"a":1,}"#), "expected a field name");
+        assert_eq!(syntax_error(r#"{"a":1 "b":2}"#), "expected ',' or '}'");
+        assert_eq!(syntax_error("nul"), "invalid literal");
+        assert_eq!(syntax_error("-"), "expected a digit");
+        assert_eq!(syntax_error("1."), "expected a digit after '.'");
+        assert_eq!(syntax_error("1e"), "expected a digit in the exponent");
+        assert_eq!(syntax_error("[1] 2"), "unexpected data after the end of the document");
+        assert_eq!(syntax_error(r#""abc"#), "unterminated string");
+        assert_eq!(syntax_error(r#""\x""#), "invalid escape");
+        assert_eq!(syntax_error(r#""\u12""#), "truncated \\u escape");
+        assert_eq!(syntax_error(r#""\u12g4""#), "invalid \\u escape");
+        assert_eq!(syntax_error(r#""\ud800""#), "unpaired surrogate");
+        assert_eq!(syntax_error("\"a\nb\""), "control character in string");
+        assert_eq!(syntax_error(&"[".repeat(MAX_DEPTH + 2)), "document is nested too deeply");
+    }
+
+    #[test]
+    fn an_error_points_at_the_problem() {
+        assert_eq!(JsonValue::parse("[1,]"), Err(JsonError::Syntax { position: 3, message: "unexpected character".to_string() }));
+    }
+
+    #[test]
+    fn duplicate_field_names_are_rejected() {
+        assert_eq!(syntax_error(r#"{"key":1,"left":null,"key":2}"#), "duplicate field name");
+        // the same name in different objects is fine
+        assert!(JsonValue::parse(r#"{"key":1,"left":{"key":0}}"#).is_ok());
+    }
+
+    #[test]
+    fn keys_of_every_type() {
+        assert_eq!(u8::from_json(&JsonValue::parse("255").unwrap()), Ok(255));
+        assert!(matches!(u8::from_json(&JsonValue::parse("256").unwrap()), Err(JsonError::Schema(_))));
+        assert!(matches!(i32::from_json(&JsonValue::parse("\"1\"").unwrap()), Err(JsonError::Schema(_))));
+        for float in [1.5, -0.0, f64::INFINITY, f64::NEG_INFINITY] {
+            assert_eq!(f64::from_json(&float.to_json()).unwrap().to_bits(), float.to_bits());
+        }
+        assert!(f64::from_json(&f64::NAN.to_json()).unwrap().is_nan());
+        assert!(matches!(f64::from_json(&JsonValue::String("1.5".to_string())), Err(JsonError::Schema(_))));
+        assert_eq!(bool::from_json(&true.to_json()), Ok(true));
+        assert_eq!(char::from_json(&'é'.to_json()), Ok('é'));
+        assert!(matches!(char::from_json(&JsonValue::String("ab".to_string())), Err(JsonError::Schema(_))));
+        assert_eq!(String::from_json(&"a\"b".to_string().to_json()), Ok("a\"b".to_string()));
+    }
+
+    #[test]
+    fn sorted_keys_must_be_strictly_increasing() {
+        assert_eq!(parse_sorted_keys::<i32>("[1,2,3]"), Ok(vec![1, 2, 3]));
+        assert_eq!(parse_sorted_keys::<i32>("[]"), Ok(vec![]));
+        assert_eq!(
+            parse_sorted_keys::<i32>("[1,2,2]"),
+            Err(JsonError::Schema("keys must be strictly increasing (item 2)".to_string()))
+        );
+        assert!(matches!(parse_sorted_keys::<i32>("[2,1]"), Err(JsonError::Schema(_))));
+        assert!(matches!(parse_sorted_keys::<i32>(r#"{"key":1}"#), Err(JsonError::Schema(_))));
+        assert!(matches!(parse_sorted_keys::<i32>("[1,"), Err(JsonError::Syntax { .. })));
+    }
+
+    #[test]
+    fn node_fields() {
+        let node = node_object(JsonValue::Number("1".to_string()), ("height", JsonValue::Number("1".to_string())), JsonValue::Null, JsonValue::Null);
+        assert_eq!(node.to_string(), r#"{"key":1,"height":1,"left":null,"right":null}"#);
+        assert_eq!(field(&node, "key"), Ok(&JsonValue::Number("1".to_string())));
+        assert_eq!(field(&node, "color"), Err(JsonError::Schema("node is missing the \"color\" field".to_string())));
+        assert!(matches!(field(&JsonValue::Array(vec![]), "key"), Err(JsonError::Schema(_))));
+    }
+}
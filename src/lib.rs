@@ -2,6 +2,15 @@ use crate::AVL::{AvlTree, AvlTreeNode};
 pub mod AVL;
 pub mod BST;
 pub mod RBTree;
+pub mod codec;
+pub mod fuzz;
+pub mod json;
+pub mod oplog;
+pub mod order;
+pub mod stats;
+pub mod total_float;
+pub mod trace;
+pub mod workload;
 
 pub fn run_avl_tree_example() {
     let mut avl_tree: AvlTreeNode<_> = AvlTree::generate_empty_tree();
@@ -23,14 +32,14 @@ pub fn run_avl_tree_example() {
     println!("Post Order Traverse: {:?}", avl_tree.post_order_traverse());
 
     println!(" ===== Delete {:?} from the tree =====", vec![2, 6, 8]);
-    avl_tree.delete_node(2);
-    let s = avl_tree.delete_node(6);
+    avl_tree.delete_node(&2);
+    let s = avl_tree.delete_node(&6);
     println!("The deleted Node(6) contains {:?}", s);
-    avl_tree.delete_node(8);
+    avl_tree.delete_node(&8);
 
     println!(" ===== Check the result of above operation ===== ");
     for i in vec![1, 2, 3, 4, 5, 6] {
-        println!("Does {} exist? {}", i, avl_tree.exist_or_not(i));
+        println!("Does {} exist? {}", i, avl_tree.exist_or_not(&i));
     };
     avl_tree.print_tree_diagram();
 
@@ -41,7 +50,7 @@ pub fn run_avl_tree_example() {
     avl_tree.print_tree_diagram();
 
     println!(" ===== Update node value 8 -> 9 ===== ");
-    avl_tree.update_node(8, 9);
+    avl_tree.update_node(&8, 9);
 
     println!(" ===== let's do a in order traversal in the end ===== ");
     println!("In Order Traverse: {:?}", avl_tree.in_order_traverse());
@@ -0,0 +1,326 @@
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+use crate::codec::{self, BinaryKey, CodecError};
+use crate::AVL::{AvlTree, AvlTreeNode};
+use crate::BST::{BinarySearchTree, Node};
+use crate::RBTree::RBTree;
+
+// Operation logs: the inserts, deletes and searches a program did, saved so they can be replayed
+// against any tree type (see `benches/replay.rs`)
+//
+// Text format, one operation per line, `#` starts a comment:
+//     insert 42
+//     search 42
+//     delete 42
+//
+// Binary format, in the envelope of codec.rs (magic "OPLG", version, count, size, ..., checksum):
+// every operation is a tag byte (0 insert, 1 delete, 2 search) followed by its key as a varint.
+
+pub const OPLOG_MAGIC: [u8; 4] = *b"OPLG";
+
+const TAG_INSERT: u8 = 0;
+const TAG_DELETE: u8 = 1;
+const TAG_SEARCH: u8 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    Insert(u64),
+    Delete(u64),
+    Search(u64),
+}
+
+impl Op {
+    pub fn name(self) -> &'static str {
+        match self {
+            Op::Insert(_) => "insert",
+            Op::Delete(_) => "delete",
+            Op::Search(_) => "search",
+        }
+    }
+
+    pub fn key(self) -> u64 {
+        match self {
+            Op::Insert(key) | Op::Delete(key) | Op::Search(key) => key,
+        }
+    }
+}
+
+impl Display for Op {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.name(), self.key())
+    }
+}
+
+pub fn to_text(ops: &[Op]) -> String {
+    let mut text = String::new();
+    for op in ops {
+        text.push_str(&op.to_string());
+        text.push('\n');
+    }
+    text
+}
+
+// Err names the first line that is not an operation
+pub fn from_text(text: &str) -> Result<Vec<Op>, String> {
+    let mut ops = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let key = match words.as_slice() {
+            [_, key] => key.parse::<u64>().map_err(|_| format!("line {}: '{}' is not a valid key", number + 1, key))?,
+            _ => return Err(format!("line {}: expected `<insert|delete|search> <key>`, found '{}'", number + 1, line)),
+        };
+        ops.push(match words[0] {
+            "insert" => Op::Insert(key),
+            "delete" => Op::Delete(key),
+            "search" => Op::Search(key),
+            other => return Err(format!("line {}: unknown operation '{}'", number + 1, other)),
+        });
+    }
+    Ok(ops)
+}
+
+// The header stores the number of operations as a u32, so a longer log panics instead of being cut short
+pub fn to_bytes(ops: &[Op]) -> Vec<u8> {
+    let count = u32::try_from(ops.len()).expect("a binary operation log holds at most u32::MAX operations");
+    let mut out = codec::write_header(&OPLOG_MAGIC, u64::KEY_TAG, count);
+    for op in ops {
+        out.push(match op {
+            Op::Insert(_) => TAG_INSERT,
+            Op::Delete(_) => TAG_DELETE,
+            Op::Search(_) => TAG_SEARCH,
+        });
+        codec::write_varint(&mut out, op.key());
+    }
+    codec::finish(&mut out);
+    out
+}
+
+pub fn from_bytes(bytes: &[u8]) -> Result<Vec<Op>, CodecError> {
+    let (count, mut input) = codec::open(bytes, &OPLOG_MAGIC, u64::KEY_TAG)?;
+    let mut ops = Vec::new();
+    for _ in 0..count {
+        let tag = input.read_u8()?;
+        let key = input.read_varint()?;
+        ops.push(match tag {
+            TAG_INSERT => Op::Insert(key),
+            TAG_DELETE => Op::Delete(key),
+            TAG_SEARCH => Op::Search(key),
+            other => return Err(CodecError::InvalidTag(other)),
+        });
+    }
+    if !input.is_empty() {
+        return Err(CodecError::TrailingBytes);
+    }
+    Ok(ops)
+}
+
+// Save as binary if `binary`, as text otherwise
+pub fn save<P: AsRef<Path>>(path: P, ops: &[Op], binary: bool) -> Result<(), String> {
+    let data = if binary { to_bytes(ops) } else { to_text(ops).into_bytes() };
+    std::fs::write(&path, data).map_err(|e| format!("Cannot write {}: {}", path.as_ref().display(), e))
+}
+
+// Load either format, a file starting with the binary magic is read as binary
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<Op>, String> {
+    let name = path.as_ref().display().to_string();
+    let bytes = std::fs::read(&path).map_err(|e| format!("Cannot read {}: {}", name, e))?;
+    if bytes.starts_with(&OPLOG_MAGIC) {
+        return from_bytes(&bytes).map_err(|e| format!("{}: {}", name, e));
+    }
+    let text = String::from_utf8(bytes).map_err(|_| format!("{}: neither a binary nor a text operation log", name))?;
+    from_text(&text).map_err(|e| format!("{}: {}", name, e))
+}
+
+// A tree an operation log can be replayed against
+pub trait Replay {
+    // run one operation, true if the insert / delete changed the tree or the search found the key
+    fn apply(&mut self, op: Op) -> bool;
+}
+
+impl Replay for AvlTreeNode<u64> {
+    fn apply(&mut self, op: Op) -> bool {
+        match op {
+            Op::Insert(key) => {
+                if self.exist_or_not(&key) {
+                    return false;
+                }
+                self.insert_node(key);
+                true
+            }
+            Op::Delete(key) => self.remove_node(&key),
+            Op::Search(key) => self.exist_or_not(&key),
+        }
+    }
+}
+
+impl Replay for RBTree<u64> {
+    fn apply(&mut self, op: Op) -> bool {
+        match op {
+            Op::Insert(key) => self.insert_node(key),
+            Op::Delete(key) => self.delete(key).is_ok(),
+            Op::Search(key) => self.exist_or_not(key),
+        }
+    }
+}
+
+impl Replay for Node<u64> {
+    fn apply(&mut self, op: Op) -> bool {
+        match op {
+            Op::Insert(key) => {
+                if self.search_node(key) {
+                    return false;
+                }
+                self.insert_node(key);
+                true
+            }
+            Op::Delete(key) => self.delete_node(key),
+            Op::Search(key) => self.search_node(key),
+        }
+    }
+}
+
+impl Replay for BTreeSet<u64> {
+    fn apply(&mut self, op: Op) -> bool {
+        match op {
+            Op::Insert(key) => self.insert(key),
+            Op::Delete(key) => self.remove(&key),
+            Op::Search(key) => self.contains(&key),
+        }
+    }
+}
+
+// Wraps a tree and logs every operation applied to it, to capture the access pattern of a real program
+pub struct Recorder<T> {
+    pub tree: T,
+    pub log: Vec<Op>,
+}
+
+impl<T: Replay> Recorder<T> {
+    pub fn new(tree: T) -> Self {
+        Recorder { tree, log: Vec::new() }
+    }
+
+    pub fn apply(&mut self, op: Op) -> bool {
+        self.log.push(op);
+        self.tree.apply(op)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RBTree::RBTree;
+
+    fn sample() -> Vec<Op> {
+        vec![Op::Insert(5), Op::Insert(3), Op::Search(3), Op::Delete(5), Op::Insert(u64::MAX), Op::Search(0)]
+    }
+
+    // A binary log with a hand-written payload and a valid checksum
+    fn sealed(count: u32, payload: &[u8]) -> Vec<u8> {
+        let mut out = codec::write_header(&OPLOG_MAGIC, u64::KEY_TAG, count);
+        out.extend_from_slice(payload);
+        codec::finish(&mut out);
+        out
+    }
+
+    #[test]
+    fn text_round_trips_and_skips_comments() {
+        let ops = sample();
+        assert_eq!(from_text(&to_text(&ops)).unwrap(), ops);
+        let text = "# a log\n\ninsert 1   # first\n  delete   1\nsearch 2#no space\n";
+        assert_eq!(from_text(text).unwrap(), vec![Op::Insert(1), Op::Delete(1), Op::Search(2)]);
+        assert!(from_text("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn text_errors_name_the_line() {
+        assert_eq!(from_text("insert 1\n\nupdate 2").unwrap_err(), "line 3: unknown operation 'update'");
+        assert_eq!(from_text("insert -1").unwrap_err(), "line 1: '-1' is not a valid key");
+        assert_eq!(
+            from_text("# header\ninsert").unwrap_err(),
+            "line 2: expected `<insert|delete|search> <key>`, found 'insert'"
+        );
+        assert!(from_text("insert 1 2").unwrap_err().starts_with("line 1: expected"));
+    }
+
+    #[test]
+    fn binary_round_trips() {
+        let ops = sample();
+        let bytes = to_bytes(&ops);
+        assert!(bytes.starts_with(&OPLOG_MAGIC));
+        assert_eq!(from_bytes(&bytes).unwrap(), ops);
+        assert!(from_bytes(&to_bytes(&[])).unwrap().is_empty());
+        // the tags are the ones documented at the top of the file
+        assert_eq!(from_bytes(&sealed(3, &[0, 7, 1, 7, 2, 7])).unwrap(), vec![Op::Insert(7), Op::Delete(7), Op::Search(7)]);
+    }
+
+    #[test]
+    fn binary_errors_are_reported() {
+        assert!(matches!(from_bytes(&sealed(1, &[3, 7])), Err(CodecError::InvalidTag(3))));
+        assert!(matches!(from_bytes(&sealed(1, &[0, 7, 0])), Err(CodecError::TrailingBytes)));
+        assert!(matches!(from_bytes(&sealed(2, &[0, 7])), Err(CodecError::Truncated)));
+
+        let bytes = to_bytes(&sample());
+        for len in 0..bytes.len() {
+            assert!(matches!(from_bytes(&bytes[..len]), Err(CodecError::Truncated)), "{} bytes", len);
+        }
+        let mut corrupted = bytes.clone();
+        let last_key = corrupted.len() - 5;
+        corrupted[last_key] ^= 1;
+        assert!(matches!(from_bytes(&corrupted), Err(CodecError::ChecksumMismatch { .. })));
+        let mut other_keys = codec::write_header(&OPLOG_MAGIC, u32::KEY_TAG, 0);
+        codec::finish(&mut other_keys);
+        assert!(matches!(from_bytes(&other_keys), Err(CodecError::KeyTypeMismatch { .. })));
+    }
+
+    #[test]
+    fn load_reads_both_formats() {
+        let dir = std::env::temp_dir();
+        let text = dir.join(format!("oplog-test-{}.txt", std::process::id()));
+        let binary = dir.join(format!("oplog-test-{}.bin", std::process::id()));
+        save(&text, &sample(), false).unwrap();
+        save(&binary, &sample(), true).unwrap();
+        assert_eq!(load(&text).unwrap(), sample());
+        assert_eq!(load(&binary).unwrap(), sample());
+        std::fs::write(&text, "insert x").unwrap();
+        assert!(load(&text).unwrap_err().ends_with("line 1: 'x' is not a valid key"));
+        std::fs::remove_file(&text).unwrap();
+        std::fs::remove_file(&binary).unwrap();
+        assert!(load(&text).unwrap_err().starts_with("Cannot read"));
+    }
+
+    #[test]
+    fn every_tree_replays_like_a_set() {
+        let mut ops = Vec::new();
+        let mut state = 7u64;
+        for _ in 0..2000 {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            let key = (state >> 33) % 64;
+            ops.push(match (state >> 20) % 3 {
+                0 => Op::Insert(key),
+                1 => Op::Delete(key),
+                _ => Op::Search(key),
+            });
+        }
+        let mut set = Recorder::new(BTreeSet::new());
+        let mut avl: AvlTreeNode<u64> = AvlTree::generate_empty_tree();
+        let mut rb = RBTree::new();
+        let mut bst: Node<u64> = None;
+        for &op in &ops {
+            let expected = set.apply(op);
+            assert_eq!(avl.apply(op), expected, "AVL, {}", op);
+            assert_eq!(rb.apply(op), expected, "red-black, {}", op);
+            assert_eq!(bst.apply(op), expected, "BST, {}", op);
+        }
+        assert_eq!(set.log, ops);
+        let keys: Vec<&u64> = set.tree.iter().collect();
+        assert_eq!(avl.in_order_traverse(), keys);
+    }
+}
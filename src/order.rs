@@ -0,0 +1,65 @@
+use std::cmp::Ordering;
+
+// How a tree orders its keys, so a tree can be sorted differently without wrapping the keys in a new type
+//
+// Any closure `Fn(&T, &T) -> Ordering` is a comparator, and so is a boxed one (`Box<dyn Fn(&T, &T) -> Ordering>`)
+// when the order is only known at runtime:
+//     let mut tree = RBTree::with_comparator(|a: &String, b: &String| a.len().cmp(&b.len()));
+//
+// Two keys the comparator calls Equal are the same key for the tree, e.g. "Apple" and "apple" with
+// `CaseInsensitive`. The comparator has to be a consistent total order or the trees cannot find their keys again.
+pub trait Comparator<T: ?Sized> {
+    fn compare(&self, a: &T, b: &T) -> Ordering;
+}
+
+impl<T: ?Sized, F: Fn(&T, &T) -> Ordering> Comparator<T> for F {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self(a, b)
+    }
+}
+
+// The order of the keys themselves, what the trees use when no comparator is given
+//
+// It needs a total order (`Ord`). Floats are not `Ord`, use `total_float::TotalF64` keys or the
+// `total_float::TotalOrder` comparator for them.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Natural;
+
+impl<T: Ord + ?Sized> Comparator<T> for Natural {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+// The opposite of another comparator: `Reverse(Natural)` keeps the largest key first
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Reverse<C>(pub C);
+
+impl<T: ?Sized, C: Comparator<T>> Comparator<T> for Reverse<C> {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self.0.compare(b, a)
+    }
+}
+
+// Orders keys by a field or any other value computed from them:
+//     ByKey(|user: &User| user.age)
+#[derive(Clone, Copy, Debug)]
+pub struct ByKey<F>(pub F);
+
+impl<T: ?Sized, K: Ord, F: Fn(&T) -> K> Comparator<T> for ByKey<F> {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        (self.0)(a).cmp(&(self.0)(b))
+    }
+}
+
+// Orders text ignoring upper and lower case, "apple" and "APPLE" are the same key
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CaseInsensitive;
+
+impl<T: AsRef<str> + ?Sized> Comparator<T> for CaseInsensitive {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        let a = a.as_ref().chars().flat_map(char::to_lowercase);
+        let b = b.as_ref().chars().flat_map(char::to_lowercase);
+        a.cmp(b)
+    }
+}
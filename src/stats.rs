@@ -0,0 +1,282 @@
+use std::cell::Cell;
+use std::fmt::{Display, Formatter};
+
+// Operation statistics: how much work the trees really do, independent of machine noise
+//
+// Counting is off by default. The counters belong to the thread, not to a tree: every tree used on this
+// thread while counting is enabled adds to the same counters, and trees used on other threads are not
+// counted here. To get the cost of one tree, count only its operations with `measure`, or `reset` before them.
+//
+// An operation that calls other public operations (e.g. update = delete + insert) is counted once, and each
+// nested call walks its own path, so it does not add to the path length of the operation around it.
+//
+//     stats::enable();
+//     for i in 0..1000 { avl_tree.insert_node(i); }
+//     println!("{}", stats::stats());
+//     stats::reset();
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OpStats {
+    // insert / delete / search / update calls
+    pub operations: u64,
+    // key comparisons
+    pub comparisons: u64,
+    // single rotations (a double rotation counts as two)
+    pub rotations: u64,
+    // red-black color changes
+    pub recolorings: u64,
+    // nodes allocated
+    pub allocations: u64,
+    // longest root-to-node path walked by a single operation
+    pub max_path_length: u64,
+}
+
+impl OpStats {
+    // Average of a counter over all operations
+    pub fn per_operation(&self, total: u64) -> f64 {
+        if self.operations == 0 {
+            0.0
+        } else {
+            total as f64 / self.operations as f64
+        }
+    }
+}
+
+impl Display for OpStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Operations:      {}", self.operations)?;
+        writeln!(f, "Comparisons:     {} ({:.2}/op)", self.comparisons, self.per_operation(self.comparisons))?;
+        writeln!(f, "Rotations:       {} ({:.2}/op)", self.rotations, self.per_operation(self.rotations))?;
+        writeln!(f, "Recolorings:     {} ({:.2}/op)", self.recolorings, self.per_operation(self.recolorings))?;
+        writeln!(f, "Allocations:     {} ({:.2}/op)", self.allocations, self.per_operation(self.allocations))?;
+        write!(f, "Max path length: {}", self.max_path_length)
+    }
+}
+
+thread_local! {
+    static ENABLED: Cell<bool> = const { Cell::new(false) };
+    static STATS: Cell<OpStats> = const { Cell::new(OpStats {
+        operations: 0,
+        comparisons: 0,
+        rotations: 0,
+        recolorings: 0,
+        allocations: 0,
+        max_path_length: 0,
+    }) };
+    // comparisons made by the current operation so far
+    static PATH_LENGTH: Cell<u64> = const { Cell::new(0) };
+    // > 0 while a public operation is running, so nested calls (e.g. update = delete + insert) count once
+    static DEPTH: Cell<u32> = const { Cell::new(0) };
+}
+
+// Start counting on this thread
+pub fn enable() {
+    ENABLED.with(|e| e.set(true));
+}
+
+// Stop counting on this thread, the counters keep their values
+pub fn disable() {
+    ENABLED.with(|e| e.set(false));
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.with(|e| e.get())
+}
+
+// The counters collected so far
+pub fn stats() -> OpStats {
+    STATS.with(|s| s.get())
+}
+
+// Set all counters back to zero
+pub fn reset() {
+    STATS.with(|s| s.set(OpStats::default()));
+    PATH_LENGTH.with(|p| p.set(0));
+}
+
+// Count only what `f` does, the previous state and counters are restored afterwards
+//
+// Like a nested operation (see OperationGuard), `f` walks its own path and the path of an operation around
+// the call goes on from where it was.
+pub fn measure<R, F: FnOnce() -> R>(f: F) -> (R, OpStats) {
+    let was_enabled = is_enabled();
+    let previous = stats();
+    let outer_path_length = PATH_LENGTH.with(|p| p.get());
+    reset();
+    enable();
+    let res = f();
+    let measured = stats();
+    STATS.with(|s| s.set(previous));
+    PATH_LENGTH.with(|p| p.set(outer_path_length));
+    if !was_enabled {
+        disable();
+    }
+    (res, measured)
+}
+
+fn update<F: FnOnce(&mut OpStats)>(f: F) {
+    if !is_enabled() {
+        return;
+    }
+    STATS.with(|s| {
+        let mut stats = s.get();
+        f(&mut stats);
+        s.set(stats);
+    });
+}
+
+// Called at the start of every public operation, the returned guard marks its end
+pub(crate) fn begin_operation() -> OperationGuard {
+    let outermost = DEPTH.with(|d| {
+        d.set(d.get() + 1);
+        d.get() == 1
+    });
+    if outermost {
+        update(|s| s.operations += 1);
+    }
+    OperationGuard { outer_path_length: PATH_LENGTH.with(|p| p.replace(0)) }
+}
+
+pub(crate) struct OperationGuard {
+    // path walked by the operation around this one, it goes on from there once this one is done
+    outer_path_length: u64,
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        DEPTH.with(|d| d.set(d.get() - 1));
+        PATH_LENGTH.with(|p| p.set(self.outer_path_length));
+    }
+}
+
+pub(crate) fn count_comparison() {
+    if !is_enabled() {
+        return;
+    }
+    let path_length = PATH_LENGTH.with(|p| {
+        p.set(p.get() + 1);
+        p.get()
+    });
+    update(|s| {
+        s.comparisons += 1;
+        s.max_path_length = s.max_path_length.max(path_length);
+    });
+}
+
+pub(crate) fn count_rotation() {
+    update(|s| s.rotations += 1);
+}
+
+pub(crate) fn count_recoloring() {
+    update(|s| s.recolorings += 1);
+}
+
+pub(crate) fn count_allocation() {
+    update(|s| s.allocations += 1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AVL::{AvlTree, AvlTreeNode};
+    use crate::BST::{BinarySearchTree, Node};
+    use crate::RBTree::RBTree;
+
+    #[test]
+    fn avl_insert_of_a_sorted_run() {
+        let mut tree: AvlTreeNode<i32> = AvlTree::generate_empty_tree();
+        // 1, then 2 on its right, then 3 makes it lean right and one left rotation puts 2 on top
+        let (_, counted) = measure(|| [1, 2, 3].iter().for_each(|&key| tree.insert_node(key)));
+        assert_eq!(counted, OpStats { operations: 3, comparisons: 3, rotations: 1, recolorings: 0, allocations: 3, max_path_length: 2 });
+        // 4 goes below 3, 5 below 4 and a rotation at 3 balances it again
+        let (_, counted) = measure(|| [4, 5].iter().for_each(|&key| tree.insert_node(key)));
+        assert_eq!(counted, OpStats { operations: 2, comparisons: 5, rotations: 1, recolorings: 0, allocations: 2, max_path_length: 3 });
+        // the tree is 2(1, 4(3, 5)), finding 5 compares with 2, 4 and 5
+        let (_, counted) = measure(|| tree.exist_or_not(&5));
+        assert_eq!(counted, OpStats { operations: 1, comparisons: 3, rotations: 0, recolorings: 0, allocations: 0, max_path_length: 3 });
+    }
+
+    #[test]
+    fn avl_double_rotation_counts_twice() {
+        let mut tree: AvlTreeNode<i32> = AvlTree::generate_empty_tree();
+        // 2 goes left of 3 and right of 1, a left-right case
+        let (_, counted) = measure(|| [3, 1, 2].iter().for_each(|&key| tree.insert_node(key)));
+        assert_eq!(counted.rotations, 2);
+        assert_eq!(counted.comparisons, 3);
+    }
+
+    #[test]
+    fn rb_insert_of_a_sorted_run() {
+        let mut tree = RBTree::<u32>::new();
+        // 3 has a red parent and no uncle: 2 turns black, 1 turns red and rotates left
+        let (_, counted) = measure(|| [1, 2, 3].iter().for_each(|&key| {
+            tree.insert_node(key);
+        }));
+        assert_eq!(counted, OpStats { operations: 3, comparisons: 3, rotations: 1, recolorings: 2, allocations: 3, max_path_length: 2 });
+        // 4 has a red uncle: parent and uncle turn black, the grandparent turns red and, being the root, black again
+        let (_, counted) = measure(|| tree.insert_node(4));
+        assert_eq!(counted, OpStats { operations: 1, comparisons: 2, rotations: 0, recolorings: 4, allocations: 1, max_path_length: 2 });
+    }
+
+    #[test]
+    fn bst_insert_of_a_sorted_run() {
+        let mut tree: Node<i32> = BinarySearchTree::gen_empty_tree();
+        let (_, counted) = measure(|| [1, 2, 3, 4].iter().for_each(|&key| tree.insert_node(key)));
+        assert_eq!(counted, OpStats { operations: 4, comparisons: 6, rotations: 0, recolorings: 0, allocations: 4, max_path_length: 3 });
+    }
+
+    #[test]
+    fn nested_operations_count_once() {
+        let mut tree = RBTree::<u32>::new();
+        for key in [1, 2, 3] {
+            tree.insert_node(key);
+        }
+        // two searches, a delete and an insert inside one update
+        let (_, counted) = measure(|| tree.replace_node(1, 4));
+        assert_eq!(counted.operations, 1);
+    }
+
+    #[test]
+    fn a_nested_operation_does_not_add_to_the_outer_path() {
+        let (_, counted) = measure(|| {
+            let _outer = begin_operation();
+            for _ in 0..3 {
+                count_comparison();
+            }
+            {
+                let _inner = begin_operation();
+                count_comparison();
+                count_comparison();
+            }
+            count_comparison();
+        });
+        assert_eq!(counted.operations, 1);
+        assert_eq!(counted.comparisons, 6);
+        // the outer operation walked 3 + 1 steps, the inner one 2
+        assert_eq!(counted.max_path_length, 4);
+    }
+
+    #[test]
+    fn a_nested_measure_keeps_the_outer_path() {
+        let (_, outer) = measure(|| {
+            let _op = begin_operation();
+            count_comparison();
+            count_comparison();
+            let (_, inner) = measure(count_comparison);
+            assert_eq!(inner.max_path_length, 1);
+            count_comparison();
+        });
+        assert_eq!(outer.comparisons, 3);
+        assert_eq!(outer.max_path_length, 3);
+    }
+
+    #[test]
+    fn nothing_is_counted_while_disabled() {
+        disable();
+        reset();
+        let mut tree: AvlTreeNode<i32> = AvlTree::generate_empty_tree();
+        tree.insert_node(1);
+        tree.insert_node(2);
+        assert_eq!(stats(), OpStats::default());
+    }
+}
@@ -0,0 +1,89 @@
+use std::cmp::Ordering;
+use std::fmt::{Debug, Display, Formatter};
+use std::num::ParseFloatError;
+use std::str::FromStr;
+
+use crate::order::Comparator;
+
+// Floats are only partially ordered (NaN is neither smaller, bigger nor equal to anything, and -0.0 == 0.0),
+// so the trees do not take plain f64 keys. There are two ways to store them:
+//     let mut a: AvlTreeNode<TotalF64> = AvlTree::generate_empty_tree();  // keys wrapped in TotalF64
+//     let mut b = AvlTreeBy::with_comparator(TotalOrder);                // plain f64 keys
+// Both use the same order, described below.
+
+// An f64 key with a total order, so floats can be used in trees that need `Ord`
+//
+// Uses the IEEE 754 totalOrder predicate (`f64::total_cmp`):
+//     -NaN < -inf < ... < -0.0 < +0.0 < ... < +inf < +NaN
+// so every value, NaN included, has exactly one place in a tree and -0.0 and +0.0 are different keys.
+#[derive(Clone, Copy, Default)]
+pub struct TotalF64(pub f64);
+
+impl TotalF64 {
+    pub fn value(self) -> f64 {
+        self.0
+    }
+}
+
+impl PartialEq for TotalF64 {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for TotalF64 {}
+
+impl PartialOrd for TotalF64 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TotalF64 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl From<f64> for TotalF64 {
+    fn from(value: f64) -> Self {
+        TotalF64(value)
+    }
+}
+
+impl FromStr for TotalF64 {
+    type Err = ParseFloatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(TotalF64)
+    }
+}
+
+// Printed like the plain float, so trees of TotalF64 look the same as trees of f64
+impl Debug for TotalF64 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&self.0, f)
+    }
+}
+
+impl Display for TotalF64 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+// Orders plain f64 / f32 keys like TotalF64 does, for trees made `with_comparator`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TotalOrder;
+
+impl Comparator<f64> for TotalOrder {
+    fn compare(&self, a: &f64, b: &f64) -> Ordering {
+        a.total_cmp(b)
+    }
+}
+
+impl Comparator<f32> for TotalOrder {
+    fn compare(&self, a: &f32, b: &f32) -> Ordering {
+        a.total_cmp(b)
+    }
+}
@@ -0,0 +1,164 @@
+use std::cell::{Cell, RefCell};
+use std::fmt::{Debug, Display, Formatter};
+use std::rc::Rc;
+
+// Step-by-step tracing of what the trees do internally (comparisons, rotations, recolorings, ...)
+//
+// A tracer is installed per thread, the trees report every step to it while it is installed.
+// When no tracer is installed the events are not even built, so normal use does not pay for it.
+//
+//     trace::set_tracer(|event| println!("{}", event));
+//     avl_tree.insert_node(5);
+//     trace::clear_tracer();
+//
+// `record_steps` also asks the trees to draw themselves after every rotation and recoloring.
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TraceEvent {
+    // The key being inserted / deleted / searched was compared with a node
+    Compare { key: String, node: String },
+    // Single rotations, `pivot` is the node that moves down
+    RotateLeft { pivot: String },
+    RotateRight { pivot: String },
+    // AVL double rotations (they are followed by the two single rotations they are made of)
+    RotateLR { pivot: String },
+    RotateRL { pivot: String },
+    // A red-black node changed its color
+    Recolor { node: String, color: String },
+    // A red-black fix-up case was chosen, `operation` is "insert" or "delete"
+    Case { operation: &'static str, number: u8, description: &'static str },
+    // The stored height of an AVL node changed
+    HeightUpdate { node: String, old: i32, new: i32 },
+}
+
+impl Display for TraceEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TraceEvent::Compare { key, node } => write!(f, "Compare {} with Node({})", key, node),
+            TraceEvent::RotateLeft { pivot } => write!(f, "Rotate left at Node({})", pivot),
+            TraceEvent::RotateRight { pivot } => write!(f, "Rotate right at Node({})", pivot),
+            TraceEvent::RotateLR { pivot } => write!(f, "Left-right double rotation at Node({})", pivot),
+            TraceEvent::RotateRL { pivot } => write!(f, "Right-left double rotation at Node({})", pivot),
+            TraceEvent::Recolor { node, color } => write!(f, "Recolor Node({}) to {}", node, color),
+            TraceEvent::Case { operation, number, description } => {
+                write!(f, "{} fix-up case {}: {}", operation, number, description)
+            }
+            TraceEvent::HeightUpdate { node, old, new } => write!(f, "Height of Node({}) {} -> {}", node, old, new),
+        }
+    }
+}
+
+// How a tree writes keys into trace events. Writing a key needs Debug, which the trees do not ask of their
+// keys for their operations, so an operation is told which one to use: `DebugKeys` where the key type has
+// Debug anyway, `HiddenKeys` (every key is `?`) everywhere else.
+pub(crate) trait KeyText<T: ?Sized> {
+    fn key_text(&self, key: &T) -> String;
+}
+
+pub(crate) struct DebugKeys;
+
+impl<T: Debug + ?Sized> KeyText<T> for DebugKeys {
+    fn key_text(&self, key: &T) -> String {
+        format!("{:?}", key)
+    }
+}
+
+pub(crate) struct HiddenKeys;
+
+impl<T: ?Sized> KeyText<T> for HiddenKeys {
+    fn key_text(&self, _key: &T) -> String {
+        "?".to_string()
+    }
+}
+
+// One event of `record_steps`, with the diagram of the tree right after it when the event finished a change
+// to the shape or the colors of the tree
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step {
+    pub event: TraceEvent,
+    pub diagram: Option<Vec<String>>,
+}
+
+type Tracer = Box<dyn FnMut(&TraceEvent)>;
+type Steps = Rc<RefCell<Vec<Step>>>;
+
+thread_local! {
+    static TRACING: Cell<bool> = const { Cell::new(false) };
+    static TRACER: RefCell<Option<Tracer>> = const { RefCell::new(None) };
+    // only set inside `record_steps`
+    static STEPS: RefCell<Option<Steps>> = const { RefCell::new(None) };
+}
+
+// Install a tracer for the current thread, it replaces the previous one
+pub fn set_tracer<F: FnMut(&TraceEvent) + 'static>(tracer: F) {
+    TRACER.with(|t| *t.borrow_mut() = Some(Box::new(tracer)));
+    TRACING.with(|t| t.set(true));
+}
+
+// Remove the tracer of the current thread
+pub fn clear_tracer() {
+    TRACING.with(|t| t.set(false));
+    TRACER.with(|t| *t.borrow_mut() = None);
+}
+
+pub fn is_tracing() -> bool {
+    TRACING.with(|t| t.get())
+}
+
+// Run `f` with `tracer` installed, the tracer and the steps that were there before are put back afterwards
+fn run_traced<R, F: FnOnce() -> R>(tracer: Tracer, steps: Option<Steps>, f: F) -> R {
+    let previous = TRACER.with(|t| t.borrow_mut().replace(tracer));
+    let was_tracing = TRACING.with(|t| t.replace(true));
+    let previous_steps = STEPS.with(|s| s.replace(steps));
+    let res = f();
+    TRACER.with(|t| *t.borrow_mut() = previous);
+    TRACING.with(|t| t.set(was_tracing));
+    STEPS.with(|s| *s.borrow_mut() = previous_steps);
+    res
+}
+
+// Run `f` and collect every event it produces, any installed tracer is put back afterwards
+pub fn record<R, F: FnOnce() -> R>(f: F) -> (R, Vec<TraceEvent>) {
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let sink = events.clone();
+    let res = run_traced(Box::new(move |event| sink.borrow_mut().push(event.clone())), None, f);
+    let events = events.borrow().clone();
+    (res, events)
+}
+
+// Like `record`, but every rotation and recoloring also comes with a diagram of the tree after it.
+// The red-black tree draws the whole tree, the AVL tree only the subtree that was rotated.
+pub fn record_steps<R, F: FnOnce() -> R>(f: F) -> (R, Vec<Step>) {
+    let steps: Steps = Rc::new(RefCell::new(Vec::new()));
+    let sink = steps.clone();
+    let tracer = move |event: &TraceEvent| sink.borrow_mut().push(Step { event: event.clone(), diagram: None });
+    let res = run_traced(Box::new(tracer), Some(steps.clone()), f);
+    let steps = steps.borrow().clone();
+    (res, steps)
+}
+
+// Report an event, the closure only runs when a tracer is installed
+pub(crate) fn emit<F: FnOnce() -> TraceEvent>(event: F) {
+    if !is_tracing() {
+        return;
+    }
+    TRACER.with(|t| {
+        // A tracer that itself uses a tree must not be called recursively
+        if let Ok(mut tracer) = t.try_borrow_mut() {
+            if let Some(tracer) = tracer.as_mut() {
+                tracer(&event());
+            }
+        }
+    });
+}
+
+// Attach a diagram to the last reported event, the closure only runs inside `record_steps`
+pub(crate) fn emit_diagram<F: FnOnce() -> Vec<String>>(diagram: F) {
+    let steps = STEPS.with(|s| s.borrow().clone());
+    if let Some(steps) = steps {
+        let lines = diagram();
+        if let Some(last) = steps.borrow_mut().last_mut() {
+            last.diagram = Some(lines);
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use std::str::FromStr;
+
+// Reproducible random workloads for the command line and the benchmarks, no external crates needed
+//
+// The same distribution, count and seed always give the same keys.
+//
+//     let keys = workload::generate_keys(Distribution::Zipf, 1000, 42);
+
+// SplitMix64: small and fast, good enough for workloads (not for anything security related)
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in 0..n, n must not be 0
+    pub fn below(&mut self, n: u64) -> u64 {
+        ((self.next_u64() as u128 * n as u128) >> 64) as u64
+    }
+
+    // Uniform in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // Fisher-Yates shuffle
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i as u64 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Distribution {
+    // every key equally likely
+    Uniform,
+    // 0, 1, 2, ...
+    Sequential,
+    // ..., 2, 1, 0
+    Reverse,
+    // a few hot keys: key k is drawn about 1/(k+1) times as often as key 0 (Zipf with s = 1)
+    Zipf,
+    // dense runs of keys around a few random centers
+    Clustered,
+}
+
+impl Distribution {
+    pub const ALL: [Distribution; 5] = [
+        Distribution::Uniform, Distribution::Sequential, Distribution::Reverse, Distribution::Zipf, Distribution::Clustered,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Distribution::Uniform => "uniform",
+            Distribution::Sequential => "sequential",
+            Distribution::Reverse => "reverse",
+            Distribution::Zipf => "zipf",
+            Distribution::Clustered => "clustered",
+        }
+    }
+}
+
+impl FromStr for Distribution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Distribution::ALL.iter().copied().find(|d| d.name() == s).ok_or_else(|| {
+            let names: Vec<&str> = Distribution::ALL.iter().map(|d| d.name()).collect();
+            format!("Unknown distribution '{}', expected one of {}", s, names.join(", "))
+        })
+    }
+}
+
+// keys of one cluster lie in center..center + CLUSTER_WIDTH
+const CLUSTER_WIDTH: u64 = 32;
+
+// An endless stream of keys following a distribution
+pub struct KeyGenerator {
+    distribution: Distribution,
+    rng: Rng,
+    universe: u64,
+    drawn: u64,
+    cluster_centers: Vec<u64>,
+}
+
+impl KeyGenerator {
+    // `count` is roughly how many keys will be drawn: sequential and reverse keys lie in 0..count,
+    // the other distributions draw from 0..10 * count so most keys are different
+    pub fn new(distribution: Distribution, count: usize, seed: u64) -> Self {
+        let count = count.max(1) as u64;
+        let universe = match distribution {
+            Distribution::Sequential | Distribution::Reverse => count,
+            _ => count.saturating_mul(10),
+        };
+        let mut rng = Rng::new(seed);
+        let cluster_centers = match distribution {
+            Distribution::Clustered => {
+                let clusters = ((count as f64).sqrt() as u64).max(1);
+                (0..clusters).map(|_| rng.below(universe)).collect()
+            }
+            _ => Vec::new(),
+        };
+        KeyGenerator { distribution, rng, universe, drawn: 0, cluster_centers }
+    }
+
+    pub fn next_key(&mut self) -> u64 {
+        let i = self.drawn;
+        self.drawn += 1;
+        match self.distribution {
+            Distribution::Uniform => self.rng.below(self.universe),
+            Distribution::Sequential => i % self.universe,
+            Distribution::Reverse => self.universe - 1 - i % self.universe,
+            Distribution::Zipf => {
+                // inverse of the continuous approximation of the distribution, P(key < x) = ln(x + 1) / ln(universe + 1)
+                let x = ((self.universe + 1) as f64).powf(self.rng.next_f64());
+                (x as u64).saturating_sub(1).min(self.universe - 1)
+            }
+            Distribution::Clustered => {
+                let center = self.cluster_centers[self.rng.below(self.cluster_centers.len() as u64) as usize];
+                center + self.rng.below(CLUSTER_WIDTH)
+            }
+        }
+    }
+}
+
+// `count` keys drawn from the distribution, keys can repeat except for sequential and reverse
+pub fn generate_keys(distribution: Distribution, count: usize, seed: u64) -> Vec<u64> {
+    let mut generator = KeyGenerator::new(distribution, count, seed);
+    (0..count).map(|_| generator.next_key()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_same_seed_gives_the_same_keys() {
+        for distribution in Distribution::ALL {
+            let keys = generate_keys(distribution, 500, 7);
+            assert_eq!(keys, generate_keys(distribution, 500, 7), "{}", distribution.name());
+            assert_eq!(keys.len(), 500);
+        }
+        for distribution in [Distribution::Uniform, Distribution::Zipf, Distribution::Clustered] {
+            assert_ne!(generate_keys(distribution, 500, 7), generate_keys(distribution, 500, 8), "{}", distribution.name());
+        }
+    }
+
+    #[test]
+    fn sequential_and_reverse_count_through_the_keys() {
+        assert_eq!(generate_keys(Distribution::Sequential, 5, 1), vec![0, 1, 2, 3, 4]);
+        assert_eq!(generate_keys(Distribution::Reverse, 5, 1), vec![4, 3, 2, 1, 0]);
+        // drawing more keys than asked for starts over
+        let mut generator = KeyGenerator::new(Distribution::Sequential, 2, 1);
+        let keys: Vec<u64> = (0..5).map(|_| generator.next_key()).collect();
+        assert_eq!(keys, vec![0, 1, 0, 1, 0]);
+    }
+
+    #[test]
+    fn keys_stay_in_the_universe() {
+        for distribution in [Distribution::Uniform, Distribution::Zipf] {
+            assert!(generate_keys(distribution, 1000, 3).iter().all(|&key| key < 10_000), "{}", distribution.name());
+        }
+        assert!(generate_keys(Distribution::Clustered, 1000, 3).iter().all(|&key| key < 10_000 + CLUSTER_WIDTH));
+    }
+
+    #[test]
+    fn zipf_keys_are_skewed_to_the_small_ones() {
+        let keys = generate_keys(Distribution::Zipf, 10_000, 5);
+        let zeros = keys.iter().filter(|&&key| key == 0).count();
+        let big = keys.iter().filter(|&&key| key == 1000).count();
+        assert!(zeros > 100 * big.max(1), "{} zeros, {} of 1000", zeros, big);
+    }
+
+    #[test]
+    fn clustered_keys_lie_close_to_few_centers() {
+        let mut keys = generate_keys(Distribution::Clustered, 400, 9);
+        keys.sort_unstable();
+        keys.dedup();
+        // 20 clusters of at most 32 keys each
+        assert!(keys.len() <= 20 * CLUSTER_WIDTH as usize);
+    }
+
+    #[test]
+    fn distributions_are_parsed_by_name() {
+        for distribution in Distribution::ALL {
+            assert_eq!(distribution.name().parse::<Distribution>(), Ok(distribution));
+        }
+        let error = "gauss".parse::<Distribution>().unwrap_err();
+        assert!(error.contains("'gauss'") && error.contains("uniform, sequential, reverse, zipf, clustered"), "{}", error);
+    }
+
+    #[test]
+    fn the_rng_stays_below_its_bound_and_shuffles_into_a_permutation() {
+        let mut rng = Rng::new(11);
+        assert!((0..1000).all(|_| rng.below(7) < 7));
+        assert!((0..1000).map(|_| rng.next_f64()).all(|x| (0.0..1.0).contains(&x)));
+        let mut items: Vec<u32> = (0..50).collect();
+        rng.shuffle(&mut items);
+        assert_ne!(items, (0..50).collect::<Vec<u32>>());
+        items.sort_unstable();
+        assert_eq!(items, (0..50).collect::<Vec<u32>>());
+    }
+}
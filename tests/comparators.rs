@@ -0,0 +1,206 @@
+use std::fmt::Debug;
+
+use ECE522_project::AVL::AvlTreeBy;
+use ECE522_project::BST::BstBy;
+use ECE522_project::RBTree::RBTree;
+use ECE522_project::order::{ByKey, CaseInsensitive, Comparator, Natural, Reverse};
+
+// The trees built with a comparator keep their keys in the order of the comparator, and two keys the
+// comparator calls Equal are one key: the second insert is refused and either of them finds and deletes it.
+
+// What every tree with a comparator has to offer
+trait Ordered<T> {
+    fn name(&self) -> &'static str;
+    // false if an equal key was already in the tree
+    fn insert(&mut self, key: T) -> bool;
+    fn delete(&mut self, key: T) -> bool;
+    fn contains(&mut self, key: T) -> bool;
+    fn keys(&mut self) -> Vec<T>;
+    fn valid(&self) -> bool;
+}
+
+impl<T: Clone + Debug, C: Comparator<T>> Ordered<T> for AvlTreeBy<T, C> {
+    fn name(&self) -> &'static str {
+        "AVL tree"
+    }
+
+    fn insert(&mut self, key: T) -> bool {
+        self.insert_node(key)
+    }
+
+    fn delete(&mut self, key: T) -> bool {
+        self.remove_node(&key)
+    }
+
+    fn contains(&mut self, key: T) -> bool {
+        self.exist_or_not(&key)
+    }
+
+    fn keys(&mut self) -> Vec<T> {
+        self.in_order_traverse().into_iter().cloned().collect()
+    }
+
+    fn valid(&self) -> bool {
+        self.validate_tree()
+    }
+}
+
+impl<T: Clone + Debug, C: Comparator<T>> Ordered<T> for RBTree<T, C> {
+    fn name(&self) -> &'static str {
+        "Red-Black tree"
+    }
+
+    fn insert(&mut self, key: T) -> bool {
+        self.insert_node(key)
+    }
+
+    fn delete(&mut self, key: T) -> bool {
+        RBTree::delete(self, key).is_ok()
+    }
+
+    fn contains(&mut self, key: T) -> bool {
+        self.exist_or_not(key)
+    }
+
+    fn keys(&mut self) -> Vec<T> {
+        self.print_in_order_traversal()
+    }
+
+    fn valid(&self) -> bool {
+        self.validate_tree()
+    }
+}
+
+impl<T: Copy + Debug, C: Comparator<T>> Ordered<T> for BstBy<T, C> {
+    fn name(&self) -> &'static str {
+        "Binary search tree"
+    }
+
+    fn insert(&mut self, key: T) -> bool {
+        self.insert_node(key)
+    }
+
+    fn delete(&mut self, key: T) -> bool {
+        self.delete_node(key)
+    }
+
+    fn contains(&mut self, key: T) -> bool {
+        self.search_node(key)
+    }
+
+    fn keys(&mut self) -> Vec<T> {
+        self.in_order_traverse()
+    }
+
+    fn valid(&self) -> bool {
+        true
+    }
+}
+
+fn all_trees<T, C>(cmp: C) -> Vec<Box<dyn Ordered<T>>>
+where
+    T: Copy + Debug + 'static,
+    C: Comparator<T> + Clone + 'static,
+{
+    vec![
+        Box::new(AvlTreeBy::with_comparator(cmp.clone())),
+        Box::new(RBTree::with_comparator(cmp.clone())),
+        Box::new(BstBy::with_comparator(cmp)),
+    ]
+}
+
+fn insert_all<T: Copy>(tree: &mut dyn Ordered<T>, keys: &[T]) {
+    for &key in keys {
+        assert!(tree.insert(key), "{}", tree.name());
+    }
+}
+
+#[test]
+fn reverse_keeps_the_largest_key_first() {
+    for mut tree in all_trees(Reverse(Natural)) {
+        let name = tree.name();
+        insert_all(tree.as_mut(), &[5, 1, 9, 3, 7, 2, 8]);
+        assert_eq!(tree.keys(), vec![9, 8, 7, 5, 3, 2, 1], "{}", name);
+        assert!(!tree.insert(7), "{}", name);
+        assert!(tree.contains(3) && !tree.contains(4), "{}", name);
+        assert!(tree.delete(9) && !tree.delete(9), "{}", name);
+        assert_eq!(tree.keys(), vec![8, 7, 5, 3, 2, 1], "{}", name);
+        assert!(tree.valid(), "{}", name);
+    }
+}
+
+#[test]
+fn reversed_ranges_run_from_the_largest_key() {
+    let mut avl = AvlTreeBy::with_comparator(Reverse(Natural));
+    let mut rb = RBTree::with_comparator(Reverse(Natural));
+    for key in 1..=9 {
+        avl.insert_node(key);
+        rb.insert_node(key);
+    }
+    assert_eq!(avl.range(&7, &3), vec![&7, &6, &5, &4, &3]);
+    assert_eq!(rb.range(7, 3), vec![7, 6, 5, 4, 3]);
+    assert!(avl.range(&3, &7).is_empty());
+}
+
+#[test]
+fn by_key_orders_and_finds_by_the_computed_value() {
+    let by_length = ByKey(|word: &&str| word.len());
+    for mut tree in all_trees(by_length) {
+        let name = tree.name();
+        insert_all(tree.as_mut(), &["banana", "fig", "apple", "kiwi"]);
+        assert_eq!(tree.keys(), vec!["fig", "kiwi", "apple", "banana"], "{}", name);
+        // "pear" is as long as "kiwi", so for this tree they are the same key
+        assert!(!tree.insert("pear"), "{}", name);
+        assert!(tree.contains("plum") && !tree.contains("grapefruit"), "{}", name);
+        assert!(tree.delete("date"), "{}", name);
+        assert_eq!(tree.keys(), vec!["fig", "apple", "banana"], "{}", name);
+        assert!(tree.valid(), "{}", name);
+    }
+}
+
+#[test]
+fn case_insensitive_treats_upper_and_lower_case_as_one_key() {
+    for mut tree in all_trees(CaseInsensitive) {
+        let name = tree.name();
+        assert!(tree.insert("A"), "{}", name);
+        assert!(!tree.insert("a"), "{}", name);
+        assert!(tree.contains("a"), "{}", name);
+        assert_eq!(tree.keys(), vec!["A"], "{}", name);
+        insert_all(tree.as_mut(), &["cherry", "Banana", "apple"]);
+        assert_eq!(tree.keys(), vec!["A", "apple", "Banana", "cherry"], "{}", name);
+        assert!(tree.contains("CHERRY") && !tree.contains("date"), "{}", name);
+        assert!(tree.delete("BANANA") && !tree.delete("banana"), "{}", name);
+        assert_eq!(tree.keys(), vec!["A", "apple", "cherry"], "{}", name);
+        assert!(tree.valid(), "{}", name);
+    }
+}
+
+#[test]
+fn case_insensitive_string_keys() {
+    let mut avl = AvlTreeBy::with_comparator(CaseInsensitive);
+    let mut rb = RBTree::with_comparator(CaseInsensitive);
+    let trees: [&mut dyn Ordered<String>; 2] = [&mut avl, &mut rb];
+    for tree in trees {
+        let name = tree.name();
+        for word in ["Pear", "apple", "FIG"] {
+            assert!(tree.insert(word.to_string()), "{}", name);
+        }
+        assert!(!tree.insert("APPLE".to_string()), "{}", name);
+        assert!(tree.contains("fig".to_string()), "{}", name);
+        assert_eq!(tree.keys(), vec!["apple", "FIG", "Pear"], "{}", name);
+        assert!(tree.delete("pear".to_string()), "{}", name);
+        assert_eq!(tree.keys(), vec!["apple", "FIG"], "{}", name);
+        assert!(tree.valid(), "{}", name);
+    }
+}
+
+#[test]
+fn closures_are_comparators() {
+    let mut rb = RBTree::with_comparator(|a: &i32, b: &i32| (a % 10).cmp(&(b % 10)));
+    for key in [13, 21, 7, 45] {
+        assert!(rb.insert_node(key));
+    }
+    assert!(!rb.insert_node(31));
+    assert_eq!(rb.print_in_order_traversal(), vec![21, 13, 45, 7]);
+    assert!(rb.validate_tree());
+}
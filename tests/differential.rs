@@ -0,0 +1,351 @@
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+use std::panic::{self, AssertUnwindSafe};
+
+use ECE522_project::AVL::{AvlTree, AvlTreeNode};
+use ECE522_project::BST::{BinarySearchTree, Node};
+use ECE522_project::RBTree::RBTree;
+use ECE522_project::workload::Rng;
+
+// Differential tests: the same random sequence of inserts, deletes, updates and searches is applied to
+// every tree and to a BTreeSet model. After every step each tree must hold exactly the keys of the model,
+// agree with it on every answer and still be a valid tree of its kind.
+//
+// A failing sequence is shrunk to a minimal one before it is reported, together with the seed:
+//     DIFFERENTIAL_CASES=5000 cargo test --test differential
+// runs more random sequences than the default.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Insert(i32),
+    Delete(i32),
+    Update(i32, i32),
+    Search(i32),
+}
+
+impl Display for Op {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Op::Insert(key) => write!(f, "insert {}", key),
+            Op::Delete(key) => write!(f, "delete {}", key),
+            Op::Update(old, new) => write!(f, "update {} {}", old, new),
+            Op::Search(key) => write!(f, "search {}", key),
+        }
+    }
+}
+
+// What every tree under test has to offer, answers are None when the tree's API does not report them
+trait Subject {
+    fn name(&self) -> &'static str;
+    fn insert(&mut self, key: i32) -> Option<bool>;
+    fn delete(&mut self, key: i32) -> Option<bool>;
+    fn update(&mut self, old: i32, new: i32);
+    fn contains(&mut self, key: i32) -> bool;
+    fn keys(&mut self) -> Vec<i32>;
+    fn count(&mut self) -> usize;
+    fn valid(&self) -> bool;
+}
+
+impl Subject for AvlTreeNode<i32> {
+    fn name(&self) -> &'static str {
+        "AVL tree"
+    }
+
+    fn insert(&mut self, key: i32) -> Option<bool> {
+        self.insert_node(key);
+        None
+    }
+
+    fn delete(&mut self, key: i32) -> Option<bool> {
+        Some(self.delete_node(&key).is_some())
+    }
+
+    fn update(&mut self, old: i32, new: i32) {
+        self.update_node(&old, new);
+    }
+
+    fn contains(&mut self, key: i32) -> bool {
+        self.exist_or_not(&key)
+    }
+
+    fn keys(&mut self) -> Vec<i32> {
+        self.in_order_traverse().into_iter().copied().collect()
+    }
+
+    fn count(&mut self) -> usize {
+        self.total_number_elements() as usize
+    }
+
+    fn valid(&self) -> bool {
+        self.validate_tree()
+    }
+}
+
+impl Subject for RBTree<i32> {
+    fn name(&self) -> &'static str {
+        "red-black tree"
+    }
+
+    fn insert(&mut self, key: i32) -> Option<bool> {
+        Some(self.insert_node(key))
+    }
+
+    fn delete(&mut self, key: i32) -> Option<bool> {
+        Some(RBTree::delete(self, key).is_ok())
+    }
+
+    fn update(&mut self, old: i32, new: i32) {
+        self.update_node(old, new);
+    }
+
+    fn contains(&mut self, key: i32) -> bool {
+        self.exist_or_not(key)
+    }
+
+    fn keys(&mut self) -> Vec<i32> {
+        self.print_in_order_traversal()
+    }
+
+    fn count(&mut self) -> usize {
+        self.total_number_elements() as usize
+    }
+
+    fn valid(&self) -> bool {
+        self.validate_tree()
+    }
+}
+
+impl Subject for Node<i32> {
+    fn name(&self) -> &'static str {
+        "binary search tree"
+    }
+
+    fn insert(&mut self, key: i32) -> Option<bool> {
+        self.insert_node(key);
+        None
+    }
+
+    fn delete(&mut self, key: i32) -> Option<bool> {
+        Some(self.delete_node(key))
+    }
+
+    fn update(&mut self, old: i32, new: i32) {
+        // the plain binary search tree has no update, do what update_node does in the other trees
+        if old != new && self.search_node(old) && !self.search_node(new) {
+            self.delete_node(old);
+            self.insert_node(new);
+        }
+    }
+
+    fn contains(&mut self, key: i32) -> bool {
+        let found = self.search_node(key);
+        assert_eq!(found, self.search_node_optimize(key), "search_node and search_node_optimize disagree on {}", key);
+        found
+    }
+
+    fn keys(&mut self) -> Vec<i32> {
+        self.in_order_traverse()
+    }
+
+    fn count(&mut self) -> usize {
+        self.in_order_traverse().len()
+    }
+
+    fn valid(&self) -> bool {
+        // no balance to check, only the search order
+        self.in_order_traverse().windows(2).all(|w| w[0] < w[1])
+    }
+}
+
+// Apply one operation to the model, returns what the trees should answer
+fn apply_to_model(model: &mut BTreeSet<i32>, op: Op) -> Option<bool> {
+    match op {
+        Op::Insert(key) => Some(model.insert(key)),
+        Op::Delete(key) => Some(model.remove(&key)),
+        Op::Update(old, new) => {
+            if old != new && model.contains(&old) && !model.contains(&new) {
+                model.remove(&old);
+                model.insert(new);
+            }
+            None
+        }
+        Op::Search(key) => Some(model.contains(&key)),
+    }
+}
+
+fn apply_to_tree<T: Subject>(tree: &mut T, op: Op) -> Option<bool> {
+    match op {
+        Op::Insert(key) => tree.insert(key),
+        Op::Delete(key) => tree.delete(key),
+        Op::Update(old, new) => {
+            tree.update(old, new);
+            None
+        }
+        Op::Search(key) => Some(tree.contains(key)),
+    }
+}
+
+// Run `ops` on one tree and the model, Err describes the first step where they differ
+fn check_tree<T: Subject>(mut tree: T, ops: &[Op]) -> Result<(), String> {
+    let mut model = BTreeSet::new();
+    for (step, &op) in ops.iter().enumerate() {
+        let expected = apply_to_model(&mut model, op);
+        let answer = panic::catch_unwind(AssertUnwindSafe(|| apply_to_tree(&mut tree, op)))
+            .map_err(|_| format!("{}: step {} `{}` panicked", tree.name(), step + 1, op))?;
+        let name = tree.name();
+        let fail = |reason: String| Err(format!("{}: step {} `{}`: {}", name, step + 1, op, reason));
+        if let (Some(answer), Some(expected)) = (answer, expected) {
+            if answer != expected {
+                return fail(format!("answered {}, expected {}", answer, expected));
+            }
+        }
+        let keys = tree.keys();
+        let expected_keys: Vec<i32> = model.iter().copied().collect();
+        if keys != expected_keys {
+            return fail(format!("holds {:?}, expected {:?}", keys, expected_keys));
+        }
+        let count = tree.count();
+        if count != model.len() {
+            return fail(format!("counts {} elements, expected {}", count, model.len()));
+        }
+        if !tree.valid() {
+            return fail("the tree is not valid".to_string());
+        }
+    }
+    Ok(())
+}
+
+fn check_all(ops: &[Op]) -> Result<(), String> {
+    check_tree::<AvlTreeNode<i32>>(AvlTree::generate_empty_tree(), ops)?;
+    check_tree(RBTree::<i32>::new(), ops)?;
+    check_tree::<Node<i32>>(Node::gen_empty_tree(), ops)
+}
+
+// Smallest sequence we can find that still fails `check`: first whole chunks are dropped (halves, quarters,
+// ... single operations), then keys are made smaller, until neither changes anything
+fn shrink(ops: &[Op], check: &dyn Fn(&[Op]) -> Result<(), String>) -> Vec<Op> {
+    let mut ops = ops.to_vec();
+    loop {
+        let mut progress = false;
+        let mut chunk = ops.len() / 2;
+        while chunk > 0 {
+            let mut start = 0;
+            while start < ops.len() {
+                let mut candidate = ops.clone();
+                candidate.drain(start..(start + chunk).min(ops.len()));
+                if check(&candidate).is_err() {
+                    ops = candidate;
+                    progress = true;
+                } else {
+                    start += chunk;
+                }
+            }
+            chunk /= 2;
+        }
+        for i in 0..ops.len() {
+            for smaller in smaller_ops(ops[i]) {
+                let mut candidate = ops.clone();
+                candidate[i] = smaller;
+                if check(&candidate).is_err() {
+                    ops = candidate;
+                    progress = true;
+                    break;
+                }
+            }
+        }
+        if !progress {
+            return ops;
+        }
+    }
+}
+
+// The same operation with smaller keys, the smallest first
+fn smaller_ops(op: Op) -> Vec<Op> {
+    let smaller = |key: i32| -> Vec<i32> {
+        let mut keys = vec![0, key / 2, key - 1];
+        keys.retain(|&k| k >= 0 && k < key);
+        keys.dedup();
+        keys
+    };
+    match op {
+        Op::Insert(key) => smaller(key).into_iter().map(Op::Insert).collect(),
+        Op::Delete(key) => smaller(key).into_iter().map(Op::Delete).collect(),
+        Op::Search(key) => smaller(key).into_iter().map(Op::Search).collect(),
+        Op::Update(old, new) => {
+            let mut ops: Vec<Op> = smaller(old).into_iter().map(|old| Op::Update(old, new)).collect();
+            ops.extend(smaller(new).into_iter().map(|new| Op::Update(old, new)));
+            ops
+        }
+    }
+}
+
+// A random sequence, keys below `key_range` so inserts, deletes and updates hit existing keys often
+fn random_ops(seed: u64, length: usize, key_range: u64) -> Vec<Op> {
+    let mut rng = Rng::new(seed);
+    let key = move |rng: &mut Rng| rng.below(key_range) as i32;
+    (0..length).map(|_| match rng.below(10) {
+        0..=3 => Op::Insert(key(&mut rng)),
+        4..=5 => Op::Delete(key(&mut rng)),
+        6..=7 => Op::Update(key(&mut rng), key(&mut rng)),
+        _ => Op::Search(key(&mut rng)),
+    }).collect()
+}
+
+fn report(seed: u64, ops: &[Op]) -> Result<(), String> {
+    if let Err(first) = check_all(ops) {
+        let minimal = shrink(ops, &check_all);
+        let reason = check_all(&minimal).unwrap_err();
+        let steps: Vec<String> = minimal.iter().map(|op| format!("    {}", op)).collect();
+        return Err(format!(
+            "seed {}: {}\nshrunk from {} to {} operations: {}\n{}",
+            seed, first, ops.len(), minimal.len(), reason, steps.join("\n")
+        ));
+    }
+    Ok(())
+}
+
+fn cases() -> u64 {
+    std::env::var("DIFFERENTIAL_CASES").ok().and_then(|n| n.parse().ok()).unwrap_or(300)
+}
+
+#[test]
+fn random_sequences_match_btreeset() {
+    for seed in 0..cases() {
+        // small key ranges make lots of duplicates and misses, big ones make deeper trees
+        let key_range = [8, 32, 256][seed as usize % 3];
+        if let Err(report) = report(seed, &random_ops(seed, 200, key_range)) {
+            panic!("{}", report);
+        }
+    }
+}
+
+#[test]
+fn sorted_sequences_match_btreeset() {
+    // ascending and descending inserts then deletes, every rotation case in a row
+    let up: Vec<i32> = (0..300).collect();
+    let down: Vec<i32> = (0..300).rev().collect();
+    for (seed, (inserts, deletes)) in [(&up, &up), (&up, &down), (&down, &up), (&down, &down)].iter().enumerate() {
+        let mut ops: Vec<Op> = inserts.iter().map(|&key| Op::Insert(key)).collect();
+        ops.extend(deletes.iter().map(|&key| Op::Delete(key)));
+        if let Err(report) = report(seed as u64, &ops) {
+            panic!("{}", report);
+        }
+    }
+}
+
+#[test]
+fn shrinking_finds_a_minimal_sequence() {
+    // a made-up bug: deleting a key after 17 was inserted fails
+    let check = |ops: &[Op]| -> Result<(), String> {
+        let inserted = ops.iter().position(|&op| op == Op::Insert(17));
+        match inserted {
+            Some(i) if ops[i..].iter().any(|op| matches!(op, Op::Delete(_))) => Err("bug".to_string()),
+            _ => Ok(()),
+        }
+    };
+    let mut ops = random_ops(7, 100, 64);
+    ops.insert(30, Op::Insert(17));
+    ops.push(Op::Delete(40));
+    assert!(check(&ops).is_err());
+    assert_eq!(shrink(&ops, &check), vec![Op::Insert(17), Op::Delete(0)]);
+}
@@ -0,0 +1,334 @@
+use std::collections::BTreeSet;
+
+use ECE522_project::AVL::{AvlTree, AvlTreeBy, AvlTreeNode};
+use ECE522_project::BST::{BinarySearchTree, BstBy, Node};
+use ECE522_project::RBTree::RBTree;
+use ECE522_project::total_float::{TotalF64, TotalOrder};
+use ECE522_project::workload::Rng;
+
+// Float keys are kept in IEEE 754 total order, either wrapped in TotalF64 or as plain f64 with the
+// TotalOrder comparator:
+//     -NaN < -inf < ... < -0.0 < +0.0 < ... < +inf < +NaN
+// Every NaN is a key like any other that can be found and deleted again, and -0.0 and +0.0 are two keys.
+// Keys are compared by their bits, so a test can tell -0.0 from +0.0 and one NaN from another.
+
+// What every float tree under test has to offer
+trait FloatTree {
+    fn name(&self) -> &'static str;
+    // false if the key was already in the tree
+    fn insert(&mut self, key: f64) -> bool;
+    fn delete(&mut self, key: f64) -> bool;
+    fn contains(&mut self, key: f64) -> bool;
+    fn keys(&mut self) -> Vec<f64>;
+    fn valid(&self) -> bool;
+}
+
+impl FloatTree for AvlTreeNode<TotalF64> {
+    fn name(&self) -> &'static str {
+        "AVL tree of TotalF64"
+    }
+
+    fn insert(&mut self, key: f64) -> bool {
+        if self.exist_or_not(&TotalF64(key)) {
+            return false;
+        }
+        self.insert_node(TotalF64(key));
+        true
+    }
+
+    fn delete(&mut self, key: f64) -> bool {
+        self.remove_node(&TotalF64(key))
+    }
+
+    fn contains(&mut self, key: f64) -> bool {
+        self.exist_or_not(&TotalF64(key))
+    }
+
+    fn keys(&mut self) -> Vec<f64> {
+        self.in_order_traverse().into_iter().map(|key| key.value()).collect()
+    }
+
+    fn valid(&self) -> bool {
+        self.validate_tree()
+    }
+}
+
+impl FloatTree for RBTree<TotalF64> {
+    fn name(&self) -> &'static str {
+        "Red-Black tree of TotalF64"
+    }
+
+    fn insert(&mut self, key: f64) -> bool {
+        self.insert_node(TotalF64(key))
+    }
+
+    fn delete(&mut self, key: f64) -> bool {
+        RBTree::delete(self, TotalF64(key)).is_ok()
+    }
+
+    fn contains(&mut self, key: f64) -> bool {
+        self.exist_or_not(TotalF64(key))
+    }
+
+    fn keys(&mut self) -> Vec<f64> {
+        self.print_in_order_traversal().into_iter().map(TotalF64::value).collect()
+    }
+
+    fn valid(&self) -> bool {
+        self.validate_tree()
+    }
+}
+
+impl FloatTree for Node<TotalF64> {
+    fn name(&self) -> &'static str {
+        "binary search tree of TotalF64"
+    }
+
+    fn insert(&mut self, key: f64) -> bool {
+        if self.search_node(TotalF64(key)) {
+            return false;
+        }
+        self.insert_node(TotalF64(key));
+        true
+    }
+
+    fn delete(&mut self, key: f64) -> bool {
+        self.delete_node(TotalF64(key))
+    }
+
+    fn contains(&mut self, key: f64) -> bool {
+        self.search_node(TotalF64(key))
+    }
+
+    fn keys(&mut self) -> Vec<f64> {
+        self.in_order_traverse().into_iter().map(TotalF64::value).collect()
+    }
+
+    // the binary search tree has no validator, its in-order traversal has to be sorted
+    fn valid(&self) -> bool {
+        self.in_order_traverse().windows(2).all(|w| w[0] < w[1])
+    }
+}
+
+impl FloatTree for AvlTreeBy<f64, TotalOrder> {
+    fn name(&self) -> &'static str {
+        "AVL tree of f64 with TotalOrder"
+    }
+
+    fn insert(&mut self, key: f64) -> bool {
+        self.insert_node(key)
+    }
+
+    fn delete(&mut self, key: f64) -> bool {
+        self.remove_node(&key)
+    }
+
+    fn contains(&mut self, key: f64) -> bool {
+        self.exist_or_not(&key)
+    }
+
+    fn keys(&mut self) -> Vec<f64> {
+        self.in_order_traverse().into_iter().copied().collect()
+    }
+
+    fn valid(&self) -> bool {
+        self.validate_tree()
+    }
+}
+
+impl FloatTree for RBTree<f64, TotalOrder> {
+    fn name(&self) -> &'static str {
+        "Red-Black tree of f64 with TotalOrder"
+    }
+
+    fn insert(&mut self, key: f64) -> bool {
+        self.insert_node(key)
+    }
+
+    fn delete(&mut self, key: f64) -> bool {
+        RBTree::delete(self, key).is_ok()
+    }
+
+    fn contains(&mut self, key: f64) -> bool {
+        self.exist_or_not(key)
+    }
+
+    fn keys(&mut self) -> Vec<f64> {
+        self.print_in_order_traversal()
+    }
+
+    fn valid(&self) -> bool {
+        self.validate_tree()
+    }
+}
+
+impl FloatTree for BstBy<f64, TotalOrder> {
+    fn name(&self) -> &'static str {
+        "binary search tree of f64 with TotalOrder"
+    }
+
+    fn insert(&mut self, key: f64) -> bool {
+        self.insert_node(key)
+    }
+
+    fn delete(&mut self, key: f64) -> bool {
+        self.delete_node(key)
+    }
+
+    fn contains(&mut self, key: f64) -> bool {
+        self.search_node(key)
+    }
+
+    fn keys(&mut self) -> Vec<f64> {
+        self.in_order_traverse()
+    }
+
+    fn valid(&self) -> bool {
+        self.in_order_traverse().windows(2).all(|w| w[0].total_cmp(&w[1]).is_lt())
+    }
+}
+
+fn all_trees() -> Vec<Box<dyn FloatTree>> {
+    let avl: AvlTreeNode<TotalF64> = AvlTree::generate_empty_tree();
+    let bst: Node<TotalF64> = BinarySearchTree::gen_empty_tree();
+    vec![
+        Box::new(avl),
+        Box::new(RBTree::<TotalF64>::new()),
+        Box::new(bst),
+        Box::new(AvlTreeBy::with_comparator(TotalOrder)),
+        Box::new(RBTree::with_comparator(TotalOrder)),
+        Box::new(BstBy::with_comparator(TotalOrder)),
+    ]
+}
+
+fn bits(keys: &[f64]) -> Vec<u64> {
+    keys.iter().map(|key| key.to_bits()).collect()
+}
+
+// A quiet NaN with another payload than f64::NAN
+fn other_nan() -> f64 {
+    f64::from_bits(f64::NAN.to_bits() | 1)
+}
+
+// Every special value, in IEEE total order
+fn special_values() -> Vec<f64> {
+    vec![
+        -f64::NAN,
+        f64::NEG_INFINITY,
+        f64::MIN,
+        -1.5,
+        -f64::MIN_POSITIVE,
+        -f64::from_bits(1),
+        -0.0,
+        0.0,
+        f64::from_bits(1),
+        f64::MIN_POSITIVE,
+        1.5,
+        f64::MAX,
+        f64::INFINITY,
+        f64::NAN,
+        other_nan(),
+    ]
+}
+
+#[test]
+fn special_values_are_kept_in_total_order() {
+    let expected = special_values();
+    for seed in 0..20 {
+        let mut keys = expected.clone();
+        Rng::new(seed).shuffle(&mut keys);
+        for mut tree in all_trees() {
+            for &key in &keys {
+                assert!(tree.insert(key), "{}: inserting {:?} failed", tree.name(), key);
+            }
+            assert_eq!(bits(&tree.keys()), bits(&expected), "{}: wrong order for seed {}", tree.name(), seed);
+            assert!(tree.valid(), "{}: invalid tree for seed {}", tree.name(), seed);
+            for &key in &keys {
+                assert!(tree.contains(key), "{}: {:?} not found", tree.name(), key);
+                assert!(!tree.insert(key), "{}: {:?} inserted twice", tree.name(), key);
+            }
+        }
+    }
+}
+
+#[test]
+fn nan_is_found_and_deleted_like_any_other_key() {
+    for mut tree in all_trees() {
+        for key in [1.0, f64::NAN, 2.0, f64::INFINITY] {
+            tree.insert(key);
+        }
+        assert!(tree.contains(f64::NAN), "{}", tree.name());
+        assert!(!tree.insert(f64::NAN), "{}: NaN inserted twice", tree.name());
+        // a NaN with another sign or payload is another key
+        assert!(!tree.contains(-f64::NAN), "{}", tree.name());
+        assert!(!tree.contains(other_nan()), "{}", tree.name());
+        assert!(!tree.delete(other_nan()), "{}", tree.name());
+
+        assert!(tree.delete(f64::NAN), "{}: NaN not deleted", tree.name());
+        assert!(!tree.contains(f64::NAN), "{}", tree.name());
+        assert!(!tree.delete(f64::NAN), "{}: NaN deleted twice", tree.name());
+        assert_eq!(tree.keys(), vec![1.0, 2.0, f64::INFINITY], "{}", tree.name());
+        assert!(tree.valid(), "{}", tree.name());
+    }
+}
+
+#[test]
+fn signed_zeros_are_two_keys() {
+    for mut tree in all_trees() {
+        assert!(tree.insert(0.0), "{}", tree.name());
+        assert!(!tree.contains(-0.0), "{}: -0.0 found in a tree holding only 0.0", tree.name());
+        assert!(tree.insert(-0.0), "{}: -0.0 refused next to 0.0", tree.name());
+        assert_eq!(bits(&tree.keys()), bits(&[-0.0, 0.0]), "{}", tree.name());
+
+        assert!(tree.delete(0.0), "{}", tree.name());
+        assert_eq!(bits(&tree.keys()), bits(&[-0.0]), "{}: deleting 0.0 removed the wrong zero", tree.name());
+        assert!(!tree.delete(0.0), "{}", tree.name());
+        assert!(tree.delete(-0.0), "{}", tree.name());
+        assert!(tree.keys().is_empty(), "{}", tree.name());
+    }
+}
+
+#[test]
+fn ranges_respect_signed_zeros_and_nan() {
+    let mut avl: AvlTreeNode<TotalF64> = AvlTree::generate_empty_tree();
+    let mut rb = RBTree::with_comparator(TotalOrder);
+    for key in special_values() {
+        avl.insert_node(TotalF64(key));
+        rb.insert_node(key);
+    }
+    let zeros: Vec<f64> = AvlTree::range(&avl, &TotalF64(-0.0), &TotalF64(0.0)).into_iter().map(|key| key.value()).collect();
+    assert_eq!(bits(&zeros), bits(&[-0.0, 0.0]));
+    assert_eq!(bits(&rb.range(0.0, 0.0)), bits(&[0.0]));
+    // everything above +inf is a positive NaN
+    assert_eq!(bits(&rb.range(f64::INFINITY, f64::NAN)), bits(&[f64::INFINITY, f64::NAN]));
+    assert_eq!(bits(&rb.range(-f64::NAN, f64::NEG_INFINITY)), bits(&[-f64::NAN, f64::NEG_INFINITY]));
+}
+
+#[test]
+fn random_floats_match_a_sorted_set() {
+    let specials = special_values();
+    for seed in 0..10 {
+        let mut rng = Rng::new(seed);
+        let mut model = BTreeSet::new();
+        let mut trees = all_trees();
+        for _ in 0..400 {
+            // half of the keys are special values, so NaNs and zeros come up again and again
+            let key = if rng.below(2) == 0 {
+                specials[rng.below(specials.len() as u64) as usize]
+            } else {
+                f64::from_bits(rng.next_u64())
+            };
+            let insert = rng.below(3) != 0;
+            let expected = if insert { model.insert(TotalF64(key)) } else { model.remove(&TotalF64(key)) };
+            for tree in trees.iter_mut() {
+                let answer = if insert { tree.insert(key) } else { tree.delete(key) };
+                assert_eq!(answer, expected, "{}: {} {:?} for seed {}", tree.name(), if insert { "insert" } else { "delete" }, key, seed);
+            }
+        }
+        let expected: Vec<f64> = model.iter().map(|key| key.value()).collect();
+        for tree in trees.iter_mut() {
+            assert_eq!(bits(&tree.keys()), bits(&expected), "{}: wrong keys for seed {}", tree.name(), seed);
+            assert!(tree.valid(), "{}: invalid tree for seed {}", tree.name(), seed);
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use std::fs;
+use std::path::Path;
+
+use ECE522_project::fuzz::{self, FuzzTarget};
+
+// Every input `cargo run fuzz` saved to fuzz/corpus/<target> is a regression test: the tree has to
+// agree with the BTreeSet model on all of them.
+
+fn check_corpus(target: FuzzTarget) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz").join("corpus").join(target.name());
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries {
+        let file = entry.unwrap().path();
+        let data = fs::read(&file).unwrap();
+        if let Err(e) = fuzz::check(target, &data) {
+            panic!("{} failed at {}, run `cargo run fuzz {} {}`", file.display(), e, target.name(), file.display());
+        }
+    }
+}
+
+#[test]
+fn avl_corpus() {
+    check_corpus(FuzzTarget::Avl);
+}
+
+#[test]
+fn rb_corpus() {
+    check_corpus(FuzzTarget::RedBlack);
+}
+
+#[test]
+fn bst_corpus() {
+    check_corpus(FuzzTarget::Bst);
+}
@@ -0,0 +1,314 @@
+use std::collections::BTreeMap;
+
+use ECE522_project::AVL::AvlMultiset;
+use ECE522_project::BST::BstMultiset;
+use ECE522_project::RBTree::RBMultiset;
+use ECE522_project::workload::Rng;
+
+// The three multisets against a BTreeMap of counts. Every node keeps the total count of its subtree,
+// which `len`, `rank` and `select` rely on, so they are checked after every change together with the counts.
+
+// What every multiset under test has to offer
+trait Bag {
+    fn name(&self) -> &'static str;
+    fn insert(&mut self, key: i32) -> usize;
+    fn remove_one(&mut self, key: i32) -> bool;
+    fn remove_all(&mut self, key: i32) -> usize;
+    fn count(&self, key: i32) -> usize;
+    fn len(&self) -> usize;
+    fn rank(&self, key: i32) -> usize;
+    fn select(&self, index: usize) -> Option<i32>;
+    fn keys(&self) -> Vec<i32>;
+    fn entries(&self) -> Vec<(i32, usize)>;
+    fn valid(&self) -> bool;
+}
+
+impl Bag for AvlMultiset<i32> {
+    fn name(&self) -> &'static str {
+        "AVL multiset"
+    }
+
+    fn insert(&mut self, key: i32) -> usize {
+        AvlMultiset::insert(self, key)
+    }
+
+    fn remove_one(&mut self, key: i32) -> bool {
+        AvlMultiset::remove_one(self, &key)
+    }
+
+    fn remove_all(&mut self, key: i32) -> usize {
+        AvlMultiset::remove_all(self, &key)
+    }
+
+    fn count(&self, key: i32) -> usize {
+        AvlMultiset::count(self, &key)
+    }
+
+    fn len(&self) -> usize {
+        AvlMultiset::len(self)
+    }
+
+    fn rank(&self, key: i32) -> usize {
+        AvlMultiset::rank(self, &key)
+    }
+
+    fn select(&self, index: usize) -> Option<i32> {
+        AvlMultiset::select(self, index).copied()
+    }
+
+    fn keys(&self) -> Vec<i32> {
+        self.in_order_traverse().into_iter().copied().collect()
+    }
+
+    fn entries(&self) -> Vec<(i32, usize)> {
+        AvlMultiset::entries(self).into_iter().map(|(key, count)| (*key, count)).collect()
+    }
+
+    // also checks the subtree counts
+    fn valid(&self) -> bool {
+        self.validate_tree()
+    }
+}
+
+impl Bag for RBMultiset<i32> {
+    fn name(&self) -> &'static str {
+        "Red-Black multiset"
+    }
+
+    fn insert(&mut self, key: i32) -> usize {
+        RBMultiset::insert(self, key)
+    }
+
+    fn remove_one(&mut self, key: i32) -> bool {
+        RBMultiset::remove_one(self, key)
+    }
+
+    fn remove_all(&mut self, key: i32) -> usize {
+        RBMultiset::remove_all(self, key)
+    }
+
+    fn count(&self, key: i32) -> usize {
+        RBMultiset::count(self, key)
+    }
+
+    fn len(&self) -> usize {
+        RBMultiset::len(self)
+    }
+
+    fn rank(&self, key: i32) -> usize {
+        RBMultiset::rank(self, key)
+    }
+
+    fn select(&self, index: usize) -> Option<i32> {
+        RBMultiset::select(self, index)
+    }
+
+    fn keys(&self) -> Vec<i32> {
+        self.print_in_order_traversal()
+    }
+
+    fn entries(&self) -> Vec<(i32, usize)> {
+        RBMultiset::entries(self)
+    }
+
+    // also checks the subtree counts
+    fn valid(&self) -> bool {
+        self.tree().validate_tree()
+    }
+}
+
+impl Bag for BstMultiset<i32> {
+    fn name(&self) -> &'static str {
+        "binary search multiset"
+    }
+
+    fn insert(&mut self, key: i32) -> usize {
+        BstMultiset::insert(self, key)
+    }
+
+    fn remove_one(&mut self, key: i32) -> bool {
+        BstMultiset::remove_one(self, key)
+    }
+
+    fn remove_all(&mut self, key: i32) -> usize {
+        BstMultiset::remove_all(self, key)
+    }
+
+    fn count(&self, key: i32) -> usize {
+        BstMultiset::count(self, key)
+    }
+
+    fn len(&self) -> usize {
+        BstMultiset::len(self)
+    }
+
+    fn rank(&self, key: i32) -> usize {
+        BstMultiset::rank(self, key)
+    }
+
+    fn select(&self, index: usize) -> Option<i32> {
+        BstMultiset::select(self, index)
+    }
+
+    fn keys(&self) -> Vec<i32> {
+        self.in_order_traverse()
+    }
+
+    fn entries(&self) -> Vec<(i32, usize)> {
+        BstMultiset::entries(self)
+    }
+
+    // the binary search multiset has no validator, its keys have to be in order
+    fn valid(&self) -> bool {
+        self.in_order_traverse().windows(2).all(|w| w[0] <= w[1])
+    }
+}
+
+fn all_bags() -> Vec<Box<dyn Bag>> {
+    vec![Box::new(AvlMultiset::new()), Box::new(RBMultiset::new()), Box::new(BstMultiset::new())]
+}
+
+// Everything a bag answers has to match the model
+fn check(bag: &dyn Bag, model: &BTreeMap<i32, usize>, context: &str) {
+    let name = bag.name();
+    let keys: Vec<i32> = model.iter().flat_map(|(&key, &count)| std::iter::repeat_n(key, count)).collect();
+    assert!(bag.valid(), "{}: invalid tree {}", name, context);
+    assert_eq!(bag.keys(), keys, "{}: wrong keys {}", name, context);
+    assert_eq!(bag.entries(), model.iter().map(|(&key, &count)| (key, count)).collect::<Vec<_>>(), "{}: wrong entries {}", name, context);
+    assert_eq!(bag.len(), keys.len(), "{}: wrong len {}", name, context);
+    for (index, &key) in keys.iter().enumerate() {
+        assert_eq!(bag.select(index), Some(key), "{}: select({}) {}", name, index, context);
+    }
+    assert_eq!(bag.select(keys.len()), None, "{}: select past the end {}", name, context);
+    let low = keys.first().map_or(0, |key| key - 1);
+    let high = keys.last().map_or(0, |key| key + 1);
+    for key in low..=high {
+        let rank = keys.iter().filter(|&&k| k < key).count();
+        assert_eq!(bag.rank(key), rank, "{}: rank({}) {}", name, key, context);
+        assert_eq!(bag.count(key), model.get(&key).copied().unwrap_or(0), "{}: count({}) {}", name, key, context);
+    }
+}
+
+#[test]
+fn duplicates_are_counted() {
+    for mut bag in all_bags() {
+        for (key, expected) in [(5, 1), (3, 1), (5, 2), (8, 1), (5, 3), (3, 2)] {
+            assert_eq!(bag.insert(key), expected, "{}: count after inserting {}", bag.name(), key);
+        }
+        let model = BTreeMap::from([(3, 2), (5, 3), (8, 1)]);
+        check(bag.as_ref(), &model, "after the inserts");
+        assert_eq!(bag.rank(5), 2, "{}", bag.name());
+        assert_eq!(bag.rank(6), 5, "{}", bag.name());
+        assert_eq!(bag.select(4), Some(5), "{}", bag.name());
+        assert_eq!(bag.select(5), Some(8), "{}", bag.name());
+    }
+}
+
+#[test]
+fn remove_one_leaves_the_other_copies() {
+    for mut bag in all_bags() {
+        for key in [4, 2, 6, 4, 4] {
+            bag.insert(key);
+        }
+        assert!(bag.remove_one(4), "{}", bag.name());
+        check(bag.as_ref(), &BTreeMap::from([(2, 1), (4, 2), (6, 1)]), "after one remove_one");
+        assert!(bag.remove_one(4), "{}", bag.name());
+        assert!(bag.remove_one(4), "{}", bag.name());
+        check(bag.as_ref(), &BTreeMap::from([(2, 1), (6, 1)]), "after removing every copy one by one");
+        assert!(!bag.remove_one(4), "{}: removed a key that is gone", bag.name());
+        assert!(!bag.remove_one(7), "{}: removed a key that was never there", bag.name());
+    }
+}
+
+#[test]
+fn remove_all_drops_every_copy() {
+    for mut bag in all_bags() {
+        for key in [4, 2, 6, 4, 4, 2] {
+            bag.insert(key);
+        }
+        assert_eq!(bag.remove_all(4), 3, "{}", bag.name());
+        check(bag.as_ref(), &BTreeMap::from([(2, 2), (6, 1)]), "after remove_all");
+        assert_eq!(bag.remove_all(4), 0, "{}", bag.name());
+        assert_eq!(bag.remove_all(2), 2, "{}", bag.name());
+        assert_eq!(bag.remove_all(6), 1, "{}", bag.name());
+        check(bag.as_ref(), &BTreeMap::new(), "after removing everything");
+    }
+}
+
+// Removing a node with two children moves the key of its neighbour up, and its count has to move along
+#[test]
+fn a_two_children_delete_moves_the_count_of_the_replacement() {
+    for remove in [remove_one_by_one, remove_all_at_once] {
+        for mut bag in all_bags() {
+            // 20 becomes the root of every tree, with 10 and 30 below it
+            for key in [20, 10, 30] {
+                bag.insert(key);
+            }
+            for key in [10, 10, 30, 30, 30] {
+                bag.insert(key);
+            }
+            remove(bag.as_mut(), 20);
+            check(bag.as_ref(), &BTreeMap::from([(10, 3), (30, 4)]), "after removing the root");
+            assert_eq!(bag.rank(30), 3, "{}", bag.name());
+        }
+    }
+}
+
+fn remove_one_by_one(bag: &mut dyn Bag, key: i32) {
+    assert!(bag.remove_one(key), "{}", bag.name());
+}
+
+fn remove_all_at_once(bag: &mut dyn Bag, key: i32) {
+    assert_eq!(bag.remove_all(key), 1, "{}", bag.name());
+}
+
+#[test]
+fn random_operations_match_a_map_of_counts() {
+    for seed in 0..40 {
+        let mut rng = Rng::new(seed);
+        let mut model = BTreeMap::new();
+        let mut bags = all_bags();
+        for step in 0..300 {
+            // few different keys, so most of them are stored more than once
+            let key = rng.below(30) as i32;
+            match rng.below(4) {
+                0 | 1 => {
+                    let count = model.entry(key).or_insert(0);
+                    *count += 1;
+                    for bag in bags.iter_mut() {
+                        assert_eq!(bag.insert(key), *count, "{}: insert {} for seed {}", bag.name(), key, seed);
+                    }
+                }
+                2 => {
+                    let expected = match model.get_mut(&key) {
+                        None => false,
+                        Some(count) => {
+                            *count -= 1;
+                            if *count == 0 {
+                                model.remove(&key);
+                            }
+                            true
+                        }
+                    };
+                    for bag in bags.iter_mut() {
+                        assert_eq!(bag.remove_one(key), expected, "{}: remove_one {} for seed {}", bag.name(), key, seed);
+                    }
+                }
+                _ => {
+                    let expected = model.remove(&key).unwrap_or(0);
+                    for bag in bags.iter_mut() {
+                        assert_eq!(bag.remove_all(key), expected, "{}: remove_all {} for seed {}", bag.name(), key, seed);
+                    }
+                }
+            }
+            if step % 10 == 0 {
+                for bag in bags.iter() {
+                    check(bag.as_ref(), &model, &format!("at step {} for seed {}", step, seed));
+                }
+            }
+        }
+        for bag in bags.iter() {
+            check(bag.as_ref(), &model, &format!("at the end for seed {}", seed));
+        }
+    }
+}